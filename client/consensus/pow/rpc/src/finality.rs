@@ -0,0 +1,50 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::Block as BlockT;
+use std::{marker::PhantomData, sync::Arc};
+
+#[rpc(client, server)]
+pub trait PowFinalityApi<Hash> {
+	/// Returns the hash of the last block finalized by checkpoints or confirmations.
+	#[method(name = "pow_finalizedHead")]
+	fn finalized_head(&self) -> RpcResult<Hash>;
+}
+
+pub struct PowFinality<B, C> {
+	client: Arc<C>,
+	_marker: PhantomData<B>,
+}
+
+impl<B, C> PowFinality<B, C> {
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: PhantomData }
+	}
+}
+
+impl<B, C> PowFinalityApiServer<B::Hash> for PowFinality<B, C>
+where
+	B: BlockT,
+	C: HeaderBackend<B> + Send + Sync + 'static,
+{
+	fn finalized_head(&self) -> RpcResult<B::Hash> {
+		Ok(self.client.info().finalized_hash)
+	}
+}
@@ -0,0 +1,256 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Mining jobs shared by the RPC and the Stratum server.
+
+use nc_consensus_pow::{MiningHandle, MiningMetadata, Version, WorkHasher};
+use np_consensus_pow::{check_hash, WorkSeal};
+use parity_scale_codec::Encode;
+use parking_lot::Mutex;
+use sp_core::U256;
+use std::{
+	collections::{HashSet, VecDeque},
+	sync::Arc,
+	time::{Duration, Instant},
+};
+
+/// Window over which the hash rate is estimated from accepted shares.
+const HASH_RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Maximum number of shares accepted for a job, bounding the nonces kept to detect duplicates.
+const MAX_SHARES_PER_JOB: usize = 1 << 16;
+
+/// Maximum number of shares the hash rate is estimated from.
+const MAX_RECENT_SHARES: usize = 1 << 16;
+
+/// A mining job.
+#[derive(Clone)]
+pub struct Job<Hash> {
+	/// Identifier of the job, to be given back with the shares.
+	pub id: u64,
+	/// Mining metadata of the job.
+	pub metadata: MiningMetadata<Hash, U256>,
+	/// Difficulty a share must meet to be accepted.
+	pub share_difficulty: U256,
+}
+
+/// Result of an accepted share.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Share {
+	/// The share does not meet the block difficulty.
+	Accepted,
+	/// The share sealed a block, which was imported if `true`.
+	Sealed(bool),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ShareError {
+	#[error("Job not found")]
+	Stale,
+	#[error("Duplicate share")]
+	Duplicate,
+	#[error("Low difficulty share")]
+	LowDifficulty,
+	#[error("Too many shares for the job")]
+	TooManyShares,
+}
+
+struct State<Hash, Context> {
+	version: Option<Version>,
+	job: Option<(Job<Hash>, Arc<Context>)>,
+	next_id: u64,
+	nonces: HashSet<u64>,
+	shares: VecDeque<(Instant, U256)>,
+}
+
+impl<Hash, Context> State<Hash, Context> {
+	fn prune_shares(&mut self) {
+		while self.shares.front().is_some_and(|(at, _)| at.elapsed() > HASH_RATE_WINDOW) {
+			self.shares.pop_front();
+		}
+	}
+}
+
+/// Jobs of the current mining build of the worker.
+pub struct Jobs<W: MiningHandle> {
+	worker: W,
+	hasher: W::Hasher,
+	share_difficulty: U256,
+	state: Mutex<State<W::Hash, <W::Hasher as WorkHasher>::Context>>,
+}
+
+impl<W: MiningHandle> Jobs<W> {
+	/// Create jobs accepting shares meeting `share_difficulty`, or the block difficulty if it is
+	/// lower.
+	pub fn new(worker: W, share_difficulty: U256) -> Self {
+		Self {
+			hasher: worker.hasher(),
+			worker,
+			share_difficulty,
+			state: Mutex::new(State {
+				version: None,
+				job: None,
+				next_id: 0,
+				nonces: Default::default(),
+				shares: Default::default(),
+			}),
+		}
+	}
+
+	/// Get the current job, issuing a new one if the mining build changed. `None` if there is
+	/// nothing to mine.
+	pub fn current(&self) -> Option<Job<W::Hash>> {
+		let version = self.worker.version();
+		let mut state = self.state.lock();
+
+		if state.version != Some(version) {
+			let id = state.next_id;
+			state.version = Some(version);
+			state.next_id += 1;
+			state.nonces.clear();
+			state.job = self.worker.metadata().map(|metadata| {
+				let context = self.hasher.context(metadata.pre_hash.as_ref());
+				let share_difficulty = self.share_difficulty.min(metadata.difficulty);
+				(Job { id, metadata, share_difficulty }, Arc::new(context))
+			});
+		}
+
+		state.job.as_ref().map(|(job, _)| job.clone())
+	}
+
+	/// Submit a share of the job `job_id`, submitting it to the worker if it seals a block.
+	pub async fn submit(&self, job_id: u64, nonce: u64) -> Result<Share, ShareError> {
+		let (job, context) = match &self.state.lock().job {
+			Some((job, context)) if job.id == job_id => (job.clone(), context.clone()),
+			_ => return Err(ShareError::Stale),
+		};
+
+		let work = self.hasher.hash(&context, nonce);
+		if !check_hash(&work, job.share_difficulty) {
+			return Err(ShareError::LowDifficulty)
+		}
+
+		{
+			let mut state = self.state.lock();
+			if state.job.as_ref().is_none_or(|(job, _)| job.id != job_id) {
+				return Err(ShareError::Stale)
+			}
+			if state.nonces.contains(&nonce) {
+				return Err(ShareError::Duplicate)
+			}
+			if state.nonces.len() >= MAX_SHARES_PER_JOB {
+				return Err(ShareError::TooManyShares)
+			}
+			state.nonces.insert(nonce);
+			state.prune_shares();
+			if state.shares.len() >= MAX_RECENT_SHARES {
+				state.shares.pop_front();
+			}
+			state.shares.push_back((Instant::now(), job.share_difficulty));
+		}
+
+		if check_hash(&work, job.metadata.difficulty) {
+			let imported = self.worker.submit(WorkSeal { nonce, work }.encode()).await;
+			Ok(Share::Sealed(imported))
+		} else {
+			Ok(Share::Accepted)
+		}
+	}
+
+	/// Hashes per second estimated from the shares accepted in the last minute.
+	pub fn hash_rate(&self) -> u64 {
+		let mut state = self.state.lock();
+		state.prune_shares();
+
+		let hashes = state.shares.iter().fold(U256::zero(), |sum, (_, d)| sum.saturating_add(*d));
+		(hashes / HASH_RATE_WINDOW.as_secs()).try_into().unwrap_or(u64::MAX)
+	}
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+	use super::*;
+	use futures::{executor::block_on, Future};
+	use nc_consensus_pow::{Seal, Sha3};
+	use sp_core::H256;
+
+	#[derive(Clone, Default)]
+	pub(crate) struct TestWorker {
+		pub seals: Arc<Mutex<Vec<Seal>>>,
+	}
+
+	impl MiningHandle for TestWorker {
+		type Hash = H256;
+		type Hasher = Sha3;
+
+		fn hasher(&self) -> Sha3 {
+			Sha3
+		}
+
+		fn version(&self) -> Version {
+			Version::default()
+		}
+
+		fn metadata(&self) -> Option<MiningMetadata<H256, U256>> {
+			Some(MiningMetadata {
+				best_hash: H256::zero(),
+				pre_hash: H256::repeat_byte(1),
+				pre_digest: None,
+				difficulty: U256::MAX,
+			})
+		}
+
+		fn submit(&self, seal: Seal) -> impl Future<Output = bool> + Send {
+			self.seals.lock().push(seal);
+			futures::future::ready(true)
+		}
+	}
+
+	#[test]
+	fn shares_are_validated() {
+		let worker = TestWorker::default();
+		let jobs = Jobs::new(worker.clone(), U256::from(2));
+		let job = jobs.current().unwrap();
+		assert_eq!(job.share_difficulty, U256::from(2));
+
+		let context = Sha3.context(job.metadata.pre_hash.as_ref());
+		let meets = |nonce: &u64| check_hash(&Sha3.hash(&context, *nonce), job.share_difficulty);
+		let low = (0..).find(|nonce| !meets(nonce)).unwrap();
+		let share = (0..).find(meets).unwrap();
+
+		assert_eq!(block_on(jobs.submit(job.id + 1, share)), Err(ShareError::Stale));
+		assert_eq!(block_on(jobs.submit(job.id, low)), Err(ShareError::LowDifficulty));
+		assert_eq!(block_on(jobs.submit(job.id, share)), Ok(Share::Accepted));
+		assert_eq!(block_on(jobs.submit(job.id, share)), Err(ShareError::Duplicate));
+		assert!(worker.seals.lock().is_empty());
+	}
+
+	#[test]
+	fn shares_per_job_are_bounded() {
+		let jobs = Jobs::new(TestWorker::default(), U256::one());
+		let job = jobs.current().unwrap();
+
+		let shares = MAX_SHARES_PER_JOB as u64;
+		for nonce in 0..shares {
+			assert_eq!(block_on(jobs.submit(job.id, nonce)), Ok(Share::Accepted));
+		}
+		assert_eq!(block_on(jobs.submit(job.id, shares)), Err(ShareError::TooManyShares));
+		assert_eq!(block_on(jobs.submit(job.id, 0)), Err(ShareError::Duplicate));
+		assert_eq!(jobs.state.lock().shares.len(), MAX_RECENT_SHARES);
+	}
+}
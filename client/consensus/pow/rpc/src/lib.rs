@@ -0,0 +1,47 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Mining interfaces for external miners and pools.
+//!
+//! Both the JSON-RPC module and the Stratum server hand out jobs built from the mining metadata
+//! of the PoW worker, accept shares meeting the pool share difficulty and submit the shares
+//! sealing a block to the worker. `pow_finalizedHead` returns the last block finalized by the
+//! checkpoints or confirmations of the PoW finality worker.
+
+pub mod finality;
+pub mod job;
+pub mod pow;
+#[cfg(feature = "stratum")]
+pub mod stratum;
+
+use jsonrpsee::types::{
+	error::{INTERNAL_ERROR_CODE, INVALID_REQUEST_CODE},
+	ErrorObject, ErrorObjectOwned,
+};
+
+pub fn error<T: ToString>(code: i32, message: T) -> ErrorObjectOwned {
+	ErrorObject::owned(code, message.to_string(), None::<()>)
+}
+
+pub fn request_error<T: ToString>(message: T) -> ErrorObjectOwned {
+	error(INVALID_REQUEST_CODE, message)
+}
+
+pub fn internal_error<T: ToString>(message: T) -> ErrorObjectOwned {
+	error(INTERNAL_ERROR_CODE, message)
+}
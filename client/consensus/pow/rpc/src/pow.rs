@@ -0,0 +1,106 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+	job::{Job, Jobs, Share},
+	request_error,
+};
+use jsonrpsee::{
+	core::{async_trait, RpcResult},
+	proc_macros::rpc,
+};
+use nc_consensus_pow::{HashRate, MiningHandle};
+use serde::{Deserialize, Serialize};
+use sp_core::{Bytes, U256};
+use std::sync::Arc;
+
+/// Work to be mined.
+///
+/// A share is the nonce whose work hash, `H(pre_hash ++ nonce.to_le_bytes())`, meets the share
+/// difficulty.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Work {
+	pub job_id: u64,
+	pub best_hash: Bytes,
+	pub pre_hash: Bytes,
+	pub pre_digest: Option<Bytes>,
+	pub difficulty: U256,
+	pub share_difficulty: U256,
+}
+
+impl<Hash: AsRef<[u8]>> From<Job<Hash>> for Work {
+	fn from(job: Job<Hash>) -> Self {
+		Self {
+			job_id: job.id,
+			best_hash: job.metadata.best_hash.as_ref().to_vec().into(),
+			pre_hash: job.metadata.pre_hash.as_ref().to_vec().into(),
+			pre_digest: job.metadata.pre_digest.map(Into::into),
+			difficulty: job.metadata.difficulty,
+			share_difficulty: job.share_difficulty,
+		}
+	}
+}
+
+#[rpc(client, server)]
+#[async_trait]
+pub trait PowApi {
+	#[method(name = "pow_getWork")]
+	async fn get_work(&self) -> RpcResult<Work>;
+
+	/// Returns true if the share sealed a block which was imported.
+	#[method(name = "pow_submitWork")]
+	async fn submit_work(&self, job_id: u64, nonce: u64) -> RpcResult<bool>;
+
+	#[method(name = "pow_hashrate")]
+	async fn hashrate(&self) -> RpcResult<u64>;
+}
+
+pub struct Pow<W: MiningHandle> {
+	jobs: Arc<Jobs<W>>,
+	local_hash_rate: Option<HashRate>,
+}
+
+impl<W: MiningHandle> Pow<W> {
+	/// Create the RPC, adding the hash rate of the local miner to the one of the shares.
+	pub fn new(jobs: Arc<Jobs<W>>, local_hash_rate: Option<HashRate>) -> Self {
+		Self { jobs, local_hash_rate }
+	}
+}
+
+#[async_trait]
+impl<W: MiningHandle> PowApiServer for Pow<W> {
+	async fn get_work(&self) -> RpcResult<Work> {
+		self.jobs
+			.current()
+			.map(Into::into)
+			.ok_or_else(|| request_error("No work available"))
+	}
+
+	async fn submit_work(&self, job_id: u64, nonce: u64) -> RpcResult<bool> {
+		match self.jobs.submit(job_id, nonce).await.map_err(request_error)? {
+			Share::Accepted => Ok(false),
+			Share::Sealed(imported) => Ok(imported),
+		}
+	}
+
+	async fn hashrate(&self) -> RpcResult<u64> {
+		let local = self.local_hash_rate.as_ref().map_or(0, HashRate::get);
+		Ok(self.jobs.hash_rate().saturating_add(local))
+	}
+}
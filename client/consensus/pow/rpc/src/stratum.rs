@@ -0,0 +1,351 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Stratum v1 server.
+//!
+//! Messages are newline-delimited JSON-RPC 1.0 objects:
+//!
+//! - `mining.subscribe` returns `[subscriptions, extranonce1, 4]`. `extranonce1` is the hex of the
+//!   4-byte session id, which must be the upper 32 bits of the nonces of the session.
+//! - `mining.authorize` `[worker, password]` returns `true` for any worker.
+//! - `mining.submit` `[worker, job_id, nonce]`, with the job id and the 8-byte nonce in hex,
+//!   returns `true` if the share is accepted.
+//! - `mining.set_difficulty` `[share_difficulty]` is sent with the difficulty in hex, before the
+//!   first job and whenever it changes.
+//! - `mining.notify` `[job_id, pre_hash, best_hash, difficulty, true]` is sent on every new job.
+//!
+//! Errors are `[code, message, null]`, using the codes of the Stratum v1 pools. A session is
+//! closed on a message longer than `MAX_LINE_LENGTH`.
+
+use crate::job::{Job, Jobs, ShareError};
+use futures::StreamExt;
+use log::*;
+use nc_consensus_pow::MiningHandle;
+use serde_json::{json, Value};
+use sp_core::U256;
+use std::{io, net::SocketAddr, sync::Arc, time::Duration};
+use tokio::{
+	io::AsyncWriteExt,
+	net::{TcpListener, TcpStream},
+	sync::watch,
+};
+use tokio_util::codec::{FramedRead, LinesCodec, LinesCodecError};
+
+const LOG_TARGET: &str = "stratum";
+
+/// Interval at which the worker is polled for a new job.
+const JOB_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Maximum length of a message, in bytes.
+const MAX_LINE_LENGTH: usize = 4096;
+
+const OTHER: i64 = 20;
+const JOB_NOT_FOUND: i64 = 21;
+const DUPLICATE_SHARE: i64 = 22;
+const LOW_DIFFICULTY_SHARE: i64 = 23;
+const UNAUTHORIZED_WORKER: i64 = 24;
+const NOT_SUBSCRIBED: i64 = 25;
+
+/// Serve Stratum v1 miners on `addr` with the given jobs.
+pub async fn run_stratum<W: MiningHandle>(addr: SocketAddr, jobs: Arc<Jobs<W>>) {
+	let listener = match TcpListener::bind(addr).await {
+		Ok(listener) => listener,
+		Err(e) => {
+			error!(target: LOG_TARGET, "Unable to bind Stratum server to {}: {}", addr, e);
+			return
+		},
+	};
+	info!(target: LOG_TARGET, "⛏️  Stratum server listening on {}", addr);
+
+	accept_miners(listener, jobs).await
+}
+
+async fn accept_miners<W: MiningHandle>(listener: TcpListener, jobs: Arc<Jobs<W>>) {
+	let (job_tx, job_rx) = watch::channel(None::<Job<W::Hash>>);
+	let mut interval = tokio::time::interval(JOB_POLL_INTERVAL);
+	let mut session = 0u32;
+
+	loop {
+		tokio::select! {
+			accepted = listener.accept() => match accepted {
+				Ok((stream, peer)) => {
+					debug!(target: LOG_TARGET, "Accepted miner {} as session {}", peer, session);
+					let jobs = jobs.clone();
+					let job_rx = job_rx.clone();
+					tokio::spawn(async move {
+						if let Err(e) = serve(stream, session, jobs, job_rx).await {
+							debug!(target: LOG_TARGET, "Session with {} closed: {}", peer, e);
+						}
+					});
+					session = session.wrapping_add(1);
+				},
+				Err(e) => warn!(target: LOG_TARGET, "Unable to accept miner: {}", e),
+			},
+			_ = interval.tick() => {
+				let job = jobs.current();
+				job_tx.send_if_modified(|current| {
+					let modified = current.as_ref().map(|j| j.id) != job.as_ref().map(|j| j.id);
+					if modified {
+						*current = job;
+					}
+					modified
+				});
+			},
+		}
+	}
+}
+
+async fn serve<W: MiningHandle>(
+	stream: TcpStream,
+	session: u32,
+	jobs: Arc<Jobs<W>>,
+	mut job_rx: watch::Receiver<Option<Job<W::Hash>>>,
+) -> io::Result<()> {
+	let (reader, mut writer) = stream.into_split();
+	let mut lines = FramedRead::new(reader, LinesCodec::new_with_max_length(MAX_LINE_LENGTH));
+	let mut subscribed = false;
+	let mut authorized = false;
+	let mut share_difficulty = None;
+
+	loop {
+		let mut messages = Vec::new();
+
+		tokio::select! {
+			line = lines.next() => {
+				let line = match line {
+					Some(Ok(line)) => line,
+					Some(Err(LinesCodecError::Io(e))) => return Err(e),
+					Some(Err(LinesCodecError::MaxLineLengthExceeded)) =>
+						return Err(io::Error::new(io::ErrorKind::InvalidData, "Message too long")),
+					None => return Ok(()),
+				};
+				let request = match serde_json::from_str::<Value>(&line) {
+					Ok(request) => request,
+					Err(_) => {
+						messages.push(error_response(Value::Null, OTHER, "Parse error"));
+						Value::Null
+					},
+				};
+				let id = request["id"].clone();
+				let params = request["params"].as_array().cloned().unwrap_or_default();
+
+				match request["method"].as_str() {
+					None => (),
+					Some("mining.subscribe") => {
+						subscribed = true;
+						let subscription = format!("{:08x}", session);
+						messages.push(json!({
+							"id": id,
+							"result": [
+								[
+									["mining.set_difficulty", subscription],
+									["mining.notify", subscription],
+								],
+								subscription,
+								4,
+							],
+							"error": null,
+						}));
+						job_rx.mark_changed();
+					},
+					Some("mining.authorize") => {
+						authorized = true;
+						messages.push(json!({ "id": id, "result": true, "error": null }));
+					},
+					Some("mining.submit") if !subscribed =>
+						messages.push(error_response(id, NOT_SUBSCRIBED, "Not subscribed")),
+					Some("mining.submit") if !authorized => messages.push(error_response(
+						id,
+						UNAUTHORIZED_WORKER,
+						"Unauthorized worker",
+					)),
+					Some("mining.submit") => {
+						let share = params.get(1).zip(params.get(2)).and_then(|(job_id, nonce)| {
+							Some((
+								u64::from_str_radix(job_id.as_str()?, 16).ok()?,
+								u64::from_str_radix(nonce.as_str()?, 16).ok()?,
+							))
+						});
+
+						let response = match share {
+							Some((_, nonce)) if (nonce >> 32) as u32 != session =>
+								error_response(id, OTHER, "Nonce out of session range"),
+							Some((job_id, nonce)) => match jobs.submit(job_id, nonce).await {
+								Ok(_) => json!({ "id": id, "result": true, "error": null }),
+								Err(e) => error_response(id, share_error_code(e), e),
+							},
+							None => error_response(id, OTHER, "Invalid params"),
+						};
+						messages.push(response);
+					},
+					_ => messages.push(error_response(id, OTHER, "Unknown method")),
+				}
+			},
+			changed = job_rx.changed(), if subscribed => {
+				if changed.is_err() {
+					return Ok(())
+				}
+
+				let job = job_rx.borrow_and_update().clone();
+				if let Some(job) = job {
+					if share_difficulty != Some(job.share_difficulty) {
+						share_difficulty = Some(job.share_difficulty);
+						messages.push(json!({
+							"id": null,
+							"method": "mining.set_difficulty",
+							"params": [hex(job.share_difficulty)],
+						}));
+					}
+					messages.push(json!({
+						"id": null,
+						"method": "mining.notify",
+						"params": [
+							format!("{:x}", job.id),
+							hex_bytes(job.metadata.pre_hash.as_ref()),
+							hex_bytes(job.metadata.best_hash.as_ref()),
+							hex(job.metadata.difficulty),
+							true,
+						],
+					}));
+				}
+			},
+		}
+
+		for message in messages {
+			writer.write_all(format!("{}\n", message).as_bytes()).await?;
+		}
+	}
+}
+
+fn error_response(id: Value, code: i64, message: impl ToString) -> Value {
+	json!({ "id": id, "result": null, "error": [code, message.to_string(), null] })
+}
+
+fn share_error_code(e: ShareError) -> i64 {
+	match e {
+		ShareError::Stale => JOB_NOT_FOUND,
+		ShareError::Duplicate => DUPLICATE_SHARE,
+		ShareError::LowDifficulty => LOW_DIFFICULTY_SHARE,
+		ShareError::TooManyShares => OTHER,
+	}
+}
+
+fn hex(value: U256) -> String {
+	format!("{:x}", value)
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+	bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::job::tests::TestWorker;
+	use nc_consensus_pow::{Sha3, WorkHasher};
+	use np_consensus_pow::check_hash;
+	use tokio::io::{AsyncBufReadExt, BufReader, Lines};
+
+	type Reader = Lines<BufReader<tokio::net::tcp::OwnedReadHalf>>;
+
+	async fn request(writer: &mut tokio::net::tcp::OwnedWriteHalf, request: Value) {
+		writer.write_all(format!("{}\n", request).as_bytes()).await.unwrap();
+	}
+
+	async fn message(lines: &mut Reader) -> Value {
+		let line = tokio::time::timeout(Duration::from_secs(5), lines.next_line())
+			.await
+			.expect("message received in time")
+			.unwrap()
+			.expect("session open");
+		serde_json::from_str(&line).unwrap()
+	}
+
+	/// Reads messages until the response to `id`, returning the notifications read before it.
+	async fn response(lines: &mut Reader, id: u64) -> (Value, Vec<Value>) {
+		let mut notifications = Vec::new();
+		loop {
+			let message = message(lines).await;
+			if message["id"] == id {
+				return (message, notifications)
+			}
+			notifications.push(message);
+		}
+	}
+
+	#[tokio::test]
+	async fn subscribe_notify_and_submit_work() {
+		let worker = TestWorker::default();
+		let jobs = Arc::new(Jobs::new(worker.clone(), U256::from(2)));
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let addr = listener.local_addr().unwrap();
+		tokio::spawn(accept_miners(listener, jobs));
+
+		let (reader, mut writer) = TcpStream::connect(addr).await.unwrap().into_split();
+		let mut lines = BufReader::new(reader).lines();
+
+		request(&mut writer, json!({ "id": 1, "method": "mining.subscribe", "params": [] })).await;
+		let (subscribed, _) = response(&mut lines, 1).await;
+		assert_eq!(subscribed["result"][1], "00000000");
+		assert_eq!(subscribed["result"][2], 4);
+
+		let mut notifications = Vec::new();
+		while !notifications.iter().any(|n: &Value| n["method"] == "mining.notify") {
+			notifications.push(message(&mut lines).await);
+		}
+		assert_eq!(notifications[0]["method"], "mining.set_difficulty");
+		assert_eq!(notifications[0]["params"][0], "2");
+		let notify = &notifications[1]["params"];
+		let job_id = notify[0].as_str().unwrap().to_string();
+		assert_eq!(notify[1], hex_bytes(&[1; 32]));
+
+		request(&mut writer, json!({ "id": 2, "method": "mining.submit", "params": [] })).await;
+		assert_eq!(response(&mut lines, 2).await.0["error"][0], UNAUTHORIZED_WORKER);
+
+		request(&mut writer, json!({ "id": 3, "method": "mining.authorize", "params": ["w", ""] }))
+			.await;
+		assert_eq!(response(&mut lines, 3).await.0["result"], true);
+
+		let context = Sha3.context(&[1; 32]);
+		let nonce = (0..).find(|n| check_hash(&Sha3.hash(&context, *n), U256::from(2))).unwrap();
+		let submit = |id: u64, nonce: u64| {
+			json!({
+				"id": id,
+				"method": "mining.submit",
+				"params": ["w", job_id, format!("{:016x}", nonce)],
+			})
+		};
+
+		request(&mut writer, submit(4, nonce)).await;
+		let (accepted, _) = response(&mut lines, 4).await;
+		assert_eq!(accepted["result"], true);
+		assert_eq!(accepted["error"], Value::Null);
+
+		request(&mut writer, submit(5, nonce)).await;
+		assert_eq!(response(&mut lines, 5).await.0["error"][0], DUPLICATE_SHARE);
+
+		request(&mut writer, submit(6, nonce | 1 << 32)).await;
+		assert_eq!(response(&mut lines, 6).await.0["error"][0], OTHER);
+
+		// A message longer than the limit closes the session.
+		writer.write_all(&[b'x'; MAX_LINE_LENGTH + 1]).await.unwrap();
+		writer.write_all(b"\n").await.unwrap();
+		let closed = tokio::time::timeout(Duration::from_secs(5), lines.next_line()).await;
+		assert!(matches!(closed, Ok(Ok(None)) | Ok(Err(_))));
+	}
+}
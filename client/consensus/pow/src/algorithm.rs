@@ -0,0 +1,332 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Reference PoW algorithms sealing blocks with a [`WorkSeal`].
+
+use np_consensus_pow::{check_hash, DifficultyApi, WorkSeal};
+use parity_scale_codec::{DecodeAll, Encode};
+use sp_api::ProvideRuntimeApi;
+use sp_core::{H256, U256};
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+use std::{marker::PhantomData, ops::Range, sync::Arc};
+
+use crate::{Error, MiningMetadata, PowAlgorithm, Seal};
+
+/// Number of nonces hashed between two calls of the `on_round` callback of [`mine`].
+pub const MINING_ROUND: u64 = 1024;
+
+/// Hash function computing the work of a nonce over a pre-hash.
+pub trait WorkHasher: Clone + Send + Sync {
+	/// State shared by all nonces of a pre-hash.
+	type Context: Send + Sync;
+
+	/// Prepares the context to hash nonces over `pre_hash`.
+	fn context(&self, pre_hash: &[u8]) -> Self::Context;
+
+	/// Computes the work hash of `nonce`.
+	fn hash(&self, context: &Self::Context, nonce: u64) -> H256;
+}
+
+/// SHA3-256 (FIPS 202) work hasher.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha3;
+
+impl WorkHasher for Sha3 {
+	type Context = sha3::Sha3_256;
+
+	fn context(&self, pre_hash: &[u8]) -> Self::Context {
+		use sha3::Digest;
+		sha3::Sha3_256::new_with_prefix(pre_hash)
+	}
+
+	fn hash(&self, context: &Self::Context, nonce: u64) -> H256 {
+		use sha3::Digest;
+		H256(context.clone().chain_update(nonce.to_le_bytes()).finalize().into())
+	}
+}
+
+/// Blake3 work hasher.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Blake3;
+
+impl WorkHasher for Blake3 {
+	type Context = blake3::Hasher;
+
+	fn context(&self, pre_hash: &[u8]) -> Self::Context {
+		let mut hasher = blake3::Hasher::new();
+		hasher.update(pre_hash);
+		hasher
+	}
+
+	fn hash(&self, context: &Self::Context, nonce: u64) -> H256 {
+		let mut hasher = context.clone();
+		hasher.update(&nonce.to_le_bytes());
+		H256(*hasher.finalize().as_bytes())
+	}
+}
+
+#[cfg(feature = "randomx-light")]
+pub use randomx_light::RandomXLight;
+
+#[cfg(feature = "randomx-light")]
+mod randomx_light {
+	use super::*;
+	use parking_lot::Mutex;
+
+	/// Number of 32-byte items in the dataset (4 MiB).
+	const DATASET_ITEMS: usize = 1 << 17;
+	/// Number of dataset reads per nonce.
+	const ROUNDS: usize = 64;
+
+	type Dataset = Arc<Vec<[u8; 32]>>;
+
+	/// Memory-hard work hasher in the spirit of the RandomX light mode.
+	///
+	/// Each pre-hash seeds a 4 MiB dataset of chained Blake3 hashes, and every nonce mixes
+	/// 64 items read at data-dependent positions. It is not compatible with RandomX.
+	/// The last dataset is cached and shared between clones.
+	#[derive(Clone, Default)]
+	pub struct RandomXLight {
+		cache: Arc<Mutex<Option<(Vec<u8>, Dataset)>>>,
+	}
+
+	impl RandomXLight {
+		fn dataset(&self, pre_hash: &[u8]) -> Dataset {
+			let mut cache = self.cache.lock();
+			if let Some((key, dataset)) = cache.as_ref() {
+				if key == pre_hash {
+					return dataset.clone()
+				}
+			}
+
+			let mut dataset = Vec::with_capacity(DATASET_ITEMS);
+			let mut item = *blake3::hash(pre_hash).as_bytes();
+			for _ in 0..DATASET_ITEMS {
+				dataset.push(item);
+				item = *blake3::hash(&item).as_bytes();
+			}
+			let dataset = Arc::new(dataset);
+			*cache = Some((pre_hash.to_vec(), dataset.clone()));
+
+			dataset
+		}
+	}
+
+	impl WorkHasher for RandomXLight {
+		type Context = (blake3::Hasher, Dataset);
+
+		fn context(&self, pre_hash: &[u8]) -> Self::Context {
+			(Blake3.context(pre_hash), self.dataset(pre_hash))
+		}
+
+		fn hash(&self, (hasher, dataset): &Self::Context, nonce: u64) -> H256 {
+			let mut hasher = hasher.clone();
+			hasher.update(&nonce.to_le_bytes());
+			let mut work = *hasher.finalize().as_bytes();
+
+			for _ in 0..ROUNDS {
+				let index = u64::from_le_bytes(work[..8].try_into().expect("8 bytes; qed"));
+				let item = &dataset[index as usize % dataset.len()];
+				let mut hasher = blake3::Hasher::new();
+				hasher.update(&work);
+				hasher.update(item);
+				work = *hasher.finalize().as_bytes();
+			}
+
+			H256(work)
+		}
+	}
+}
+
+/// Searches `nonces` for a seal of `pre_hash` meeting `difficulty`.
+///
+/// `on_round` is called with the number of nonces hashed since its last call, every
+/// [`MINING_ROUND`] nonces and when the search ends. Mining stops if it returns `false`.
+pub fn mine<H: WorkHasher>(
+	hasher: &H,
+	pre_hash: &[u8],
+	difficulty: U256,
+	nonces: Range<u64>,
+	mut on_round: impl FnMut(u64) -> bool,
+) -> Option<WorkSeal> {
+	let context = hasher.context(pre_hash);
+	let mut hashes = 0;
+
+	for nonce in nonces {
+		let work = hasher.hash(&context, nonce);
+		hashes += 1;
+
+		if check_hash(&work, difficulty) {
+			on_round(hashes);
+			return Some(WorkSeal { nonce, work })
+		}
+
+		if hashes == MINING_ROUND {
+			if !on_round(hashes) {
+				return None
+			}
+			hashes = 0;
+		}
+	}
+
+	on_round(hashes);
+	None
+}
+
+/// PoW algorithm hashing nonces with `H`, targeting the difficulty of the runtime
+/// [`DifficultyApi`].
+pub struct HashAlgorithm<B, C, H> {
+	client: Arc<C>,
+	hasher: H,
+	_marker: PhantomData<B>,
+}
+
+/// SHA3-256 PoW algorithm.
+pub type Sha3Algorithm<B, C> = HashAlgorithm<B, C, Sha3>;
+
+/// Blake3 PoW algorithm.
+pub type Blake3Algorithm<B, C> = HashAlgorithm<B, C, Blake3>;
+
+/// Memory-hard PoW algorithm.
+#[cfg(feature = "randomx-light")]
+pub type RandomXLightAlgorithm<B, C> = HashAlgorithm<B, C, RandomXLight>;
+
+impl<B, C, H: Clone> Clone for HashAlgorithm<B, C, H> {
+	fn clone(&self) -> Self {
+		Self { client: self.client.clone(), hasher: self.hasher.clone(), _marker: PhantomData }
+	}
+}
+
+impl<B, C, H> HashAlgorithm<B, C, H>
+where
+	B: BlockT,
+	H: WorkHasher,
+{
+	pub fn new(client: Arc<C>, hasher: H) -> Self {
+		Self { client, hasher, _marker: PhantomData }
+	}
+
+	pub fn hasher(&self) -> &H {
+		&self.hasher
+	}
+
+	/// Searches `nonces` for a seal of the mining build, to be submitted to the `PowWorker`.
+	///
+	/// See [`mine`] for `on_round`.
+	pub fn mine(
+		&self,
+		metadata: &MiningMetadata<B::Hash, U256>,
+		nonces: Range<u64>,
+		on_round: impl FnMut(u64) -> bool,
+	) -> Option<Seal> {
+		mine(&self.hasher, metadata.pre_hash.as_ref(), metadata.difficulty, nonces, on_round)
+			.map(|seal| seal.encode())
+	}
+
+	/// Decodes the seal, returning it if its work hash is the one of its nonce.
+	fn check_seal(&self, pre_hash: &B::Hash, seal: &Seal) -> Option<WorkSeal> {
+		let seal = WorkSeal::decode_all(&mut &seal[..]).ok()?;
+		let context = self.hasher.context(pre_hash.as_ref());
+
+		(self.hasher.hash(&context, seal.nonce) == seal.work).then_some(seal)
+	}
+}
+
+impl<B, C, H> PowAlgorithm<B> for HashAlgorithm<B, C, H>
+where
+	B: BlockT,
+	C: ProvideRuntimeApi<B>,
+	C::Api: DifficultyApi<B, U256>,
+	H: WorkHasher,
+{
+	type Difficulty = U256;
+
+	fn difficulty(&self, parent: B::Hash) -> Result<Self::Difficulty, Error<B>> {
+		self.client
+			.runtime_api()
+			.difficulty(parent)
+			.map_err(|e| Error::Client(e.into()))
+	}
+
+	fn preliminary_verify(
+		&self,
+		pre_hash: &B::Hash,
+		seal: &Seal,
+	) -> Result<Option<bool>, Error<B>> {
+		Ok(Some(self.check_seal(pre_hash, seal).is_some()))
+	}
+
+	fn verify(
+		&self,
+		_parent: &BlockId<B>,
+		pre_hash: &B::Hash,
+		_pre_digest: Option<&[u8]>,
+		seal: &Seal,
+		difficulty: Self::Difficulty,
+	) -> Result<Option<Self::Difficulty>, Error<B>> {
+		Ok(self
+			.check_seal(pre_hash, seal)
+			.filter(|seal| check_hash(&seal.work, difficulty))
+			.map(|_| difficulty))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn mine_and_check<H: WorkHasher>(hasher: H) {
+		let pre_hash = H256::repeat_byte(0x42);
+		let difficulty = U256::from(16);
+
+		let seal = mine(&hasher, pre_hash.as_ref(), difficulty, 0..u64::MAX, |_| true)
+			.expect("a seal is found at a low difficulty");
+		let context = hasher.context(pre_hash.as_ref());
+
+		assert_eq!(hasher.hash(&context, seal.nonce), seal.work);
+		assert!(check_hash(&seal.work, difficulty));
+	}
+
+	#[test]
+	fn sha3_mining_works() {
+		mine_and_check(Sha3);
+	}
+
+	#[test]
+	fn blake3_mining_works() {
+		mine_and_check(Blake3);
+	}
+
+	#[cfg(feature = "randomx-light")]
+	#[test]
+	fn randomx_light_mining_works() {
+		mine_and_check(RandomXLight::default());
+	}
+
+	#[test]
+	fn mining_stops_on_round() {
+		let mut hashes = 0;
+		let seal = mine(&Sha3, &[0u8; 32], U256::MAX, 0..u64::MAX, |n| {
+			hashes += n;
+			false
+		});
+
+		assert_eq!(seal, None);
+		assert_eq!(hashes, MINING_ROUND);
+	}
+}
@@ -56,3 +56,27 @@ where
 	let key = block_weight_key(block_hash);
 	block_weight.using_encoded(|s| write_aux(&[(key, s)]))
 }
+
+pub fn block_difficulty_key<H: Encode>(block_hash: H) -> Vec<u8> {
+	(b"block_difficulty", block_hash).encode()
+}
+
+/// Load the target difficulty the block was verified against, if it was imported.
+pub fn load_block_difficulty<B, H, D>(backend: &B, block_hash: &H) -> Result<Option<D>>
+where
+	B: AuxStore,
+	H: Encode,
+	D: Decode,
+{
+	load_decode(backend, &block_difficulty_key(block_hash)[..])
+}
+
+pub(crate) fn write_block_difficulty<H, D, F, R>(block_hash: H, difficulty: D, write_aux: F) -> R
+where
+	H: Encode,
+	D: Encode,
+	F: FnOnce(&[(Vec<u8>, &[u8])]) -> R,
+{
+	let key = block_difficulty_key(block_hash);
+	difficulty.using_encoded(|s| write_aux(&[(key, s)]))
+}
@@ -20,6 +20,7 @@ use nc_consensus::PreDigestProvider;
 use np_consensus_pow::POW_ENGINE_ID;
 use parity_scale_codec::{Decode, Encode};
 use sp_blockchain::Result;
+use sp_core::H256;
 use sp_runtime::DigestItem;
 use std::ops::Deref;
 
@@ -61,3 +62,62 @@ where
 		Ok(vec![DigestItem::PreRuntime(POW_ENGINE_ID, self.author.encode())])
 	}
 }
+
+/// Pre-digest provider committing to the reward shares of the block along with the author.
+///
+/// `shares_hash` returns the hash of the shares provided to the rewards inherent of the block. The
+/// pre-digest decodes as a `PreDigest<AccountId, Option<H256>>`, whose hash is checked by hashing
+/// the shares of the rewards inherent of the block again.
+pub struct SharesPreDigestProvider<AccountId, F> {
+	author: AccountId,
+	shares_hash: F,
+}
+
+impl<AccountId, F> SharesPreDigestProvider<AccountId, F> {
+	pub fn new(author: AccountId, shares_hash: F) -> Self {
+		Self { author, shares_hash }
+	}
+}
+
+#[async_trait::async_trait]
+impl<AccountId, F> PreDigestProvider for SharesPreDigestProvider<AccountId, F>
+where
+	AccountId: Clone + Encode + Send + Sync,
+	F: Fn() -> Option<H256> + Send + Sync,
+{
+	async fn pre_digest(&self, _best_hash: &[u8]) -> Result<Vec<DigestItem>> {
+		let pre_digest = PreDigest::new(self.author.clone(), (self.shares_hash)());
+		Ok(vec![DigestItem::PreRuntime(POW_ENGINE_ID, pre_digest.encode())])
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use parking_lot::Mutex;
+	use std::sync::Arc;
+
+	#[test]
+	fn shares_pre_digest_commits_to_the_last_shares() {
+		let shares = Arc::new(Mutex::new(None));
+		let provider = SharesPreDigestProvider::new(1u64, {
+			let shares = shares.clone();
+			move || *shares.lock()
+		});
+		let pre_digest = || {
+			let digest = futures::executor::block_on(provider.pre_digest(&[])).unwrap();
+			let [DigestItem::PreRuntime(POW_ENGINE_ID, data)] = &digest[..] else {
+				panic!("expected a single PoW pre-runtime digest");
+			};
+			PreDigest::<u64, Option<H256>>::decode(&mut &data[..]).unwrap()
+		};
+
+		assert_eq!(*pre_digest(), None);
+
+		let hash = H256::repeat_byte(1);
+		*shares.lock() = Some(hash);
+		let pre_digest = pre_digest();
+		assert_eq!(pre_digest.author(), &1);
+		assert_eq!(*pre_digest, Some(hash));
+	}
+}
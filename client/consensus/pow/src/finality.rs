@@ -0,0 +1,446 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Checkpoints and probabilistic finality of PoW chains.
+//!
+//! Checkpoints pin the hash of the canonical block at given heights. [`PowBlockImport`] rejects
+//! the blocks not matching them, and [`finality_worker`] finalizes them once they are on the
+//! best chain, so that no reorg can go below them.
+//!
+//! [`PowBlockImport`]: crate::PowBlockImport
+
+use crate::{aux_schema, Error, LOG_TARGET};
+use futures::StreamExt;
+use log::*;
+use parity_scale_codec::Decode;
+use sc_chain_spec::{ChainSpecExtension, GetExtension};
+use sc_client_api::{backend::AuxStore, Backend, BlockchainEvents, Finalizer};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::{
+	Block as BlockT, Header as HeaderT, One, SaturatedConversion, Saturating,
+};
+use std::{collections::BTreeMap, sync::Arc};
+
+/// Hashes of the canonical blocks at given heights.
+///
+/// Hard-coded checkpoints can be extended with the ones of the chain spec, where they are
+/// deserialized from a map of block numbers to hashes. See [`CheckpointsExtension`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoints<Hash>(BTreeMap<u64, Hash>);
+
+impl<Hash: PartialEq> Checkpoints<Hash> {
+	pub fn new(checkpoints: impl IntoIterator<Item = (u64, Hash)>) -> Self {
+		Self(checkpoints.into_iter().collect())
+	}
+
+	/// Returns true if there is no checkpoint at `number`, or it is `hash`.
+	pub fn check(&self, number: u64, hash: &Hash) -> bool {
+		self.0.get(&number).map_or(true, |checkpoint| checkpoint == hash)
+	}
+
+	/// Returns the highest checkpoint at or below `number`.
+	pub fn last(&self, number: u64) -> Option<(u64, &Hash)> {
+		self.0.range(..=number).next_back().map(|(number, hash)| (*number, hash))
+	}
+}
+
+impl<Hash: Clone + 'static> Checkpoints<Hash> {
+	/// Returns the checkpoints of the chain spec extensions, if they have any.
+	///
+	/// The extensions are either a [`CheckpointsExtension`], or a struct deriving
+	/// `ChainSpecExtension` with a `Checkpoints` field.
+	pub fn from_extensions(extensions: &dyn GetExtension) -> Option<Self> {
+		sc_chain_spec::get_extension::<Self>(extensions).cloned()
+	}
+}
+
+impl<Hash> Extend<(u64, Hash)> for Checkpoints<Hash> {
+	fn extend<I: IntoIterator<Item = (u64, Hash)>>(&mut self, iter: I) {
+		self.0.extend(iter)
+	}
+}
+
+/// Chain spec extension carrying the checkpoints of the chain.
+///
+/// ```json
+/// "checkpoints": { "100000": "0x…" }
+/// ```
+#[derive(Clone, Debug, Default, Serialize, Deserialize, ChainSpecExtension)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointsExtension<Hash>
+where
+	Hash: Clone + Serialize + DeserializeOwned + 'static,
+{
+	pub checkpoints: Checkpoints<Hash>,
+}
+
+/// Checks that the block `hash` with `header` matches the checkpoints, and does not revert
+/// finalized blocks.
+///
+/// A block at or below the last finalized one is accepted only if it is finalized itself.
+/// Others must descend from the last finalized block.
+pub(crate) fn check_import<B, C>(
+	client: &C,
+	checkpoints: &Checkpoints<B::Hash>,
+	header: &B::Header,
+	hash: B::Hash,
+) -> Result<(), Error<B>>
+where
+	B: BlockT,
+	C: HeaderBackend<B>,
+{
+	let number = *header.number();
+	if !checkpoints.check(number.saturated_into(), &hash) {
+		return Err(Error::CheckpointMismatch(hash))
+	}
+
+	let info = client.info();
+	if number <= info.finalized_number {
+		return match client.hash(number).map_err(Error::Client)? {
+			Some(canonical) if canonical == hash => Ok(()),
+			_ => Err(Error::RevertsFinalized(hash)),
+		}
+	}
+
+	// Canonical blocks above the finalized one descend from it.
+	let mut ancestor = *header.parent_hash();
+	let mut ancestor_number = number - One::one();
+	while ancestor_number > info.finalized_number {
+		if client.hash(ancestor_number).map_err(Error::Client)? == Some(ancestor) {
+			return Ok(())
+		}
+		let parent = client.header(ancestor).map_err(Error::Client)?.ok_or_else(|| {
+			Error::Client(sp_blockchain::Error::UnknownBlock(format!("{:?}", ancestor)))
+		})?;
+		ancestor = *parent.parent_hash();
+		ancestor_number -= One::one();
+	}
+
+	if ancestor == info.finalized_hash {
+		Ok(())
+	} else {
+		Err(Error::RevertsFinalized(hash))
+	}
+}
+
+/// Rule finalizing the best chain blocks with `confirmations` blocks on top of them, totaling at
+/// least `min_work`.
+#[derive(Clone, Copy, Debug)]
+pub struct ProbabilisticFinality<Difficulty> {
+	pub confirmations: u32,
+	pub min_work: Difficulty,
+}
+
+/// Returns the block to be finalized, if any, from the checkpoints and the optional
+/// probabilistic finality rule.
+pub fn finality_target<B, C, D>(
+	client: &C,
+	checkpoints: &Checkpoints<B::Hash>,
+	probabilistic: Option<&ProbabilisticFinality<D>>,
+) -> sp_blockchain::Result<Option<B::Hash>>
+where
+	B: BlockT,
+	C: HeaderBackend<B> + AuxStore,
+	D: Decode + Default + Copy + Saturating + Ord,
+{
+	let info = client.info();
+	let best_number = info.best_number.saturated_into::<u64>();
+	let finalized_number = info.finalized_number.saturated_into::<u64>();
+	let mut target = None;
+
+	if let Some((number, hash)) = checkpoints.last(best_number) {
+		if number > finalized_number && client.hash(number.saturated_into())? == Some(*hash) {
+			target = Some((number, *hash));
+		}
+	}
+
+	if let Some(rule) = probabilistic {
+		let number = best_number.saturating_sub(rule.confirmations.into());
+		if number > finalized_number && target.map_or(true, |(target, _)| number > target) {
+			if let Some(hash) = client.hash(number.saturated_into())? {
+				let mut weight = aux_schema::load_block_weight::<_, _, D>(client, &hash)?;
+				weight += rule.min_work;
+				if weight <= aux_schema::load_block_weight(client, &info.best_hash)? {
+					target = Some((number, hash));
+				}
+			}
+		}
+	}
+
+	Ok(target.map(|(_, hash)| hash))
+}
+
+/// Finalizes blocks following [`finality_target`] as the best chain grows.
+pub async fn finality_worker<B, BE, C, D>(
+	client: Arc<C>,
+	checkpoints: Checkpoints<B::Hash>,
+	probabilistic: Option<ProbabilisticFinality<D>>,
+) where
+	B: BlockT,
+	BE: Backend<B>,
+	C: BlockchainEvents<B> + HeaderBackend<B> + Finalizer<B, BE> + AuxStore,
+	D: Decode + Default + Copy + Saturating + Ord,
+{
+	let mut imports = client.import_notification_stream();
+	while let Some(notification) = imports.next().await {
+		if !notification.is_new_best {
+			continue
+		}
+
+		match finality_target(&*client, &checkpoints, probabilistic.as_ref()) {
+			Ok(Some(hash)) =>
+				if let Err(e) = client.finalize_block(hash, None, true) {
+					warn!(target: LOG_TARGET, "Failed to finalize block {:?}: {}", hash, e);
+				},
+			Ok(None) => {},
+			Err(e) => warn!(target: LOG_TARGET, "Failed to find the block to finalize: {}", e),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use np_consensus_pow::BlockWeight;
+	use parking_lot::Mutex;
+	use sp_blockchain::{BlockStatus, Info};
+	use sp_core::H256;
+	use sp_runtime::{generic, traits::BlakeTwo256, OpaqueExtrinsic};
+	use std::collections::HashMap;
+
+	type Header = generic::Header<u64, BlakeTwo256>;
+	type Block = generic::Block<Header, OpaqueExtrinsic>;
+
+	/// Chain of headers, with the canonical one indexed by number.
+	#[derive(Default)]
+	struct MockChain {
+		headers: HashMap<H256, Header>,
+		canonical: Vec<H256>,
+		finalized: u64,
+		aux: Mutex<HashMap<Vec<u8>, Vec<u8>>>,
+	}
+
+	impl MockChain {
+		/// Canonical chain of `len` blocks on top of the genesis, each of difficulty 1.
+		fn new(len: u64) -> Self {
+			let mut chain = Self::default();
+			let genesis = chain.fork(H256::zero(), 0);
+			chain.canonical.push(genesis);
+			for _ in 0..len {
+				let hash = chain.fork(*chain.canonical.last().unwrap(), 0);
+				chain.canonical.push(hash);
+			}
+			chain
+		}
+
+		/// Adds a non-canonical child of `parent`, distinguished by `fork`.
+		fn fork(&mut self, parent: H256, fork: u8) -> H256 {
+			let number = self.headers.get(&parent).map_or(0, |parent| parent.number + 1);
+			let header = Header::new(
+				number,
+				Default::default(),
+				H256::repeat_byte(fork),
+				parent,
+				Default::default(),
+			);
+			let hash = header.hash();
+			let mut weight = BlockWeight::default();
+			weight += number;
+			aux_schema::write_block_weight(hash, weight, |insert| {
+				self.insert_aux(
+					insert.iter().map(|(k, v)| (&k[..], *v)).collect::<Vec<_>>().iter(),
+					&[],
+				)
+			})
+			.unwrap();
+			self.headers.insert(hash, header);
+			hash
+		}
+	}
+
+	impl HeaderBackend<Block> for MockChain {
+		fn header(&self, hash: H256) -> sp_blockchain::Result<Option<Header>> {
+			Ok(self.headers.get(&hash).cloned())
+		}
+
+		fn info(&self) -> Info<Block> {
+			Info {
+				best_hash: *self.canonical.last().unwrap(),
+				best_number: self.canonical.len() as u64 - 1,
+				genesis_hash: self.canonical[0],
+				finalized_hash: self.canonical[self.finalized as usize],
+				finalized_number: self.finalized,
+				finalized_state: None,
+				number_leaves: 1,
+				block_gap: None,
+			}
+		}
+
+		fn status(&self, hash: H256) -> sp_blockchain::Result<BlockStatus> {
+			Ok(match self.headers.contains_key(&hash) {
+				true => BlockStatus::InChain,
+				false => BlockStatus::Unknown,
+			})
+		}
+
+		fn number(&self, hash: H256) -> sp_blockchain::Result<Option<u64>> {
+			Ok(self.headers.get(&hash).map(|header| header.number))
+		}
+
+		fn hash(&self, number: u64) -> sp_blockchain::Result<Option<H256>> {
+			Ok(self.canonical.get(number as usize).copied())
+		}
+	}
+
+	impl AuxStore for MockChain {
+		fn insert_aux<
+			'a,
+			'b: 'a,
+			'c: 'a,
+			I: IntoIterator<Item = &'a (&'c [u8], &'c [u8])>,
+			D: IntoIterator<Item = &'a &'b [u8]>,
+		>(
+			&self,
+			insert: I,
+			delete: D,
+		) -> sp_blockchain::Result<()> {
+			let mut aux = self.aux.lock();
+			for (key, value) in insert {
+				aux.insert(key.to_vec(), value.to_vec());
+			}
+			for key in delete {
+				aux.remove(*key);
+			}
+			Ok(())
+		}
+
+		fn get_aux(&self, key: &[u8]) -> sp_blockchain::Result<Option<Vec<u8>>> {
+			Ok(self.aux.lock().get(key).cloned())
+		}
+	}
+
+	fn check(
+		chain: &MockChain,
+		checkpoints: &Checkpoints<H256>,
+		hash: H256,
+	) -> Result<(), Error<Block>> {
+		check_import::<Block, _>(chain, checkpoints, &chain.headers[&hash], hash)
+	}
+
+	#[test]
+	fn checkpoints_work() {
+		let mut checkpoints = Checkpoints::new([(10, H256::repeat_byte(1))]);
+		checkpoints.extend([(20, H256::repeat_byte(2))]);
+
+		assert!(checkpoints.check(10, &H256::repeat_byte(1)));
+		assert!(!checkpoints.check(10, &H256::repeat_byte(2)));
+		assert!(checkpoints.check(15, &H256::repeat_byte(2)));
+
+		assert_eq!(checkpoints.last(9), None);
+		assert_eq!(checkpoints.last(19), Some((10, &H256::repeat_byte(1))));
+		assert_eq!(checkpoints.last(20), Some((20, &H256::repeat_byte(2))));
+	}
+
+	#[test]
+	fn checkpoints_load_from_chain_spec_extension() {
+		let extension: CheckpointsExtension<H256> = serde_json::from_value(serde_json::json!({
+			"checkpoints": { "10": H256::repeat_byte(1) },
+		}))
+		.unwrap();
+
+		assert_eq!(
+			Checkpoints::from_extensions(&extension),
+			Some(Checkpoints::new([(10, H256::repeat_byte(1))]))
+		);
+	}
+
+	#[test]
+	fn checkpoint_mismatch_is_rejected() {
+		let mut chain = MockChain::new(3);
+		let checkpoints = Checkpoints::new([(2, chain.canonical[2])]);
+		let fork = chain.fork(chain.canonical[1], 1);
+
+		assert!(matches!(
+			check(&chain, &checkpoints, fork),
+			Err(Error::CheckpointMismatch(h)) if h == fork
+		));
+		assert!(check(&chain, &checkpoints, chain.canonical[2]).is_ok());
+	}
+
+	#[test]
+	fn blocks_not_descending_from_finalized_are_rejected() {
+		let mut chain = MockChain::new(5);
+		chain.finalized = 2;
+		let checkpoints = Checkpoints::default();
+
+		// Forks below the finalized block.
+		let stale = chain.fork(chain.canonical[1], 1);
+		assert!(matches!(
+			check(&chain, &checkpoints, stale),
+			Err(Error::RevertsFinalized(h)) if h == stale
+		));
+		let stale_child = chain.fork(stale, 1);
+		let stale_grandchild = chain.fork(stale_child, 1);
+		assert!(matches!(
+			check(&chain, &checkpoints, stale_grandchild),
+			Err(Error::RevertsFinalized(h)) if h == stale_grandchild
+		));
+
+		// Finalized blocks themselves, and forks above the finalized block.
+		assert!(check(&chain, &checkpoints, chain.canonical[1]).is_ok());
+		assert!(check(&chain, &checkpoints, chain.canonical[2]).is_ok());
+		let fork = chain.fork(chain.canonical[2], 1);
+		let fork_child = chain.fork(fork, 1);
+		assert!(check(&chain, &checkpoints, fork).is_ok());
+		assert!(check(&chain, &checkpoints, fork_child).is_ok());
+		let fork = chain.fork(chain.canonical[4], 1);
+		assert!(check(&chain, &checkpoints, fork).is_ok());
+	}
+
+	#[test]
+	fn finality_target_follows_confirmations_and_min_work() {
+		let mut chain = MockChain::new(10);
+		let none = Checkpoints::default();
+		let rule = |min_work| ProbabilisticFinality { confirmations: 3, min_work };
+
+		assert_eq!(finality_target::<Block, _, u64>(&chain, &none, None).unwrap(), None);
+		assert_eq!(
+			finality_target::<Block, _, u64>(&chain, &none, Some(&rule(3))).unwrap(),
+			Some(chain.canonical[7])
+		);
+		// Not enough work on top of the confirmations.
+		assert_eq!(finality_target::<Block, _, u64>(&chain, &none, Some(&rule(4))).unwrap(), None);
+
+		// Checkpoints on the best chain are finalized ahead of the probabilistic rule.
+		let checkpoints = Checkpoints::new([(8, chain.canonical[8])]);
+		assert_eq!(
+			finality_target::<Block, _, u64>(&chain, &checkpoints, Some(&rule(3))).unwrap(),
+			Some(chain.canonical[8])
+		);
+		let checkpoints = Checkpoints::new([(5, chain.canonical[5])]);
+		assert_eq!(
+			finality_target::<Block, _, u64>(&chain, &checkpoints, Some(&rule(3))).unwrap(),
+			Some(chain.canonical[7])
+		);
+
+		// Nothing at or below the finalized block.
+		chain.finalized = 7;
+		assert_eq!(finality_target::<Block, _, u64>(&chain, &none, Some(&rule(3))).unwrap(), None);
+	}
+}
@@ -31,6 +31,31 @@
 //! mining on a standalone thread. Finally, when a seal is found, call
 //! [`MiningHandle::submit`] to build the block.
 //!
+//! Reference algorithms hashing nonces with SHA3-256, Blake3 or, with the `randomx-light`
+//! feature, a memory-hard function are provided by [`HashAlgorithm`], along with the CPU
+//! mining loop [`mine`]. [`start_miner`] runs that loop on multiple threads against the
+//! worker handle.
+//!
+//! Blocks may include the sealed headers of recent stale blocks as uncles, found by
+//! [`UnclesInherentDataProvider`]. The verifier always checks their seals with [`check_uncles`]
+//! for runtimes exposing [`UnclesApi`], as the runtime pays their miners. The check is
+//! skipped for the blocks imported without execution checks, whose uncles are trusted along with
+//! their state.
+//!
+//! [`UnclesApi`]: np_consensus_pow::UnclesApi
+//!
+//! With no slots to check timestamps against, [`TimestampCheck`] requires the timestamp of a
+//! block to exceed the median time past and to be at most `max_future_drift` ahead of the local
+//! time, for runtimes exposing [`TimestampValidationApi`]. The [`import_queue`] always runs it.
+//!
+//! [`TimestampValidationApi`]: np_consensus_pow::TimestampValidationApi
+//!
+//! Nothing is finalized by the fork choice on total difficulty alone. [`finality_worker`]
+//! finalizes the [`Checkpoints`] given to the block import, and optionally the blocks buried
+//! under enough confirmations following [`ProbabilisticFinality`]. The block import rejects the
+//! blocks not descending from the last finalized one. Chain specs can carry checkpoints in a
+//! [`CheckpointsExtension`].
+//!
 //! The auxiliary storage for PoW engine only stores the total difficulty.
 //! For other storage requirements for particular PoW algorithm (such as
 //! the actual difficulty for each particular blocks), you can take a client
@@ -39,25 +64,35 @@
 //! as the storage, but it is not recommended as it won't work well with light
 //! clients.
 
+mod algorithm;
 mod aux_schema;
 mod digests;
+mod finality;
+mod miner;
+mod timestamp;
+mod uncles;
 mod worker;
 
+pub use algorithm::*;
 pub use aux_schema::*;
 pub use digests::*;
+pub use finality::*;
+pub use miner::*;
+pub use timestamp::*;
+pub use uncles::*;
 pub use worker::*;
 
 use futures::{Future, StreamExt};
 use log::*;
 use nc_consensus::PreDigestProvider;
-use np_consensus_pow::{Seal, POW_ENGINE_ID};
+use np_consensus_pow::{Seal, TimestampValidationApi, UnclesApi, POW_ENGINE_ID};
 use parity_scale_codec::{Decode, Encode};
 use sc_client_api::{self, backend::AuxStore, BlockOf, BlockchainEvents};
 use sc_consensus::{
 	BasicQueue, BlockCheckParams, BlockImport, BlockImportParams, BoxJustificationImport,
 	ForkChoiceStrategy, ImportResult, Verifier,
 };
-use sp_api::ProvideRuntimeApi;
+use sp_api::{ApiExt, ProvideRuntimeApi};
 use sp_block_builder::BlockBuilder as BlockBuilderApi;
 use sp_blockchain::HeaderBackend;
 use sp_consensus::{
@@ -81,6 +116,16 @@ pub enum Error<B: BlockT> {
 	HeaderBadSeal(B::Hash),
 	#[error("Header {0:?} is unsealed")]
 	HeaderUnsealed(B::Hash),
+	#[error("Uncle {0:?} has a bad seal")]
+	UncleBadSeal(B::Hash),
+	#[error("Timestamp {0} is not after the median time past {1}")]
+	TimestampBeforeMedianTimePast(u64, u64),
+	#[error("Timestamp {0} is too far in the future")]
+	TimestampInFuture(u64),
+	#[error("Block {0:?} does not match the checkpoint at its height")]
+	CheckpointMismatch(B::Hash),
+	#[error("Block {0:?} would revert finalized blocks")]
+	RevertsFinalized(B::Hash),
 	#[error("Preliminary verification failed")]
 	PreliminaryVerificationFailed,
 	#[error("Creating inherents failed: {0}")]
@@ -172,12 +217,32 @@ pub trait PowAlgorithm<B: BlockT> {
 	}
 }
 
+/// Additional check of a block, run by [`PowVerifier`] along with its inherents and uncles.
+pub trait BlockCheck<B: BlockT>: Send + Sync {
+	/// Check `block` against the state of its parent `at_hash`.
+	fn check(&self, block: &B, at_hash: B::Hash) -> Result<(), Error<B>>;
+}
+
+impl<B: BlockT> BlockCheck<B> for () {
+	fn check(&self, _block: &B, _at_hash: B::Hash) -> Result<(), Error<B>> {
+		Ok(())
+	}
+}
+
+impl<B: BlockT, X: BlockCheck<B>, Y: BlockCheck<B>> BlockCheck<B> for (X, Y) {
+	fn check(&self, block: &B, at_hash: B::Hash) -> Result<(), Error<B>> {
+		self.0.check(block, at_hash)?;
+		self.1.check(block, at_hash)
+	}
+}
+
 /// A block importer for PoW.
-pub struct PowBlockImport<B, I, C, SC, A> {
+pub struct PowBlockImport<B: BlockT, I, C, SC, A> {
 	inner: I,
 	client: Arc<C>,
 	select_chain: SC,
 	algorithm: A,
+	checkpoints: Checkpoints<B::Hash>,
 	_marker: PhantomData<B>,
 }
 
@@ -188,6 +253,7 @@ impl<B: BlockT, I: Clone, C, SC: Clone, A: Clone> Clone for PowBlockImport<B, I,
 			client: self.client.clone(),
 			select_chain: self.select_chain.clone(),
 			algorithm: self.algorithm.clone(),
+			checkpoints: self.checkpoints.clone(),
 			_marker: Default::default(),
 		}
 	}
@@ -204,7 +270,20 @@ where
 {
 	/// Create a new block import suitable to be used in PoW
 	pub fn new(inner: I, client: Arc<C>, select_chain: SC, algorithm: A) -> Self {
-		Self { inner, client, select_chain, algorithm, _marker: Default::default() }
+		Self {
+			inner,
+			client,
+			select_chain,
+			algorithm,
+			checkpoints: Default::default(),
+			_marker: Default::default(),
+		}
+	}
+
+	/// Reject the blocks not matching `checkpoints`.
+	pub fn with_checkpoints(mut self, checkpoints: Checkpoints<B::Hash>) -> Self {
+		self.checkpoints = checkpoints;
+		self
 	}
 }
 
@@ -230,6 +309,13 @@ where
 		&self,
 		mut block: BlockImportParams<B>,
 	) -> Result<ImportResult, Self::Error> {
+		finality::check_import::<B, _>(
+			&*self.client,
+			&self.checkpoints,
+			&block.header,
+			block.post_hash(),
+		)?;
+
 		let best_header = self
 			.select_chain
 			.best_chain()
@@ -274,6 +360,11 @@ where
 				.auxiliary
 				.extend(insert.iter().map(|(k, v)| (k.to_vec(), Some(v.to_vec()))));
 		});
+		aux_schema::write_block_difficulty(block.post_hash(), target_difficulty, |insert| {
+			block
+				.auxiliary
+				.extend(insert.iter().map(|(k, v)| (k.to_vec(), Some(v.to_vec()))));
+		});
 		if block.fork_choice.is_none() {
 			block.fork_choice =
 				Some(ForkChoiceStrategy::Custom(match block_weight.cmp(&best_weight) {
@@ -303,23 +394,51 @@ where
 }
 
 /// A verifier for PoW blocks.
-pub struct PowVerifier<B: BlockT, C, A, CIDP> {
+pub struct PowVerifier<B: BlockT, C, A, CIDP, BC = ()> {
 	client: Arc<C>,
 	algorithm: A,
 	create_inherent_data_providers: CIDP,
+	checks: BC,
 	_marker: PhantomData<B>,
 }
 
 impl<B, C, A, CIDP> PowVerifier<B, C, A, CIDP>
 where
 	B: BlockT,
-	C: ProvideRuntimeApi<B>,
-	C::Api: BlockBuilderApi<B>,
+	C: ProvideRuntimeApi<B> + AuxStore,
+	C::Api: BlockBuilderApi<B> + UnclesApi<B>,
 	A: PowAlgorithm<B>,
 	CIDP: CreateInherentDataProviders<B, ()>,
 {
 	pub fn new(client: Arc<C>, algorithm: A, create_inherent_data_providers: CIDP) -> Self {
-		Self { client, algorithm, create_inherent_data_providers, _marker: Default::default() }
+		Self {
+			client,
+			algorithm,
+			create_inherent_data_providers,
+			checks: (),
+			_marker: Default::default(),
+		}
+	}
+}
+
+impl<B, C, A, CIDP, BC> PowVerifier<B, C, A, CIDP, BC>
+where
+	B: BlockT,
+	C: ProvideRuntimeApi<B> + AuxStore,
+	C::Api: BlockBuilderApi<B> + UnclesApi<B>,
+	A: PowAlgorithm<B>,
+	CIDP: CreateInherentDataProviders<B, ()>,
+	BC: BlockCheck<B>,
+{
+	/// Run `checks` on the blocks whose execution is checked, besides the uncle check.
+	pub fn with_checks<BC2: BlockCheck<B>>(self, checks: BC2) -> PowVerifier<B, C, A, CIDP, BC2> {
+		PowVerifier {
+			client: self.client,
+			algorithm: self.algorithm,
+			create_inherent_data_providers: self.create_inherent_data_providers,
+			checks,
+			_marker: Default::default(),
+		}
 	}
 
 	fn check_header(&self, mut header: B::Header) -> Result<(B::Header, DigestItem), Error<B>> {
@@ -375,14 +494,15 @@ where
 }
 
 #[async_trait::async_trait]
-impl<B, C, A, CIDP> Verifier<B> for PowVerifier<B, C, A, CIDP>
+impl<B, C, A, CIDP, BC> Verifier<B> for PowVerifier<B, C, A, CIDP, BC>
 where
 	B: BlockT,
-	C: ProvideRuntimeApi<B> + Send + Sync,
-	C::Api: BlockBuilderApi<B>,
+	C: ProvideRuntimeApi<B> + AuxStore + Send + Sync,
+	C::Api: BlockBuilderApi<B> + UnclesApi<B>,
 	A: PowAlgorithm<B> + Send + Sync,
 	A::Difficulty: 'static + Send,
 	CIDP: CreateInherentDataProviders<B, ()> + Send + Sync,
+	BC: BlockCheck<B>,
 {
 	async fn verify(
 		&self,
@@ -409,6 +529,8 @@ where
 					create_inherent_data_providers,
 				)
 				.await?;
+				check_uncles(&*self.client, &self.algorithm, &check_block, parent_hash)?;
+				self.checks.check(&check_block, parent_hash)?;
 			}
 
 			block.body = Some(check_block.deconstruct().1);
@@ -428,7 +550,7 @@ where
 pub type PowImportQueue<B> = BasicQueue<B>;
 
 /// Parameters passed to [`import_queue`].
-pub struct ImportQueueParams<'a, B, I, C, A, CIDP, S> {
+pub struct ImportQueueParams<'a, B, I, C, A, CIDP, S, BC = ()> {
 	/// The block import to use.
 	pub block_import: I,
 	/// The justification import.
@@ -439,6 +561,11 @@ pub struct ImportQueueParams<'a, B, I, C, A, CIDP, S> {
 	pub algorithm: A,
 	/// Something that can create the inherent data providers.
 	pub create_inherent_data_providers: CIDP,
+	/// How far ahead of the local time the timestamp of a block may be, checked by
+	/// [`TimestampCheck`].
+	pub max_future_drift: Duration,
+	/// Additional checks of the blocks, run after [`TimestampCheck`].
+	pub checks: BC,
 	/// The spawner to spawn background tasks.
 	pub spawner: &'a S,
 	/// The prometheus registry.
@@ -446,28 +573,33 @@ pub struct ImportQueueParams<'a, B, I, C, A, CIDP, S> {
 }
 
 /// Import queue for PoW engine.
-pub fn import_queue<B, I, C, A, CIDP, S>(
+pub fn import_queue<B, I, C, A, CIDP, S, BC>(
 	ImportQueueParams {
 		block_import,
 		justification_import,
 		client,
 		algorithm,
 		create_inherent_data_providers,
+		max_future_drift,
+		checks,
 		spawner,
 		registry,
-	}: ImportQueueParams<B, I, C, A, CIDP, S>,
+	}: ImportQueueParams<B, I, C, A, CIDP, S, BC>,
 ) -> Result<PowImportQueue<B>, sp_consensus::Error>
 where
 	B: BlockT,
 	I: BlockImport<B, Error = ConsensusError> + Send + Sync + 'static,
-	C: ProvideRuntimeApi<B> + Send + Sync + 'static,
-	C::Api: BlockBuilderApi<B>,
+	C: ProvideRuntimeApi<B> + AuxStore + Send + Sync + 'static,
+	C::Api: BlockBuilderApi<B> + UnclesApi<B> + TimestampValidationApi<B>,
 	A: PowAlgorithm<B> + Clone + Send + Sync + 'static,
 	A::Difficulty: Send,
 	CIDP: CreateInherentDataProviders<B, ()> + Send + 'static,
 	S: sp_core::traits::SpawnEssentialNamed,
+	BC: BlockCheck<B> + 'static,
 {
-	let verifier = PowVerifier::new(client, algorithm, create_inherent_data_providers);
+	let timestamp_check = TimestampCheck::new(client.clone(), max_future_drift);
+	let verifier = PowVerifier::new(client, algorithm, create_inherent_data_providers)
+		.with_checks((timestamp_check, checks));
 
 	Ok(BasicQueue::new(verifier, Box::new(block_import), justification_import, spawner, registry))
 }
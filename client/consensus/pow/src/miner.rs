@@ -0,0 +1,465 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Multi-threaded CPU miner driving a [`PowWorker`].
+
+use futures::Future;
+use log::*;
+use np_consensus_pow::DifficultyApi;
+use parity_scale_codec::Encode;
+use sc_consensus::BlockImport;
+use sp_api::ProvideRuntimeApi;
+use sp_consensus::SyncOracle;
+use sp_core::U256;
+use sp_runtime::traits::Block as BlockT;
+use std::{
+	ops::Range,
+	sync::{
+		atomic::{AtomicBool, AtomicU64, Ordering},
+		Arc,
+	},
+	thread,
+	time::{Duration, Instant},
+};
+use substrate_prometheus_endpoint::{register, Counter, Gauge, PrometheusError, Registry, U64};
+
+use crate::{
+	mine, HashAlgorithm, MiningMetadata, PowWorker, Seal, Version, WorkHasher, LOG_TARGET,
+};
+
+/// Time a mining thread sleeps while there is nothing to mine.
+const IDLE_INTERVAL: Duration = Duration::from_millis(100);
+/// Interval at which the hash rate is measured.
+const HASH_RATE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Handle to the mining build of a [`PowWorker`] used by the [`Miner`].
+pub trait MiningHandle: Clone + Send + Sync + 'static {
+	/// Block hash.
+	type Hash: AsRef<[u8]> + Clone + Send + Sync + 'static;
+	/// Hasher of the PoW algorithm the seals are verified with.
+	type Hasher: WorkHasher + 'static;
+
+	/// Get the hasher seals must be searched with.
+	fn hasher(&self) -> Self::Hasher;
+
+	/// Get the version of the mining build.
+	fn version(&self) -> Version;
+
+	/// Get a copy of the current mining metadata, if available.
+	fn metadata(&self) -> Option<MiningMetadata<Self::Hash, U256>>;
+
+	/// Submit a mined seal. The future resolves once the block is imported, to true if the
+	/// submission is successful. Mining threads block on it, so it must not need their progress.
+	fn submit(&self, seal: Seal) -> impl Future<Output = bool> + Send;
+}
+
+impl<Block, C, H, L, Proof, I> MiningHandle
+	for PowWorker<Block, HashAlgorithm<Block, C, H>, L, Proof, I>
+where
+	Block: BlockT,
+	C: ProvideRuntimeApi<Block> + Send + Sync + 'static,
+	C::Api: DifficultyApi<Block, U256>,
+	H: WorkHasher + 'static,
+	L: sc_consensus::JustificationSyncLink<Block> + 'static,
+	Proof: Send + Sync + 'static,
+	I: BlockImport<Block> + Send + Sync + 'static,
+{
+	type Hash = Block::Hash;
+	type Hasher = H;
+
+	fn hasher(&self) -> H {
+		self.algorithm().hasher().clone()
+	}
+
+	fn version(&self) -> Version {
+		PowWorker::version(self)
+	}
+
+	fn metadata(&self) -> Option<MiningMetadata<Self::Hash, U256>> {
+		PowWorker::metadata(self)
+	}
+
+	fn submit(&self, seal: Seal) -> impl Future<Output = bool> + Send {
+		PowWorker::submit(self, seal)
+	}
+}
+
+#[derive(Clone)]
+struct Metrics {
+	hashes: Counter<U64>,
+	hash_rate: Gauge<U64>,
+	blocks: Counter<U64>,
+}
+
+impl Metrics {
+	fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			hashes: register(
+				Counter::new(
+					"noir_pow_miner_hashes_total",
+					"Number of nonces hashed by the miner",
+				)?,
+				registry,
+			)?,
+			hash_rate: register(
+				Gauge::new("noir_pow_miner_hash_rate", "Hash rate of the miner per second")?,
+				registry,
+			)?,
+			blocks: register(
+				Counter::new("noir_pow_miner_blocks_total", "Number of blocks mined by the miner")?,
+				registry,
+			)?,
+		})
+	}
+}
+
+/// Shared handle to the hash rate of a [`Miner`].
+#[derive(Clone, Default)]
+pub struct HashRate(Arc<AtomicU64>);
+
+impl HashRate {
+	/// Hashes per second, measured over the last few seconds.
+	pub fn get(&self) -> u64 {
+		self.0.load(Ordering::Relaxed)
+	}
+
+	fn set(&self, hash_rate: u64) {
+		self.0.store(hash_rate, Ordering::Relaxed);
+	}
+}
+
+#[derive(Default)]
+struct Shared {
+	stop: AtomicBool,
+	hashes: AtomicU64,
+	hash_rate: HashRate,
+}
+
+/// Parameters passed to [`start_miner`].
+pub struct MinerParams<'a, W, SO> {
+	/// The handle to the mining build, giving the hasher of its PoW algorithm.
+	pub worker: W,
+	/// The sync oracle that can give us the current sync status.
+	pub sync_oracle: SO,
+	/// Number of mining threads.
+	pub threads: usize,
+	/// The prometheus registry.
+	pub registry: Option<&'a Registry>,
+}
+
+/// A running miner. Mining threads are stopped when it is dropped.
+pub struct Miner {
+	shared: Arc<Shared>,
+	handles: Vec<thread::JoinHandle<()>>,
+}
+
+impl Miner {
+	/// Hashes per second, measured over the last few seconds.
+	pub fn hash_rate(&self) -> u64 {
+		self.shared.hash_rate.get()
+	}
+
+	/// Handle to the hash rate, outliving the miner.
+	pub fn hash_rate_handle(&self) -> HashRate {
+		self.shared.hash_rate.clone()
+	}
+
+	/// Stop the miner and wait for its threads to exit.
+	pub fn stop(mut self) {
+		self.shared.stop.store(true, Ordering::Relaxed);
+		for handle in std::mem::take(&mut self.handles) {
+			let _ = handle.join();
+		}
+	}
+}
+
+impl Drop for Miner {
+	fn drop(&mut self) {
+		self.shared.stop.store(true, Ordering::Relaxed);
+	}
+}
+
+/// Start mining on `threads` threads, each searching its own range of nonces.
+///
+/// Threads restart whenever the version of the worker changes, and pause while the node is
+/// major syncing. Fails if the metrics cannot be registered or a thread cannot be spawned.
+pub fn start_miner<W, SO>(
+	MinerParams { worker, sync_oracle, threads, registry }: MinerParams<W, SO>,
+) -> Result<Miner, PrometheusError>
+where
+	W: MiningHandle,
+	SO: SyncOracle + Clone + Send + Sync + 'static,
+{
+	let metrics = registry.map(Metrics::register).transpose()?;
+	let shared = Arc::new(Shared::default());
+	let threads = threads.max(1);
+	let hasher = worker.hasher();
+
+	let mut handles = (0..threads)
+		.map(|index| {
+			let worker = worker.clone();
+			let hasher = hasher.clone();
+			let sync_oracle = sync_oracle.clone();
+			let shared = shared.clone();
+			let metrics = metrics.clone();
+			let nonces = nonce_range(index, threads);
+
+			thread::Builder::new()
+				.name(format!("pow-miner-{}", index))
+				.spawn(move || mining_thread(worker, hasher, sync_oracle, nonces, shared, metrics))
+		})
+		.collect::<Result<Vec<_>, _>>()?;
+
+	let monitor = {
+		let shared = shared.clone();
+		thread::Builder::new()
+			.name("pow-miner-rate".into())
+			.spawn(move || hash_rate_thread(shared, metrics))?
+	};
+	handles.push(monitor);
+
+	info!(target: LOG_TARGET, "⛏️  Started mining on {} threads", threads);
+
+	Ok(Miner { shared, handles })
+}
+
+/// The range of nonces searched by the thread `index` out of `threads`.
+fn nonce_range(index: usize, threads: usize) -> Range<u64> {
+	let span = u64::MAX / threads as u64;
+	let start = span * index as u64;
+	let end = if index + 1 == threads { u64::MAX } else { start + span };
+
+	start..end
+}
+
+fn mining_thread<W, SO>(
+	worker: W,
+	hasher: W::Hasher,
+	sync_oracle: SO,
+	nonces: Range<u64>,
+	shared: Arc<Shared>,
+	metrics: Option<Metrics>,
+) where
+	W: MiningHandle,
+	SO: SyncOracle,
+{
+	while !shared.stop.load(Ordering::Relaxed) {
+		let version = worker.version();
+		let metadata = match worker.metadata() {
+			Some(metadata) if !sync_oracle.is_major_syncing() => metadata,
+			_ => {
+				thread::sleep(IDLE_INTERVAL);
+				continue
+			},
+		};
+
+		let mut on_round = |hashes: u64| {
+			shared.hashes.fetch_add(hashes, Ordering::Relaxed);
+			if let Some(metrics) = &metrics {
+				metrics.hashes.inc_by(hashes);
+			}
+
+			!shared.stop.load(Ordering::Relaxed) &&
+				worker.version() == version &&
+				!sync_oracle.is_major_syncing()
+		};
+
+		let mut remaining = nonces.clone();
+		while let Some(seal) = mine(
+			&hasher,
+			metadata.pre_hash.as_ref(),
+			metadata.difficulty,
+			remaining.clone(),
+			&mut on_round,
+		) {
+			if futures::executor::block_on(worker.submit(seal.encode())) {
+				if let Some(metrics) = &metrics {
+					metrics.blocks.inc();
+				}
+				break
+			}
+
+			// The seal was rejected, so search past its nonce rather than find it again.
+			debug!(target: LOG_TARGET, "Seal with nonce {} rejected", seal.nonce);
+			remaining.start = seal.nonce.saturating_add(1);
+		}
+
+		// The build is sealed, outdated or its range is exhausted, so wait for a new one.
+		while !shared.stop.load(Ordering::Relaxed) && worker.version() == version {
+			thread::sleep(IDLE_INTERVAL);
+		}
+	}
+}
+
+fn hash_rate_thread(shared: Arc<Shared>, metrics: Option<Metrics>) {
+	let mut last = (Instant::now(), shared.hashes.load(Ordering::Relaxed));
+
+	while !shared.stop.load(Ordering::Relaxed) {
+		thread::sleep(IDLE_INTERVAL);
+
+		let elapsed = last.0.elapsed();
+		if elapsed < HASH_RATE_INTERVAL {
+			continue
+		}
+
+		let hashes = shared.hashes.load(Ordering::Relaxed);
+		let hash_rate = ((hashes - last.1) as f64 / elapsed.as_secs_f64()) as u64;
+		shared.hash_rate.set(hash_rate);
+		if let Some(metrics) = &metrics {
+			metrics.hash_rate.set(hash_rate);
+		}
+
+		last = (Instant::now(), hashes);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Sha3;
+	use np_consensus_pow::{check_hash, WorkSeal};
+	use parity_scale_codec::Decode;
+	use parking_lot::Mutex;
+	use sp_core::H256;
+
+	#[derive(Clone, Default)]
+	struct TestWorker {
+		build: Arc<Mutex<(usize, Option<MiningMetadata<H256, U256>>)>>,
+		seals: Arc<Mutex<Vec<(H256, Seal)>>>,
+		rejected: Arc<Mutex<(usize, Vec<Seal>)>>,
+	}
+
+	impl TestWorker {
+		fn on_build(&self, pre_hash: H256) {
+			let mut build = self.build.lock();
+			let metadata = MiningMetadata {
+				best_hash: pre_hash,
+				pre_hash,
+				pre_digest: None,
+				difficulty: 1.into(),
+			};
+			*build = (build.0 + 1, Some(metadata));
+		}
+
+		fn reject_next(&self, n: usize) {
+			self.rejected.lock().0 = n;
+		}
+
+		fn wait_for_seals(&self, n: usize) -> Vec<(H256, Seal)> {
+			let deadline = Instant::now() + Duration::from_secs(10);
+			while self.seals.lock().len() < n {
+				assert!(Instant::now() < deadline, "timed out waiting for seals");
+				thread::sleep(Duration::from_millis(10));
+			}
+			self.seals.lock().clone()
+		}
+	}
+
+	impl MiningHandle for TestWorker {
+		type Hash = H256;
+		type Hasher = Sha3;
+
+		fn hasher(&self) -> Sha3 {
+			Sha3
+		}
+
+		fn version(&self) -> Version {
+			Version(self.build.lock().0)
+		}
+
+		fn metadata(&self) -> Option<MiningMetadata<H256, U256>> {
+			self.build.lock().1.clone()
+		}
+
+		fn submit(&self, seal: Seal) -> impl Future<Output = bool> + Send {
+			let mut rejected = self.rejected.lock();
+			if rejected.0 > 0 {
+				rejected.0 -= 1;
+				rejected.1.push(seal);
+				return futures::future::ready(false)
+			}
+
+			let mut build = self.build.lock();
+			let submitted = build.1.take().map(|metadata| {
+				build.0 += 1;
+				self.seals.lock().push((metadata.pre_hash, seal));
+			});
+			futures::future::ready(submitted.is_some())
+		}
+	}
+
+	#[test]
+	fn nonce_ranges_partition_nonces() {
+		assert_eq!(nonce_range(0, 1), 0..u64::MAX);
+
+		let ranges = (0..3).map(|i| nonce_range(i, 3)).collect::<Vec<_>>();
+		assert_eq!(ranges[0].start, 0);
+		assert_eq!(ranges[0].end, ranges[1].start);
+		assert_eq!(ranges[1].end, ranges[2].start);
+		assert_eq!(ranges[2].end, u64::MAX);
+	}
+
+	#[test]
+	fn miner_mines_builds() {
+		let worker = TestWorker::default();
+		let miner = start_miner(MinerParams {
+			worker: worker.clone(),
+			sync_oracle: sp_consensus::NoNetwork,
+			threads: 2,
+			registry: Some(&Registry::new()),
+		})
+		.unwrap();
+
+		worker.on_build(H256::repeat_byte(1));
+		worker.wait_for_seals(1);
+		worker.on_build(H256::repeat_byte(2));
+		let seals = worker.wait_for_seals(2);
+		miner.stop();
+
+		for (i, (pre_hash, seal)) in seals.into_iter().enumerate() {
+			let seal = WorkSeal::decode(&mut &seal[..]).unwrap();
+			let context = Sha3.context(pre_hash.as_ref());
+
+			assert_eq!(pre_hash, H256::repeat_byte(i as u8 + 1));
+			assert_eq!(Sha3.hash(&context, seal.nonce), seal.work);
+			assert!(check_hash(&seal.work, U256::one()));
+		}
+	}
+
+	#[test]
+	fn miner_searches_past_rejected_seals() {
+		let worker = TestWorker::default();
+		worker.reject_next(2);
+		let miner = start_miner(MinerParams {
+			worker: worker.clone(),
+			sync_oracle: sp_consensus::NoNetwork,
+			threads: 1,
+			registry: None,
+		})
+		.unwrap();
+
+		worker.on_build(H256::repeat_byte(1));
+		let seals = worker.wait_for_seals(1);
+		miner.stop();
+
+		let nonce = |seal: &Seal| WorkSeal::decode(&mut &seal[..]).unwrap().nonce;
+		let rejected = worker.rejected.lock().1.iter().map(nonce).collect::<Vec<_>>();
+		assert_eq!(rejected, vec![0, 1]);
+		assert_eq!(nonce(&seals[0].1), 2);
+	}
+}
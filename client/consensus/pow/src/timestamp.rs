@@ -0,0 +1,111 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Timestamps of PoW blocks.
+
+use crate::{BlockCheck, Error};
+use np_consensus_pow::TimestampValidationApi;
+use sp_api::{ApiExt, ProvideRuntimeApi};
+use sp_runtime::traits::Block as BlockT;
+use std::{
+	sync::Arc,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// [`BlockCheck`] requiring the timestamp of a block to exceed the median time past and to be at
+/// most `max_future_drift` ahead of the local time, for runtimes exposing
+/// [`TimestampValidationApi`].
+pub struct TimestampCheck<C> {
+	client: Arc<C>,
+	max_future_drift: Duration,
+}
+
+impl<C> TimestampCheck<C> {
+	pub fn new(client: Arc<C>, max_future_drift: Duration) -> Self {
+		Self { client, max_future_drift }
+	}
+}
+
+impl<B, C> BlockCheck<B> for TimestampCheck<C>
+where
+	B: BlockT,
+	C: ProvideRuntimeApi<B> + Send + Sync,
+	C::Api: TimestampValidationApi<B>,
+{
+	fn check(&self, block: &B, at_hash: B::Hash) -> Result<(), Error<B>> {
+		let api = self.client.runtime_api();
+		if !api
+			.has_api::<dyn TimestampValidationApi<B>>(at_hash)
+			.map_err(|e| Error::Client(e.into()))?
+		{
+			return Ok(())
+		}
+
+		let median_time_past =
+			api.median_time_past(at_hash).map_err(|e| Error::Client(e.into()))?;
+		let Some(timestamp) = api
+			.block_timestamp(at_hash, block.clone())
+			.map_err(|e| Error::Client(e.into()))?
+		else {
+			return Ok(())
+		};
+
+		let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+		check_timestamp(timestamp, median_time_past, now + self.max_future_drift)
+	}
+}
+
+/// Check that `timestamp` exceeds `median_time_past` and is not later than `latest`.
+fn check_timestamp<B: BlockT>(
+	timestamp: u64,
+	median_time_past: u64,
+	latest: Duration,
+) -> Result<(), Error<B>> {
+	if timestamp <= median_time_past {
+		return Err(Error::TimestampBeforeMedianTimePast(timestamp, median_time_past))
+	}
+	if u128::from(timestamp) > latest.as_millis() {
+		return Err(Error::TimestampInFuture(timestamp))
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_runtime::{generic, traits::BlakeTwo256, OpaqueExtrinsic};
+
+	type Block = generic::Block<generic::Header<u64, BlakeTwo256>, OpaqueExtrinsic>;
+
+	#[test]
+	fn check_timestamp_works() {
+		let latest = Duration::from_millis(20_000);
+
+		assert!(check_timestamp::<Block>(10_001, 10_000, latest).is_ok());
+		assert!(check_timestamp::<Block>(20_000, 10_000, latest).is_ok());
+		assert!(matches!(
+			check_timestamp::<Block>(10_000, 10_000, latest),
+			Err(Error::TimestampBeforeMedianTimePast(10_000, 10_000))
+		));
+		assert!(matches!(
+			check_timestamp::<Block>(20_001, 10_000, latest),
+			Err(Error::TimestampInFuture(20_001))
+		));
+	}
+}
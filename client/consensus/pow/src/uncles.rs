@@ -0,0 +1,273 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Uncles of PoW blocks.
+//!
+//! A block may include, through an inherent, the sealed headers of stale blocks whose parent is
+//! one of its recent ancestors. The runtime rewards their miners, while block import verifies
+//! their seals with [`check_uncles`].
+
+use crate::{aux_schema, fetch_seal, find_pre_digest, Error, PowAlgorithm};
+use np_consensus_pow::{UnclesApi, UNCLES_INHERENT_IDENTIFIER};
+use sc_client_api::AuxStore;
+use sp_api::{ApiExt, ProvideRuntimeApi};
+use sp_blockchain::Backend as BlockchainBackend;
+use sp_inherents::{InherentData, InherentIdentifier};
+use sp_runtime::{
+	generic::BlockId,
+	traits::{Block as BlockT, Header as HeaderT, Zero},
+};
+
+/// Find the headers that can be included as uncles by a child of `parent`.
+///
+/// A candidate is a child of one of the `generations` nearest ancestors of the new block, which
+/// is not an ancestor itself. Whether it was already included is left to the runtime.
+pub fn find_uncles<B, BC>(
+	blockchain: &BC,
+	parent: B::Hash,
+	generations: u32,
+) -> sp_blockchain::Result<Vec<B::Header>>
+where
+	B: BlockT,
+	BC: BlockchainBackend<B>,
+{
+	let mut uncles = Vec::new();
+	let mut child = blockchain.expect_header(parent)?;
+
+	for _ in 0..generations {
+		if child.number().is_zero() {
+			break
+		}
+
+		let ancestor = *child.parent_hash();
+		for hash in blockchain.children(ancestor)? {
+			if hash != child.hash() {
+				uncles.push(blockchain.expect_header(hash)?);
+			}
+		}
+		child = blockchain.expect_header(ancestor)?;
+	}
+
+	Ok(uncles)
+}
+
+/// Verify the seal of `uncle` against the difficulty recorded when it was imported, or the
+/// difficulty of its parent if it was not.
+pub fn verify_uncle<B, C, A>(
+	client: &C,
+	algorithm: &A,
+	mut uncle: B::Header,
+) -> Result<(), Error<B>>
+where
+	B: BlockT,
+	C: AuxStore,
+	A: PowAlgorithm<B>,
+{
+	let hash = uncle.hash();
+	let seal = fetch_seal::<B>(uncle.digest_mut().pop().as_ref(), hash)?;
+	let pre_hash = uncle.hash();
+	let pre_digest = find_pre_digest::<B>(&uncle)?;
+	let parent = *uncle.parent_hash();
+
+	let difficulty =
+		match aux_schema::load_block_difficulty(client, &hash).map_err(Error::Client)? {
+			Some(difficulty) => difficulty,
+			None => algorithm.difficulty(parent)?,
+		};
+
+	match algorithm.verify(
+		&BlockId::hash(parent),
+		&pre_hash,
+		pre_digest.as_deref(),
+		&seal,
+		difficulty,
+	)? {
+		Some(_) => Ok(()),
+		None => Err(Error::UncleBadSeal(hash)),
+	}
+}
+
+/// Verify the seals of the uncles included in `block`, for runtimes exposing [`UnclesApi`].
+///
+/// The verifier runs it on every block whose execution is checked, as the runtime pays the
+/// miners of the uncles without checking their seals. The uncles of the blocks imported without
+/// execution checks, whose state comes from a trusted source such as warp sync, are trusted along
+/// with them.
+pub fn check_uncles<B, C, A>(
+	client: &C,
+	algorithm: &A,
+	block: &B,
+	at_hash: B::Hash,
+) -> Result<(), Error<B>>
+where
+	B: BlockT,
+	C: ProvideRuntimeApi<B> + AuxStore,
+	C::Api: UnclesApi<B>,
+	A: PowAlgorithm<B>,
+{
+	let api = client.runtime_api();
+	if !api.has_api::<dyn UnclesApi<B>>(at_hash).map_err(|e| Error::Client(e.into()))? {
+		return Ok(())
+	}
+
+	let uncles = api.uncles(at_hash, block.clone()).map_err(|e| Error::Client(e.into()))?;
+	for uncle in uncles {
+		verify_uncle(client, algorithm, uncle)?;
+	}
+
+	Ok(())
+}
+
+/// Inherent data provider of the uncles found by [`find_uncles`].
+pub struct UnclesInherentDataProvider<B: BlockT> {
+	pub uncles: Vec<B::Header>,
+}
+
+impl<B: BlockT> UnclesInherentDataProvider<B> {
+	/// Create the provider with the uncles a child of `parent` can include.
+	pub fn new<BC: BlockchainBackend<B>>(
+		blockchain: &BC,
+		parent: B::Hash,
+		generations: u32,
+	) -> sp_blockchain::Result<Self> {
+		Ok(Self { uncles: find_uncles(blockchain, parent, generations)? })
+	}
+}
+
+#[async_trait::async_trait]
+impl<B: BlockT> sp_inherents::InherentDataProvider for UnclesInherentDataProvider<B> {
+	async fn provide_inherent_data(
+		&self,
+		inherent_data: &mut InherentData,
+	) -> Result<(), sp_inherents::Error> {
+		inherent_data.put_data(UNCLES_INHERENT_IDENTIFIER, &self.uncles)
+	}
+
+	async fn try_handle_error(
+		&self,
+		_identifier: &InherentIdentifier,
+		_error: &[u8],
+	) -> Option<Result<(), sp_inherents::Error>> {
+		None
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use np_consensus_pow::{Seal, POW_ENGINE_ID};
+	use parking_lot::Mutex;
+	use sp_core::H256;
+	use sp_runtime::{
+		generic::{self, Digest, DigestItem},
+		traits::BlakeTwo256,
+		OpaqueExtrinsic,
+	};
+	use std::collections::HashMap;
+
+	type Header = generic::Header<u64, BlakeTwo256>;
+	type Block = generic::Block<Header, OpaqueExtrinsic>;
+
+	#[derive(Default)]
+	struct MemoryAux(Mutex<HashMap<Vec<u8>, Vec<u8>>>);
+
+	impl AuxStore for MemoryAux {
+		fn insert_aux<
+			'a,
+			'b: 'a,
+			'c: 'a,
+			I: IntoIterator<Item = &'a (&'c [u8], &'c [u8])>,
+			D: IntoIterator<Item = &'a &'b [u8]>,
+		>(
+			&self,
+			insert: I,
+			delete: D,
+		) -> sp_blockchain::Result<()> {
+			let mut aux = self.0.lock();
+			for (key, value) in insert {
+				aux.insert(key.to_vec(), value.to_vec());
+			}
+			for key in delete {
+				aux.remove(*key);
+			}
+			Ok(())
+		}
+
+		fn get_aux(&self, key: &[u8]) -> sp_blockchain::Result<Option<Vec<u8>>> {
+			Ok(self.0.lock().get(key).cloned())
+		}
+	}
+
+	/// Accepts the seal `b"good"` only.
+	struct MockAlgorithm;
+
+	impl PowAlgorithm<Block> for MockAlgorithm {
+		type Difficulty = u64;
+
+		fn difficulty(&self, _parent: H256) -> Result<u64, Error<Block>> {
+			Ok(1)
+		}
+
+		fn verify(
+			&self,
+			_parent: &BlockId<Block>,
+			_pre_hash: &H256,
+			_pre_digest: Option<&[u8]>,
+			seal: &Seal,
+			difficulty: u64,
+		) -> Result<Option<u64>, Error<Block>> {
+			Ok((seal == b"good").then_some(difficulty))
+		}
+	}
+
+	fn uncle(seal: &[u8]) -> Header {
+		Header::new(
+			1,
+			Default::default(),
+			Default::default(),
+			H256::repeat_byte(1),
+			Digest { logs: vec![DigestItem::Seal(POW_ENGINE_ID, seal.to_vec())] },
+		)
+	}
+
+	#[test]
+	fn uncle_with_bad_seal_is_rejected() {
+		let client = MemoryAux::default();
+
+		assert!(verify_uncle::<Block, _, _>(&client, &MockAlgorithm, uncle(b"good")).is_ok());
+
+		let bad = uncle(b"bad");
+		let hash = bad.hash();
+		assert!(matches!(
+			verify_uncle::<Block, _, _>(&client, &MockAlgorithm, bad),
+			Err(Error::UncleBadSeal(h)) if h == hash
+		));
+	}
+
+	#[test]
+	fn unsealed_uncle_is_rejected() {
+		let mut unsealed = uncle(b"good");
+		unsealed.digest_mut().pop();
+		let hash = unsealed.hash();
+
+		assert!(matches!(
+			verify_uncle::<Block, _, _>(&MemoryAux::default(), &MockAlgorithm, unsealed),
+			Err(Error::HeaderUnsealed(h)) if h == hash
+		));
+	}
+}
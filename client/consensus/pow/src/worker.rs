@@ -63,9 +63,9 @@ pub struct MiningBuild<Block: BlockT, Algorithm: PowAlgorithm<Block>, Proof> {
 	pub proposal: Proposal<Block, Proof>,
 }
 
-/// Version of the mining worker.
-#[derive(Eq, PartialEq, Clone, Copy)]
-pub struct Version(usize);
+/// Version of the mining worker. The default is the version of a worker that has just started.
+#[derive(Eq, PartialEq, Clone, Copy, Default)]
+pub struct Version(pub(crate) usize);
 
 /// PoW worker that exposes structs to query the current mining build and submit mined blocks.
 pub struct PowWorker<
@@ -116,6 +116,11 @@ where
 		self.increment_version();
 	}
 
+	/// Get the PoW algorithm of the mining worker.
+	pub(crate) fn algorithm(&self) -> &Algorithm {
+		&self.algorithm
+	}
+
 	/// Get the version of the mining worker.
 	///
 	/// This returns type `Version` which can only compare equality. If `Version` is unchanged, then
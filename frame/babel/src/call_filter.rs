@@ -0,0 +1,147 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Validation of calls dispatched by the dispatch precompiles and programs of each VM.
+
+use crate::VarAddress;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use frame_support::{
+	dispatch::{DispatchClass, GetDispatchInfo, Pays},
+	traits::ConstU32,
+};
+use parity_scale_codec::Encode;
+use sp_runtime::{traits::Get, DispatchError};
+
+/// Validates a runtime call before a VM dispatches it.
+pub trait DispatchValidate<AccountId, RuntimeCall> {
+	/// Validates `call` dispatched with `origin` by `caller`, the VM address of the account or
+	/// contract invoking the dispatch.
+	fn validate_before_dispatch(
+		caller: &VarAddress,
+		origin: &AccountId,
+		call: &RuntimeCall,
+	) -> Result<(), DispatchError>;
+}
+
+/// Allows fee-paying normal calls only.
+impl<AccountId, RuntimeCall> DispatchValidate<AccountId, RuntimeCall> for ()
+where
+	RuntimeCall: GetDispatchInfo,
+{
+	fn validate_before_dispatch(
+		_caller: &VarAddress,
+		_origin: &AccountId,
+		call: &RuntimeCall,
+	) -> Result<(), DispatchError> {
+		let info = call.get_dispatch_info();
+		if !(info.pays_fee == Pays::Yes && info.class == DispatchClass::Normal) {
+			return Err(DispatchError::Other("invalid call"));
+		}
+		Ok(())
+	}
+}
+
+/// Pallet index and call index of an allowed call, where `None` allows every call of the pallet.
+pub type CallIndex = (u8, Option<u8>);
+
+/// Extracts the calls nested in a call, like those of `pallet_utility::batch`.
+///
+/// There is no implementation for `()`, as the runtime must resolve every call nesting other
+/// calls for [`CallFilter`] to filter them.
+pub trait NestedCalls<RuntimeCall> {
+	/// Returns the calls nested in `call`, or `None` if it does not nest any call.
+	fn nested_calls(call: &RuntimeCall) -> Option<Vec<&RuntimeCall>>;
+}
+
+/// Allows calls by their pallet and call indices.
+///
+/// A call is allowed if it is in `Allowed`, or in the calls `AllowedByCaller` lists for the VM
+/// address of the calling account or contract. Calls nested in it, as resolved by `Nested`, must
+/// be allowed as well, and nesting deeper than `MaxDepth` is rejected. Only fee-paying normal
+/// calls are allowed.
+///
+/// `Nested` must resolve every allowed call nesting other calls, such as the ones of
+/// `pallet_utility` or `pallet_proxy`, or the nested calls are dispatched unfiltered.
+pub struct CallFilter<Allowed, AllowedByCaller, Nested, MaxDepth = ConstU32<2>>(
+	PhantomData<(Allowed, AllowedByCaller, Nested, MaxDepth)>,
+);
+
+impl<Allowed, AllowedByCaller, Nested, MaxDepth: Get<u32>>
+	CallFilter<Allowed, AllowedByCaller, Nested, MaxDepth>
+{
+	fn allow<RuntimeCall>(allowed: &[CallIndex], call: &RuntimeCall, depth: u32) -> bool
+	where
+		RuntimeCall: Encode,
+		Nested: NestedCalls<RuntimeCall>,
+	{
+		let index = call.using_encoded(|encoded| match encoded {
+			[pallet, call, ..] => Some((*pallet, *call)),
+			_ => None,
+		});
+		let Some((pallet_index, call_index)) = index else {
+			return false;
+		};
+		if !allowed
+			.iter()
+			.any(|(pallet, call)| *pallet == pallet_index && call.is_none_or(|c| c == call_index))
+		{
+			return false;
+		}
+
+		match Nested::nested_calls(call) {
+			Some(calls) =>
+				depth < MaxDepth::get() &&
+					calls.into_iter().all(|call| Self::allow(allowed, call, depth + 1)),
+			None => true,
+		}
+	}
+}
+
+impl<AccountId, RuntimeCall, Allowed, AllowedByCaller, Nested, MaxDepth>
+	DispatchValidate<AccountId, RuntimeCall>
+	for CallFilter<Allowed, AllowedByCaller, Nested, MaxDepth>
+where
+	RuntimeCall: GetDispatchInfo + Encode,
+	Allowed: Get<Vec<CallIndex>>,
+	AllowedByCaller: Get<Vec<(VarAddress, Vec<CallIndex>)>>,
+	Nested: NestedCalls<RuntimeCall>,
+	MaxDepth: Get<u32>,
+{
+	fn validate_before_dispatch(
+		caller: &VarAddress,
+		origin: &AccountId,
+		call: &RuntimeCall,
+	) -> Result<(), DispatchError> {
+		<() as DispatchValidate<AccountId, RuntimeCall>>::validate_before_dispatch(
+			caller, origin, call,
+		)?;
+
+		let mut allowed = Allowed::get();
+		for (address, calls) in AllowedByCaller::get() {
+			if &address == caller {
+				allowed.extend(calls);
+			}
+		}
+
+		if !Self::allow(&allowed, call, 0) {
+			return Err(DispatchError::Other("call filtered"));
+		}
+		Ok(())
+	}
+}
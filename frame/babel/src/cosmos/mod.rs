@@ -18,4 +18,5 @@
 
 pub mod address;
 pub mod msg;
+#[cfg(feature = "pallet")]
 pub mod precompile;
@@ -16,31 +16,43 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use alloc::vec::Vec;
+use crate::{call_filter::DispatchValidate, ethereum::precompile::StorageFilter};
+use alloc::{format, string::String, vec::Vec};
 use core::marker::PhantomData;
-use cosmwasm_std::{Binary, ContractResult, Response};
+use cosmwasm_std::{Binary, ContractResult, Response, Uint128};
 use cosmwasm_vm::{
 	executor::QueryResponse,
 	vm::{VMBase, VmErrorOf},
 };
 use cosmwasm_vm_wasmi::OwnedWasmiVM;
+use fp_evm::ExitReason;
 use frame_support::{
 	dispatch::{GetDispatchInfo, PostDispatchInfo},
-	ensure, PalletId,
+	ensure,
+	storage::{with_transaction, TransactionOutcome},
+	traits::{fungibles::metadata::Inspect, Get},
+	PalletId,
 };
-use pallet_cosmos::AddressMapping;
+use np_babel::VarAddress;
+use np_multimap::traits::{UniqueMap, UniqueMultimap};
+use pallet_cosmos::{types::DenomOf, AddressMapping};
 use pallet_cosmos_types::address::{acc_address_from_bech32, AUTH_ADDRESS_LEN};
 use pallet_cosmwasm::{
 	pallet_hook::PalletHook,
 	runtimes::vm::{CosmwasmVM, CosmwasmVMError},
 	types::{AccountIdOf, ContractLabelOf, ContractTrieIdOf, EntryPoint, PalletContractCodeInfo},
 };
-use parity_scale_codec::{Decode, DecodeLimit};
+use pallet_evm::{AddressMapping as _, GasWeightMapping, Runner};
+use parity_scale_codec::{Decode, DecodeLimit, Encode};
 use serde::{Deserialize, Serialize};
-use sp_core::H160;
-use sp_runtime::traits::{AccountIdConversion, Convert, Dispatchable};
+use sp_core::{H160, U256};
+use sp_runtime::{
+	traits::{AccountIdConversion, Convert, Dispatchable, TryConvert, UniqueSaturatedInto},
+	DispatchError,
+};
 
 const ID: PalletId = PalletId(*b"dispatch");
+const EVM_ID: PalletId = PalletId(*b"evm_call");
 const DECODE_LIMIT: u32 = 8;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -49,11 +61,65 @@ pub enum ExecuteMsg {
 	Dispatch { input: Binary },
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+	/// Raw storage value under `key`.
+	Storage { key: Binary },
+	/// Account unified with the SCALE-encoded `VarAddress` and the addresses linked to it.
+	UnifiedAddress { address: Binary },
+	/// Metadata of the asset with `denom`.
+	AssetMetadata { denom: String },
+	/// Nonce of the account with bech32 `address`.
+	Nonce { address: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StorageResponse {
+	pub value: Option<Binary>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct UnifiedAddressResponse {
+	/// SCALE-encoded account id.
+	pub account: Option<Binary>,
+	/// SCALE-encoded `VarAddress`es linked to the account.
+	pub addresses: Vec<Binary>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AssetMetadataResponse {
+	pub name: String,
+	pub symbol: String,
+	pub decimals: u8,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct NonceResponse {
+	pub nonce: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EvmExecuteMsg {
+	Call { to: String, data: Binary, value: Uint128, gas_limit: u64 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EvmQueryMsg {
+	Call { to: String, data: Binary, gas_limit: u64 },
+}
+
+pub trait Config: crate::Config + pallet_cosmwasm::Config {
+	type DispatchValidator: DispatchValidate<Self::AccountId, Self::RuntimeCall>;
+	type StorageFilter: StorageFilter;
+}
+
 pub struct Precompiles<T>(PhantomData<T>);
 impl<T> PalletHook<T> for Precompiles<T>
 where
-	T: pallet_cosmwasm::Config,
-	T: pallet_cosmos::Config,
+	T: Config,
 	T::RuntimeCall: Dispatchable<PostInfo = PostDispatchInfo> + GetDispatchInfo + Decode,
 	<T::RuntimeCall as Dispatchable>::RuntimeOrigin: From<Option<T::AccountId>>,
 {
@@ -61,6 +127,7 @@ where
 		contract_address: &AccountIdOf<T>,
 	) -> Option<PalletContractCodeInfo<AccountIdOf<T>, ContractLabelOf<T>, ContractTrieIdOf<T>>> {
 		let dispatch = AccountIdConversion::<T::AccountId>::into_account_truncating(&ID);
+		let evm = AccountIdConversion::<T::AccountId>::into_account_truncating(&EVM_ID);
 
 		match contract_address {
 			address if address == &dispatch => Some(PalletContractCodeInfo::new(
@@ -68,6 +135,11 @@ where
 				false,
 				ID.0.to_vec().try_into().unwrap_or_default(),
 			)),
+			address if address == &evm => Some(PalletContractCodeInfo::new(
+				evm,
+				false,
+				b"evm".to_vec().try_into().unwrap_or_default(),
+			)),
 			_ => None,
 		}
 	}
@@ -82,6 +154,7 @@ where
 	> {
 		let contract_address = vm.0.data().contract_address.clone().into_inner();
 		let dispatch = AccountIdConversion::<T::AccountId>::into_account_truncating(&ID);
+		let evm = AccountIdConversion::<T::AccountId>::into_account_truncating(&EVM_ID);
 		match contract_address {
 			address if address == dispatch => {
 				if let Ok(ExecuteMsg::Dispatch { input }) = serde_json_wasm::from_slice(message) {
@@ -89,7 +162,7 @@ where
 						.map_err(|_| CosmwasmVMError::ExecuteDeserialize)?;
 					let weight = call.get_dispatch_info().total_weight();
 					vm.0.data_mut()
-						.charge_raw(T::WeightToGas::convert(weight))
+						.charge_raw(<T as pallet_cosmos::Config>::WeightToGas::convert(weight))
 						.map_err(|_| CosmwasmVMError::OutOfGas)?;
 
 					let sender = vm.0.data().cosmwasm_message_info.sender.clone().into_string();
@@ -97,7 +170,13 @@ where
 						.map_err(|_| CosmwasmVMError::AccountConvert)?;
 					ensure!(address_raw.len() == AUTH_ADDRESS_LEN, CosmwasmVMError::AccountConvert);
 
-					let origin = T::AddressMapping::into_account_id(H160::from_slice(&address_raw));
+					let address = H160::from_slice(&address_raw);
+					let caller = VarAddress::Cosmos(address.into());
+					let origin =
+						<T as pallet_cosmos::Config>::AddressMapping::into_account_id(address);
+
+					T::DispatchValidator::validate_before_dispatch(&caller, &origin, &call)
+						.map_err(|e| CosmwasmVMError::Aborted(<&'static str>::from(e).into()))?;
 
 					call.dispatch(Some(origin).into())
 						.map_err(|e| CosmwasmVMError::SubstrateDispatch(e.error))?;
@@ -107,6 +186,23 @@ where
 					Err(CosmwasmVMError::ExecuteDeserialize)
 				}
 			},
+			address if address == evm => {
+				let EvmExecuteMsg::Call { to, data, value, gas_limit } =
+					serde_json_wasm::from_slice(message)
+						.map_err(|_| CosmwasmVMError::ExecuteDeserialize)?;
+				ensure!(
+					!vm.0.data().shared.storage_is_readonly(),
+					CosmwasmVMError::ReadOnlyViolation
+				);
+
+				let source = Self::evm_source(vm, false)?;
+				let result =
+					Self::evm_call(vm, source, &to, data.into(), value.u128().into(), gas_limit)?;
+				match result {
+					Ok(output) => Ok(ContractResult::Ok(Response::new().set_data(output))),
+					Err(e) => Ok(ContractResult::Err(e)),
+				}
+			},
 			_ => Err(CosmwasmVMError::ContractNotFound),
 		}
 	}
@@ -120,13 +216,211 @@ where
 	}
 
 	fn query<'a>(
-		_vm: &mut OwnedWasmiVM<pallet_cosmwasm::runtimes::vm::CosmwasmVM<'a, T>>,
-		_message: &[u8],
+		vm: &mut OwnedWasmiVM<pallet_cosmwasm::runtimes::vm::CosmwasmVM<'a, T>>,
+		message: &[u8],
 	) -> Result<
 		ContractResult<QueryResponse>,
 		VmErrorOf<OwnedWasmiVM<pallet_cosmwasm::runtimes::vm::CosmwasmVM<'a, T>>>,
 	> {
-		Err(CosmwasmVMError::ContractNotFound)
+		let contract_address = vm.0.data().contract_address.clone().into_inner();
+		let dispatch = AccountIdConversion::<T::AccountId>::into_account_truncating(&ID);
+		let evm = AccountIdConversion::<T::AccountId>::into_account_truncating(&EVM_ID);
+		match contract_address {
+			address if address == dispatch => Self::query_dispatch(vm, message),
+			address if address == evm => {
+				let EvmQueryMsg::Call { to, data, gas_limit } =
+					serde_json_wasm::from_slice(message)
+						.map_err(|_| CosmwasmVMError::QueryDeserialize)?;
+
+				// Like `eth_call`, state changes made by the call are always discarded.
+				let source = Self::evm_source(vm, true)?;
+				let result = with_transaction(|| {
+					let result =
+						Self::evm_call(vm, source, &to, data.into(), U256::zero(), gas_limit);
+					TransactionOutcome::Rollback(Ok::<_, DispatchError>(result))
+				})
+				.map_err(CosmwasmVMError::SubstrateDispatch)??;
+				match result {
+					Ok(output) => Ok(ContractResult::Ok(output)),
+					Err(e) => Ok(ContractResult::Err(e)),
+				}
+			},
+			_ => Err(CosmwasmVMError::ContractNotFound),
+		}
+	}
+}
+
+impl<T> Precompiles<T>
+where
+	T: Config,
+{
+	fn query_dispatch(
+		vm: &mut OwnedWasmiVM<CosmwasmVM<'_, T>>,
+		message: &[u8],
+	) -> Result<ContractResult<QueryResponse>, CosmwasmVMError<T>> {
+		const PALLET_PREFIX_LENGTH: usize = 16;
+
+		let msg: QueryMsg =
+			serde_json_wasm::from_slice(message).map_err(|_| CosmwasmVMError::QueryDeserialize)?;
+		let response = match msg {
+			QueryMsg::Storage { key } => {
+				if key.len() < PALLET_PREFIX_LENGTH ||
+					!T::StorageFilter::allow(&key[0..PALLET_PREFIX_LENGTH])
+				{
+					return Ok(ContractResult::Err("read restriction".into()));
+				}
+				Self::charge_reads(vm, 1)?;
+				let len = sp_io::storage::read(&key, &mut [], 0).unwrap_or(0);
+				vm.0.data_mut()
+					.charge_raw(
+						u64::from(len)
+							.saturating_mul(T::ContractStorageByteReadPrice::get().into()),
+					)
+					.map_err(|_| CosmwasmVMError::OutOfGas)?;
+
+				let value = frame_support::storage::unhashed::get_raw(&key).map(Binary::from);
+				serde_json_wasm::to_vec(&StorageResponse { value })
+			},
+			QueryMsg::UnifiedAddress { address } => {
+				let address = VarAddress::decode_with_depth_limit(DECODE_LIMIT, &mut &*address)
+					.map_err(|_| CosmwasmVMError::QueryDeserialize)?;
+				Self::charge_reads(vm, 2)?;
+
+				let account = T::AddressMap::find_key(address);
+				let addresses = account
+					.as_ref()
+					.map(|account| {
+						T::AddressMap::get(account)
+							.into_iter()
+							.map(|address| address.encode().into())
+							.collect()
+					})
+					.unwrap_or_default();
+				serde_json_wasm::to_vec(&UnifiedAddressResponse {
+					account: account.map(|account| account.encode().into()),
+					addresses,
+				})
+			},
+			QueryMsg::AssetMetadata { denom } => {
+				Self::charge_reads(vm, 2)?;
+
+				let Some(id) =
+					DenomOf::<T>::try_from(denom.into_bytes()).ok().and_then(T::AssetMap::find_key)
+				else {
+					return Ok(ContractResult::Err("unknown denom".into()));
+				};
+				let name = <pallet_assets::Pallet<T> as Inspect<T::AccountId>>::name(id.clone());
+				let symbol =
+					<pallet_assets::Pallet<T> as Inspect<T::AccountId>>::symbol(id.clone());
+				serde_json_wasm::to_vec(&AssetMetadataResponse {
+					name: String::from_utf8_lossy(&name).into_owned(),
+					symbol: String::from_utf8_lossy(&symbol).into_owned(),
+					decimals: <pallet_assets::Pallet<T> as Inspect<T::AccountId>>::decimals(id),
+				})
+			},
+			QueryMsg::Nonce { address } => {
+				Self::charge_reads(vm, 2)?;
+
+				let who = T::AccountToAddr::try_convert(address)
+					.map_err(|_| CosmwasmVMError::AccountConvert)?;
+				let nonce = frame_system::Pallet::<T>::account_nonce(&who).unique_saturated_into();
+				serde_json_wasm::to_vec(&NonceResponse { nonce })
+			},
+		}
+		.map_err(|_| CosmwasmVMError::QuerySerialize)?;
+
+		Ok(ContractResult::Ok(response.into()))
+	}
+
+	fn charge_reads(
+		vm: &mut OwnedWasmiVM<CosmwasmVM<'_, T>>,
+		reads: u64,
+	) -> Result<(), CosmwasmVMError<T>> {
+		let weight = <T as frame_system::Config>::DbWeight::get().reads(reads);
+		vm.0.data_mut()
+			.charge_raw(<T as pallet_cosmos::Config>::WeightToGas::convert(weight))
+			.map_err(|_| CosmwasmVMError::OutOfGas)
+	}
+
+	/// Calls the EVM contract `to` from `source`.
+	///
+	/// Returns the output of the call, or the revert data as a hex string if it failed.
+	fn evm_call(
+		vm: &mut OwnedWasmiVM<CosmwasmVM<'_, T>>,
+		source: H160,
+		to: &str,
+		data: Vec<u8>,
+		value: U256,
+		gas_limit: u64,
+	) -> Result<Result<Binary, String>, CosmwasmVMError<T>> {
+		let target: [u8; 20] =
+			const_hex::decode_to_array(to).map_err(|_| CosmwasmVMError::AccountConvert)?;
+
+		let weight_limit = T::GasWeightMapping::gas_to_weight(gas_limit, true);
+		ensure!(
+			<T as pallet_cosmos::Config>::WeightToGas::convert(weight_limit) <=
+				vm.0.data().shared.gas.remaining(),
+			CosmwasmVMError::OutOfGas
+		);
+
+		let info = <T as pallet_evm::Config>::Runner::call(
+			source,
+			H160(target),
+			data,
+			value,
+			gas_limit,
+			None,
+			None,
+			None,
+			Vec::new(),
+			false,
+			false,
+			Some(weight_limit),
+			None,
+			<T as pallet_evm::Config>::config(),
+		)
+		.map_err(|e| CosmwasmVMError::SubstrateDispatch(e.error.into()))?;
+
+		let used_gas = info.used_gas.standard.try_into().unwrap_or(u64::MAX);
+		vm.0.data_mut()
+			.charge_raw(<T as pallet_cosmos::Config>::WeightToGas::convert(
+				T::GasWeightMapping::gas_to_weight(used_gas, true),
+			))
+			.map_err(|_| CosmwasmVMError::OutOfGas)?;
+
+		Ok(match info.exit_reason {
+			ExitReason::Succeed(_) => Ok(info.value.into()),
+			ExitReason::Revert(_) => Err(format!("0x{}", const_hex::encode(info.value))),
+			reason => Err(format!("{:?}", reason)),
+		})
+	}
+
+	/// Returns the Ethereum address the sender of the running message calls the EVM from.
+	///
+	/// Only a linked address maps back to the sender, so a sender without one is rejected unless
+	/// the state changes of the call are `discarded`, in which case its leading 20 bytes are used.
+	fn evm_source(
+		vm: &mut OwnedWasmiVM<CosmwasmVM<'_, T>>,
+		discarded: bool,
+	) -> Result<H160, CosmwasmVMError<T>> {
+		let sender = vm.0.data().cosmwasm_message_info.sender.clone().into_string();
+		let who =
+			T::AccountToAddr::try_convert(sender).map_err(|_| CosmwasmVMError::AccountConvert)?;
+		let linked = T::AddressMap::get(&who)
+			.into_iter()
+			.find_map(|address| match address {
+				VarAddress::Ethereum(address) => Some(H160::from(address)),
+				_ => None,
+			})
+			.filter(|&address| {
+				<T as pallet_evm::Config>::AddressMapping::into_account_id(address) == who
+			});
+
+		match linked {
+			Some(address) => Ok(address),
+			None if discarded => Ok(H160::from_slice(&who.as_ref()[..20])),
+			None => Err(CosmwasmVMError::AccountConvert),
+		}
 	}
 }
 
@@ -142,4 +436,32 @@ mod tests {
 
 		assert_eq!(input, const_hex::decode("0a030090b5ab205c6974c9ea841be688864633dc9ca8a357843eeacf2314649965fe220f0000c16ff28623").unwrap());
 	}
+
+	#[test]
+	fn deserialize_evm_msg_test() {
+		let message = r#"{ "call": { "to": "0x0000000000000000000000000000000000000401", "data": "cKCCMQ==", "value": "100", "gas_limit": 50000 } }"#;
+		let EvmExecuteMsg::Call { to, data, value, gas_limit } =
+			serde_json_wasm::from_slice(message.as_bytes()).unwrap();
+
+		assert_eq!(to, "0x0000000000000000000000000000000000000401");
+		assert_eq!(data, const_hex::decode("70a08231").unwrap());
+		assert_eq!(value, Uint128::new(100));
+		assert_eq!(gas_limit, 50000);
+	}
+
+	#[test]
+	fn deserialize_query_msg_test() {
+		let message = r#"{ "storage": { "key": "JqpXPRLWq0uy6J3pkPV95A==" } }"#;
+		let QueryMsg::Storage { key } = serde_json_wasm::from_slice(message.as_bytes()).unwrap()
+		else {
+			panic!("unexpected query");
+		};
+		assert_eq!(key, const_hex::decode("26aa573d12d6ab4bb2e89de990f57de4").unwrap());
+
+		let message = r#"{ "asset_metadata": { "denom": "uatom" } }"#;
+		assert_eq!(
+			serde_json_wasm::from_slice::<QueryMsg>(message.as_bytes()).unwrap(),
+			QueryMsg::AssetMetadata { denom: "uatom".into() }
+		);
+	}
 }
@@ -0,0 +1,227 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Draining of interim accounts into unified accounts.
+//!
+//! The balances of all the `pallet_assets` assets are drained, however they were transferred to
+//! the interim account. CW20 tokens are kept by their contracts and are out of scope.
+
+use crate::{
+	extensions::unify_account::DrainBalance,
+	pallet::{Config, Event, Pallet},
+};
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use frame_support::traits::{
+	fungibles,
+	tokens::{Fortitude::Polite, Preservation::Expendable},
+};
+use parity_scale_codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use sp_runtime::{traits::Zero, RuntimeDebug};
+use sp_weights::Weight;
+
+/// An asset moved from an interim account to a unified account.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub enum Drained<AssetId, Balance, AssetBalance> {
+	/// Native balance.
+	Native(Balance),
+	/// Balance of a `pallet_assets` asset.
+	Asset(AssetId, AssetBalance),
+	/// Metadata and data of the Solana account.
+	SolanaAccount,
+}
+
+pub type DrainedOf<T> = Drained<
+	<T as pallet_assets::Config>::AssetId,
+	<T as pallet_balances::Config>::Balance,
+	<T as pallet_assets::Config>::Balance,
+>;
+
+/// Drains a kind of asset from an interim account, recording what was moved.
+pub trait DrainAsset<T: Config> {
+	fn drain_asset(
+		src: &T::AccountId,
+		dest: &T::AccountId,
+		drained: &mut Vec<DrainedOf<T>>,
+	) -> Result<(), &'static str>;
+
+	/// Worst case weight of draining an account.
+	fn weight() -> Weight;
+}
+
+impl<T: Config> DrainAsset<T> for () {
+	fn drain_asset(
+		_src: &T::AccountId,
+		_dest: &T::AccountId,
+		_drained: &mut Vec<DrainedOf<T>>,
+	) -> Result<(), &'static str> {
+		Ok(())
+	}
+
+	fn weight() -> Weight {
+		Weight::zero()
+	}
+}
+
+#[impl_trait_for_tuples::impl_for_tuples(1, 12)]
+impl<T: Config> DrainAsset<T> for Tuple {
+	fn drain_asset(
+		src: &T::AccountId,
+		dest: &T::AccountId,
+		drained: &mut Vec<DrainedOf<T>>,
+	) -> Result<(), &'static str> {
+		for_tuples!( #( Tuple::drain_asset(src, dest, drained)?; )* );
+		Ok(())
+	}
+
+	fn weight() -> Weight {
+		let mut weight = Weight::zero();
+		for_tuples!( #( weight = weight.saturating_add(Tuple::weight()); )* );
+		weight
+	}
+}
+
+/// Drains the native balance.
+pub struct NativeBalance;
+
+impl<T: Config> DrainAsset<T> for NativeBalance {
+	fn drain_asset(
+		src: &T::AccountId,
+		dest: &T::AccountId,
+		drained: &mut Vec<DrainedOf<T>>,
+	) -> Result<(), &'static str> {
+		let amount =
+			<pallet_balances::Pallet<T> as DrainBalance<T::AccountId>>::drain_balance(src, dest)?;
+		if !amount.is_zero() {
+			drained.push(Drained::Native(amount));
+		}
+		Ok(())
+	}
+
+	fn weight() -> Weight {
+		use pallet_balances::weights::WeightInfo;
+
+		<T as pallet_balances::Config>::WeightInfo::transfer_allow_death()
+	}
+}
+
+/// Drains the balances of all the `pallet_assets` assets held by the account.
+pub struct Assets;
+
+impl<T: Config> DrainAsset<T> for Assets {
+	fn drain_asset(
+		src: &T::AccountId,
+		dest: &T::AccountId,
+		drained: &mut Vec<DrainedOf<T>>,
+	) -> Result<(), &'static str> {
+		use fungibles::{Inspect, Mutate};
+
+		for (id, _) in pallet_assets::Pallet::<T>::account_balances(src.clone()) {
+			let amount = <pallet_assets::Pallet<T> as Inspect<T::AccountId>>::reducible_balance(
+				id.clone(),
+				src,
+				Expendable,
+				Polite,
+			);
+			if amount.is_zero() {
+				continue;
+			}
+			<pallet_assets::Pallet<T> as Mutate<T::AccountId>>::transfer(
+				id.clone(),
+				src,
+				dest,
+				amount,
+				Expendable,
+			)
+			.map_err(|_| "account draining failed: assets")?;
+			drained.push(Drained::Asset(id, amount));
+		}
+		Ok(())
+	}
+
+	fn weight() -> Weight {
+		use pallet_assets::weights::WeightInfo;
+
+		// Reads the asset and the balance of the account for every asset.
+		<T as pallet_assets::Config>::WeightInfo::transfer()
+			.saturating_add(T::DbWeight::get().reads(2))
+			.saturating_mul(T::MaxAssetsToDrain::get().into())
+	}
+}
+
+/// Moves the Solana account to the unified account along with its sufficient reference,
+/// failing if the unified account already has one.
+///
+/// This should be placed before [`NativeBalance`], since the account is reaped with its
+/// native balance.
+pub struct SolanaAccount;
+
+impl<T: Config> DrainAsset<T> for SolanaAccount {
+	fn drain_asset(
+		src: &T::AccountId,
+		dest: &T::AccountId,
+		drained: &mut Vec<DrainedOf<T>>,
+	) -> Result<(), &'static str> {
+		use pallet_solana::{AccountData, AccountMeta};
+
+		let Some(meta) = AccountMeta::<T>::get(src) else { return Ok(()) };
+		if AccountMeta::<T>::contains_key(dest) {
+			return Err("account draining failed: solana account exists");
+		}
+
+		AccountMeta::<T>::remove(src);
+		AccountMeta::<T>::insert(dest, meta);
+		AccountData::<T>::insert(dest, AccountData::<T>::take(src));
+		frame_system::Pallet::<T>::inc_sufficients(dest);
+		frame_system::Pallet::<T>::dec_sufficients(src);
+		drained.push(Drained::SolanaAccount);
+		Ok(())
+	}
+
+	fn weight() -> Weight {
+		T::DbWeight::get().reads_writes(4, 6)
+	}
+}
+
+/// Drains the assets of an interim account with `D` and emits [`Event::Drained`] listing them.
+pub struct Drain<T, D = (SolanaAccount, Assets, NativeBalance)>(PhantomData<(T, D)>);
+
+impl<T: Config, D: DrainAsset<T>> DrainBalance<T::AccountId> for Drain<T, D> {
+	type Output = Vec<DrainedOf<T>>;
+
+	fn drain_balance(
+		src: &T::AccountId,
+		dest: &T::AccountId,
+	) -> Result<Self::Output, &'static str> {
+		let mut drained = Vec::new();
+		D::drain_asset(src, dest, &mut drained)?;
+		if !drained.is_empty() {
+			Pallet::<T>::deposit_event(Event::<T>::Drained {
+				from: src.clone(),
+				to: dest.clone(),
+				assets: drained.clone(),
+			});
+		}
+		Ok(drained)
+	}
+
+	fn weight() -> Weight {
+		D::weight()
+	}
+}
@@ -0,0 +1,89 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! ERC-20 precompile set of assets, extended with their Cosmos denoms.
+
+use super::precompile::{AssetDenom, Config};
+use fp_evm::{
+	ExitSucceed, IsPrecompileResult, PrecompileHandle, PrecompileOutput, PrecompileResult,
+	PrecompileSet,
+};
+use pallet_evm_precompileset_assets_erc20::Erc20AssetsPrecompileSet;
+use parity_scale_codec::MaxEncodedLen;
+use precompile_utils::{prelude::*, EvmResult};
+use sp_core::H160;
+
+/// [`Erc20AssetsPrecompileSet`] answering `denom()` with the Cosmos denom of the asset.
+///
+/// EIP-2612 `permit`, `nonces` and `DOMAIN_SEPARATOR` are served by the inner set.
+pub struct AssetsPrecompileSet<T>(Erc20AssetsPrecompileSet<T>);
+
+impl<T> Default for AssetsPrecompileSet<T> {
+	fn default() -> Self {
+		Self(Erc20AssetsPrecompileSet::new())
+	}
+}
+
+impl<T> AssetsPrecompileSet<T>
+where
+	T: Config,
+{
+	fn denom(
+		asset_id: T::AssetId,
+		handle: &mut impl PrecompileHandle,
+	) -> EvmResult<PrecompileOutput> {
+		if !handle.context().apparent_value.is_zero() {
+			return Err(revert("Function is not payable"));
+		}
+
+		let denom = T::AssetDenom::denom(asset_id).ok_or(revert("No denom set"))?;
+		// Storage item: AssetMapStorage: Twox64(8) + AssetId + Denom
+		handle.record_db_read::<T>(8 + T::AssetId::max_encoded_len() + denom.len())?;
+
+		Ok(PrecompileOutput {
+			exit_status: ExitSucceed::Returned,
+			output: solidity::encode_return_value(UnboundedString::from(denom)),
+		})
+	}
+}
+
+impl<T> PrecompileSet for AssetsPrecompileSet<T>
+where
+	T: Config,
+	Erc20AssetsPrecompileSet<T>: PrecompileSet,
+{
+	fn execute(&self, handle: &mut impl PrecompileHandle) -> Option<PrecompileResult> {
+		let selector = &keccak256!("denom()")[..4];
+		if handle.input().get(..4) == Some(selector) {
+			if let Some(asset_id) = T::address_to_asset_id(handle.code_address()) {
+				if let Err(e) = handle.record_cost(RuntimeHelper::<T>::db_read_gas_cost()) {
+					return Some(Err(e.into()));
+				}
+				if pallet_assets::Pallet::<T>::maybe_total_supply(asset_id.clone()).is_some() {
+					return Some(Self::denom(asset_id, handle));
+				}
+			}
+		}
+
+		self.0.execute(handle)
+	}
+
+	fn is_precompile(&self, address: H160, remaining_gas: u64) -> IsPrecompileResult {
+		self.0.is_precompile(address, remaining_gas)
+	}
+}
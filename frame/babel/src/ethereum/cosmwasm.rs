@@ -0,0 +1,186 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Precompile for calling CosmWasm contracts from the EVM.
+
+use alloc::{format, string::String, vec::Vec};
+use core::marker::PhantomData;
+use cosmwasm_std::{Binary, Empty, QueryRequest, WasmQuery};
+use fp_evm::PrecompileHandle;
+use frame_support::storage::{with_transaction, TransactionOutcome};
+use pallet_cosmwasm::{
+	runtimes::vm::{CosmwasmVMError, CosmwasmVMShared, InitialStorageMutability},
+	types::{
+		AccountIdOf, CodeIdentifier, ContractLabelOf, ContractMessageOf, ContractSaltOf, FundsOf,
+	},
+};
+use pallet_evm::{AddressMapping, GasWeightMapping};
+use precompile_utils::{prelude::*, EvmResult};
+use sp_core::U256;
+use sp_runtime::{
+	traits::{Convert, ConvertBack, TryConvert, TryConvertBack},
+	DispatchError, SaturatedConversion,
+};
+
+pub struct Cosmwasm<T>(PhantomData<T>);
+
+#[precompile_utils::precompile]
+impl<T> Cosmwasm<T>
+where
+	T: pallet_evm::Config
+		+ pallet_cosmos::Config
+		+ pallet_cosmwasm::Config<AccountIdExtended = <T as frame_system::Config>::AccountId>,
+{
+	#[precompile::public("execute(string,bytes,(string,uint256)[])")]
+	fn execute(
+		handle: &mut impl PrecompileHandle,
+		contract: UnboundedString,
+		msg: UnboundedBytes,
+		funds: Vec<(UnboundedString, U256)>,
+	) -> EvmResult {
+		let who =
+			<T as pallet_evm::Config>::AddressMapping::into_account_id(handle.context().caller);
+		let contract = Self::contract_account(contract)?;
+		let funds = Self::convert_funds(funds)?;
+		let message: ContractMessageOf<T> =
+			msg.as_bytes().to_vec().try_into().map_err(|_| revert("Message too large"))?;
+
+		Self::run(handle, InitialStorageMutability::ReadWrite, |shared| {
+			pallet_cosmwasm::Pallet::<T>::do_execute(shared, who, contract, funds, message)
+		})
+	}
+
+	#[precompile::public("instantiate(uint64,string,string,bytes,(string,uint256)[],bytes)")]
+	fn instantiate(
+		handle: &mut impl PrecompileHandle,
+		code_id: u64,
+		admin: UnboundedString,
+		label: UnboundedString,
+		msg: UnboundedBytes,
+		funds: Vec<(UnboundedString, U256)>,
+		salt: UnboundedBytes,
+	) -> EvmResult<UnboundedString> {
+		let who =
+			<T as pallet_evm::Config>::AddressMapping::into_account_id(handle.context().caller);
+		let admin =
+			if admin.as_bytes().is_empty() { None } else { Some(Self::contract_account(admin)?) };
+		let label: ContractLabelOf<T> =
+			label.as_bytes().to_vec().try_into().map_err(|_| revert("Label too large"))?;
+		let funds = Self::convert_funds(funds)?;
+		let message: ContractMessageOf<T> =
+			msg.as_bytes().to_vec().try_into().map_err(|_| revert("Message too large"))?;
+		let salt: ContractSaltOf<T> =
+			salt.as_bytes().to_vec().try_into().map_err(|_| revert("Salt too large"))?;
+
+		let contract = Self::run(handle, InitialStorageMutability::ReadWrite, |shared| {
+			pallet_cosmwasm::Pallet::<T>::do_instantiate(
+				shared,
+				who,
+				CodeIdentifier::CodeId(code_id),
+				salt,
+				admin,
+				label,
+				funds,
+				message,
+			)
+		})?;
+		let address: String = T::AccountToAddr::convert(contract);
+
+		Ok(address.as_str().into())
+	}
+
+	#[precompile::public("query(string,bytes)")]
+	#[precompile::view]
+	fn query(
+		handle: &mut impl PrecompileHandle,
+		contract: UnboundedString,
+		msg: UnboundedBytes,
+	) -> EvmResult<UnboundedBytes> {
+		let contract_account = Self::contract_account(contract.clone())?;
+		let contract_addr = contract.as_str().map_err(|_| revert("Invalid address"))?.into();
+		let request = QueryRequest::<Empty>::Wasm(WasmQuery::Smart {
+			contract_addr,
+			msg: Binary::from(msg.as_bytes()),
+		});
+		let request = serde_json_wasm::to_vec(&request).map_err(|_| revert("Invalid query"))?;
+
+		let response = Self::run(handle, InitialStorageMutability::ReadOnly, |shared| {
+			pallet_cosmwasm::Pallet::<T>::do_query(shared, contract_account, &request)
+		})?;
+
+		Ok(Vec::<u8>::from(response).into())
+	}
+}
+
+impl<T> Cosmwasm<T>
+where
+	T: pallet_evm::Config
+		+ pallet_cosmos::Config
+		+ pallet_cosmwasm::Config<AccountIdExtended = <T as frame_system::Config>::AccountId>,
+{
+	fn contract_account(address: UnboundedString) -> EvmResult<AccountIdOf<T>> {
+		let address = address.as_str().map_err(|_| revert("Invalid address"))?;
+		T::AccountToAddr::try_convert(address.into()).map_err(|_| revert("Invalid address"))
+	}
+
+	fn convert_funds(coins: Vec<(UnboundedString, U256)>) -> EvmResult<FundsOf<T>> {
+		let mut funds = FundsOf::<T>::default();
+		for (denom, amount) in coins.into_iter() {
+			let denom = denom.as_str().map_err(|_| revert("Invalid denom"))?;
+			let asset_id = T::AssetToDenom::try_convert_back(denom.into())
+				.map_err(|_| revert("Unknown denom"))?;
+			let amount = u128::try_from(amount).map_err(|_| revert("Amount overflow"))?;
+
+			funds
+				.try_insert(asset_id, (amount.saturated_into(), true))
+				.map_err(|_| revert("Too many funds"))?;
+		}
+
+		Ok(funds)
+	}
+
+	/// Runs `f` in a CosmWasm VM with the remaining EVM gas and records the gas it used.
+	///
+	/// State changes are reverted if `f` fails.
+	fn run<R>(
+		handle: &mut impl PrecompileHandle,
+		storage_mutability: InitialStorageMutability,
+		f: impl FnOnce(&mut CosmwasmVMShared) -> Result<R, CosmwasmVMError<T>>,
+	) -> EvmResult<R> {
+		let weight = T::GasWeightMapping::gas_to_weight(handle.remaining_gas(), true);
+		let gas = <T as pallet_cosmos::Config>::WeightToGas::convert(weight);
+		let mut shared = pallet_cosmwasm::Pallet::<T>::do_create_vm_shared(gas, storage_mutability);
+
+		let result = with_transaction(|| {
+			let result = f(&mut shared);
+			if result.is_ok() {
+				TransactionOutcome::Commit(Ok::<_, DispatchError>(result))
+			} else {
+				TransactionOutcome::Rollback(Ok(result))
+			}
+		})
+		.map_err(|_| revert("Transactional layer limit reached"))?;
+
+		let used = <T as pallet_cosmos::Config>::WeightToGas::convert_back(
+			gas.saturating_sub(shared.gas.remaining()),
+		);
+		handle.record_cost(T::GasWeightMapping::weight_to_gas(used))?;
+
+		result.map_err(|e| revert(format!("{:?}", e)))
+	}
+}
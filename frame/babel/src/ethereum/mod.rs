@@ -16,6 +16,9 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+pub mod assets;
+#[cfg(feature = "cosmos")]
+pub mod cosmwasm;
 pub mod precompile;
 mod precompiles;
 
@@ -16,17 +16,18 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use crate::{call_filter::DispatchValidate, VarAddress};
 use alloc::{format, vec::Vec};
 use core::marker::PhantomData;
 use fp_evm::{ExitError, PrecompileFailure, PrecompileHandle};
 use frame_support::{
-	dispatch::{DispatchClass, GetDispatchInfo, Pays, PostDispatchInfo},
+	dispatch::{GetDispatchInfo, Pays, PostDispatchInfo},
 	StorageHasher, Twox128,
 };
 use pallet_evm::{AddressMapping, FrameSystemAccountProvider, GasWeightMapping};
 use pallet_evm_precompile_balances_erc20::Erc20Metadata;
 use pallet_evm_precompileset_assets_erc20::AddressToAssetId;
-use parity_scale_codec::{Decode, DecodeLimit, Encode};
+use parity_scale_codec::{Decode, DecodeLimit, Encode, MaxEncodedLen};
 use precompile_utils::{prelude::*, EvmResult};
 use sp_runtime::traits::{Dispatchable, Get};
 
@@ -40,29 +41,21 @@ pub trait Config:
 	type DispatchValidator: DispatchValidate<Self::AccountId, Self::RuntimeCall>;
 	type DecodeLimit: Get<u32>;
 	type StorageFilter: StorageFilter;
+	type AssetDenom: AssetDenom<Self::AssetId>;
 }
 
-pub trait DispatchValidate<AccountId, RuntimeCall> {
-	fn validate_before_dispatch(
-		origin: &AccountId,
-		call: &RuntimeCall,
-	) -> Option<PrecompileFailure>;
+/// Maps assets to their Cosmos denoms.
+pub trait AssetDenom<AssetId> {
+	fn denom(asset_id: AssetId) -> Option<Vec<u8>>;
+	fn asset_id(denom: &[u8]) -> Option<AssetId>;
 }
 
-impl<AccountId, RuntimeCall> DispatchValidate<AccountId, RuntimeCall> for ()
-where
-	RuntimeCall: GetDispatchInfo,
-{
-	fn validate_before_dispatch(
-		_origin: &AccountId,
-		call: &RuntimeCall,
-	) -> Option<PrecompileFailure> {
-		let info = call.get_dispatch_info();
-		if !(info.pays_fee == Pays::Yes && info.class == DispatchClass::Normal) {
-			return Some(PrecompileFailure::Error {
-				exit_status: ExitError::Other("invalid call".into()),
-			});
-		}
+impl<AssetId> AssetDenom<AssetId> for () {
+	fn denom(_asset_id: AssetId) -> Option<Vec<u8>> {
+		None
+	}
+
+	fn asset_id(_denom: &[u8]) -> Option<AssetId> {
 		None
 	}
 }
@@ -96,6 +89,24 @@ where
 		Ok(output.into())
 	}
 
+	#[precompile::public("erc20Address(string)")]
+	#[precompile::view]
+	fn erc20_address(
+		handle: &mut impl PrecompileHandle,
+		denom: UnboundedString,
+	) -> EvmResult<Address> {
+		let denom = denom.as_bytes();
+
+		// Storage item: AssetIndex: Twox64(8) + Denom + AssetId
+		handle.record_db_read::<T>(8 + denom.len() + T::AssetId::max_encoded_len())?;
+
+		// Denoms, IBC ones included, resolve only once registered with `register_asset`; there
+		// is no deterministic mapping from a denom to an asset id.
+		let asset_id = T::AssetDenom::asset_id(denom).ok_or(revert("Unknown denom"))?;
+
+		Ok(Address(T::asset_id_to_address(asset_id)))
+	}
+
 	// darwinia-precompile-state-storage
 	#[precompile::public("getStorage(bytes)")]
 	#[precompile::view]
@@ -140,11 +151,14 @@ where
 			}
 		}
 
+		let caller = VarAddress::Ethereum(context.caller.into());
 		let origin = T::AddressMapping::into_account_id(context.caller);
 
-		if let Some(err) = T::DispatchValidator::validate_before_dispatch(&origin, &call) {
-			return Err(err);
-		}
+		T::DispatchValidator::validate_before_dispatch(&caller, &origin, &call).map_err(|e| {
+			PrecompileFailure::Error {
+				exit_status: ExitError::Other(<&'static str>::from(e).into()),
+			}
+		})?;
 
 		handle.record_external_cost(
 			Some(info.total_weight().ref_time()),
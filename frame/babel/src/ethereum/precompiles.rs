@@ -16,14 +16,21 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use super::precompile;
+#[cfg(feature = "cosmos")]
+use super::cosmwasm::Cosmwasm;
+use super::{assets::AssetsPrecompileSet, precompile};
+#[cfg(not(feature = "cosmos"))]
+use core::marker::PhantomData;
+#[cfg(not(feature = "cosmos"))]
+use fp_evm::{Precompile, PrecompileHandle, PrecompileResult};
+#[cfg(not(feature = "cosmos"))]
+use precompile_utils::prelude::revert;
 use frame_support::parameter_types;
 use pallet_evm_precompile_balances_erc20::Erc20BalancesPrecompile;
 use pallet_evm_precompile_blake2::Blake2F;
 use pallet_evm_precompile_bn128::{Bn128Add, Bn128Mul, Bn128Pairing};
 use pallet_evm_precompile_modexp::Modexp;
 use pallet_evm_precompile_simple::{ECRecover, Identity, Ripemd160, Sha256};
-use pallet_evm_precompileset_assets_erc20::Erc20AssetsPrecompileSet;
 use precompile::Babel;
 use precompile_utils::precompile_set::*;
 
@@ -36,6 +43,17 @@ parameter_types! {
 
 type EthereumPrecompilesChecks = (AcceptDelegateCall, CallableByContract, CallableByPrecompile);
 
+/// Stands in for the CosmWasm precompile without the `cosmos` feature, reverting every call.
+#[cfg(not(feature = "cosmos"))]
+pub struct Cosmwasm<T>(PhantomData<T>);
+
+#[cfg(not(feature = "cosmos"))]
+impl<T> Precompile for Cosmwasm<T> {
+	fn execute(_handle: &mut impl PrecompileHandle) -> PrecompileResult {
+		Err(revert("CosmWasm is not enabled"))
+	}
+}
+
 #[precompile_utils::precompile_name_from_address]
 type BabelPrecompilesAt<T> = (
 	PrecompileAt<AddressU64<1>, ECRecover, EthereumPrecompilesChecks>,
@@ -53,6 +71,7 @@ type BabelPrecompilesAt<T> = (
 		Erc20BalancesPrecompile<T>,
 		(CallableByContract, CallableByPrecompile),
 	>,
+	PrecompileAt<AddressU64<0x402>, Cosmwasm<T>, (CallableByContract, CallableByPrecompile)>,
 );
 
 pub type BabelPrecompiles<T> = PrecompileSetBuilder<
@@ -61,7 +80,7 @@ pub type BabelPrecompiles<T> = PrecompileSetBuilder<
 		PrecompilesInRangeInclusive<(AddressU64<1>, AddressU64<2048>), BabelPrecompilesAt<T>>,
 		PrecompileSetStartingWith<
 			AssetPrefix,
-			Erc20AssetsPrecompileSet<T>,
+			AssetsPrecompileSet<T>,
 			(CallableByContract, CallableByPrecompile),
 		>,
 	),
@@ -26,18 +26,23 @@ use np_babel::VarAddress;
 use np_multimap::traits::UniqueMultimap;
 use parity_scale_codec::{Decode, Encode, MaxEncodedLen};
 use scale_info::TypeInfo;
-use sp_core::{ecdsa, H256};
+use sp_core::{ecdsa, ed25519, H256};
 use sp_runtime::{
-	impl_tx_ext_default,
-	traits::{AccountIdConversion, DispatchInfoOf, TransactionExtension, Zero},
-	transaction_validity::TransactionSource,
+	traits::{
+		AccountIdConversion, DispatchInfoOf, DispatchOriginOf, PostDispatchInfoOf,
+		TransactionExtension, Zero,
+	},
+	transaction_validity::{TransactionSource, TransactionValidityError},
+	DispatchResult,
 };
 use sp_weights::Weight;
 
 type AccountIdOf<T> = <T as AccountIdProvider>::AccountId;
 
 /// A configuration for UnifyAccount signed extension.
-pub trait Config: AccountIdProvider<AccountId: From<H256> + TryInto<ecdsa::Public>> {
+pub trait Config:
+	AccountIdProvider<AccountId: From<H256> + TryInto<ecdsa::Public> + TryInto<ed25519::Public>>
+{
 	/// A map from account to addresses.
 	type AddressMap: UniqueMultimap<AccountIdOf<Self>, VarAddress>;
 	/// Drain account balance when unifying accounts.
@@ -65,7 +70,7 @@ impl<T> UnifyAccount<T> {
 
 impl<T: Config> UnifyAccount<T> {
 	pub fn unify_ecdsa(who: &AccountIdOf<T>) -> Result<(), &'static str> {
-		if let Ok(public) = who.clone().try_into() {
+		if let Ok(public) = TryInto::<ecdsa::Public>::try_into(who.clone()) {
 			#[cfg(feature = "ethereum")]
 			{
 				let address = np_babel::EthereumAddress::from(public);
@@ -93,6 +98,23 @@ impl<T: Config> UnifyAccount<T> {
 		}
 		Ok(())
 	}
+
+	pub fn unify_ed25519(who: &AccountIdOf<T>) -> Result<(), &'static str> {
+		if let Ok(public) = TryInto::<ed25519::Public>::try_into(who.clone()) {
+			#[cfg(feature = "solana")]
+			{
+				let address = np_babel::SolanaAddress::from(public);
+				let interim: AccountIdOf<T> = H256::from(address.clone()).into();
+				// The interim account of an ed25519 account is usually the account itself.
+				if interim.encode() != who.encode() {
+					T::DrainBalance::drain_balance(&interim, who)?;
+				}
+				T::AddressMap::try_insert(who, VarAddress::Solana(address))
+					.map_err(|_| "account unification failed: solana")?;
+			}
+		}
+		Ok(())
+	}
 }
 
 impl<T> Clone for UnifyAccount<T> {
@@ -127,34 +149,57 @@ where
 {
 	const IDENTIFIER: &'static str = "UnifyAccount";
 	type Implicit = ();
-	type Val = ();
-	type Pre = ();
+	/// Weight to be refunded.
+	type Val = Weight;
+	type Pre = Weight;
 
 	fn weight(&self, _: &T::RuntimeCall) -> Weight {
-		// FIXME
-		Weight::from_parts(0, 0)
+		// Drains the interim accounts of up to three addresses of an ecdsa account.
+		T::DrainBalance::weight().saturating_mul(3)
 	}
 
 	fn validate(
 		&self,
 		origin: <T as frame_system::Config>::RuntimeOrigin,
-		_call: &T::RuntimeCall,
+		call: &T::RuntimeCall,
 		_info: &DispatchInfoOf<T::RuntimeCall>,
 		_len: usize,
 		_self_implicit: Self::Implicit,
 		_inherited_implication: &impl Encode,
 		_source: TransactionSource,
 	) -> sp_runtime::traits::ValidateResult<Self::Val, T::RuntimeCall> {
+		let mut unspent = self.weight(call);
 		if let Some(who) = origin.as_signer() {
 			let account = frame_system::Account::<T>::get(who);
 			if account.nonce.is_zero() {
 				let _ = Self::unify_ecdsa(who);
+				let _ = Self::unify_ed25519(who);
+				unspent = Weight::zero();
 			}
 		}
-		Ok((Default::default(), (), origin))
+		Ok((Default::default(), unspent, origin))
 	}
 
-	impl_tx_ext_default!(T::RuntimeCall; prepare);
+	fn prepare(
+		self,
+		val: Self::Val,
+		_origin: &DispatchOriginOf<T::RuntimeCall>,
+		_call: &T::RuntimeCall,
+		_info: &DispatchInfoOf<T::RuntimeCall>,
+		_len: usize,
+	) -> Result<Self::Pre, TransactionValidityError> {
+		Ok(val)
+	}
+
+	fn post_dispatch_details(
+		pre: Self::Pre,
+		_info: &DispatchInfoOf<T::RuntimeCall>,
+		_post_info: &PostDispatchInfoOf<T::RuntimeCall>,
+		_len: usize,
+		_result: &DispatchResult,
+	) -> Result<Weight, TransactionValidityError> {
+		Ok(pre)
+	}
 }
 
 pub trait DrainBalance<AccountId> {
@@ -163,6 +208,11 @@ pub trait DrainBalance<AccountId> {
 	fn drain_balance(_src: &AccountId, _dest: &AccountId) -> Result<Self::Output, &'static str> {
 		Ok(Default::default())
 	}
+
+	/// Worst case weight of draining an account.
+	fn weight() -> Weight {
+		Weight::zero()
+	}
 }
 
 impl<AccountId, T> DrainBalance<AccountId> for T
@@ -229,6 +279,18 @@ mod tests {
 		}
 	}
 
+	#[cfg(feature = "solana")]
+	#[test]
+	fn unify_ed25519_works() {
+		use sp_core::Pair;
+
+		let public = ed25519::Pair::from_string("//Alice", None).unwrap().public();
+		let who = AccountId::from(public);
+		let _ = UnifyAccount::<MockConfig>::unify_ed25519(&who);
+		let solana = VarAddress::solana(public);
+		assert_eq!(<MockConfig as Config>::AddressMap::find_key(solana), Some(who));
+	}
+
 	#[test]
 	fn on_killed_account_works() {
 		let who = AccountId::from(dev_public());
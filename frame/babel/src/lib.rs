@@ -26,11 +26,20 @@ mod mock;
 #[cfg(test)]
 mod tests;
 
+pub mod call_filter;
 #[cfg(feature = "cosmos")]
 pub mod cosmos;
+#[cfg(feature = "pallet")]
+pub mod drain;
 #[cfg(feature = "ethereum")]
 pub mod ethereum;
 pub mod extensions;
+#[cfg(feature = "pallet")]
+pub mod link;
+#[cfg(feature = "pallet")]
+pub mod migrations;
+#[cfg(feature = "solana")]
+pub mod solana;
 pub mod traits;
 
 pub use extensions::unify_account::UnifyAccount;
@@ -42,7 +51,13 @@ pub use pallet::*;
 #[cfg(feature = "pallet")]
 #[frame_support::pallet]
 pub mod pallet {
-	use super::VarAddress;
+	use super::{
+		drain::DrainedOf,
+		ethereum::{precompile::AssetDenom, AddressToAssetId},
+		extensions::unify_account::DrainBalance,
+		link::{LinkPayload, LinkProof},
+		VarAddress,
+	};
 	use alloc::vec::Vec;
 	use cosmos_sdk_proto::{cosmos::tx::v1beta1::Tx, traits::Message};
 	use frame_support::{
@@ -54,6 +69,7 @@ pub mod pallet {
 		},
 	};
 	use frame_system::{ensure_root, pallet_prelude::*};
+	use np_babel::asset::{AssetQuery, AssetRepresentations};
 	use np_multimap::{
 		traits::{UniqueMap, UniqueMultimap},
 		Error as MapError, UniqueMapAdapter, UniqueMultimapAdapter,
@@ -67,10 +83,9 @@ pub mod pallet {
 	use pallet_evm::{AddressMapping as _, FrameSystemAccountProvider};
 	use solana_sdk::transaction::VersionedTransaction;
 	use sp_core::{ecdsa, H256};
-	#[cfg(feature = "nostr")]
-	use sp_runtime::traits::AccountIdConversion;
 	use sp_runtime::traits::{
-		AtLeast32BitUnsigned, ConvertBack, One, Saturating, StaticLookup, UniqueSaturatedInto,
+		AccountIdConversion, AtLeast32BitUnsigned, ConvertBack, One, Saturating, StaticLookup,
+		UniqueSaturatedInto, Zero,
 	};
 
 	type AccountIdLookupOf<T> = <<T as frame_system::Config>::Lookup as StaticLookup>::Source;
@@ -86,8 +101,19 @@ pub mod pallet {
 		+ pallet_evm::Config<AccountProvider = FrameSystemAccountProvider<Self>>
 		+ pallet_solana::Config
 	{
+		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
 		type AddressMap: UniqueMultimap<Self::AccountId, VarAddress>;
 		type AssetMap: UniqueMap<AssetIdOf<Self>, DenomOf<Self>>;
+		/// Drains the interim account of an address into the account it is linked to.
+		type DrainBalance: DrainBalance<Self::AccountId>;
+		/// Number of assets the weight of draining an interim account is estimated with.
+		///
+		/// Draining reads the balance of every asset, so this should not be lower than the
+		/// number of assets.
+		#[pallet::constant]
+		type MaxAssetsToDrain: Get<u32>;
+		/// Origin allowed to manage assets and their denoms.
+		type AssetManagerOrigin: EnsureOrigin<Self::RuntimeOrigin>;
 		type Balance: Member
 			+ Parameter
 			+ AtLeast32BitUnsigned
@@ -100,13 +126,67 @@ pub mod pallet {
 			+ Into<<Self as pallet_assets::Config>::Balance>;
 	}
 
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
 	#[pallet::pallet]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(crate) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// Assets of an interim account were drained into the account it is unified with.
+		Drained { from: T::AccountId, to: T::AccountId, assets: Vec<DrainedOf<T>> },
+		/// An asset was transferred, where `asset` is `None` for the native token.
+		Transferred {
+			from: T::AccountId,
+			to: VarAddress,
+			asset: Option<AssetIdOf<T>>,
+			amount: BalanceOf<T>,
+		},
+	}
+
 	#[pallet::error]
 	pub enum Error<T> {
 		InvalidOrigin,
 		InvalidTransaction,
+		/// The address cannot be linked.
+		UnsupportedAddress,
+		/// The address is already linked to an account.
+		AddressAlreadyLinked,
+		/// The account already has an address of the same kind.
+		AddressKindAlreadyLinked,
+		/// The account has too many addresses.
+		TooManyAddresses,
+		/// The address is not linked to the account.
+		AddressNotLinked,
+		/// The proof is not signed by the key of the address.
+		InvalidProof,
+		/// Draining the interim account of the address failed.
+		DrainFailed,
+		/// No asset is mapped to the denom.
+		UnknownDenom,
+		/// The asset does not exist.
+		AssetNotFound,
+		/// The asset is already registered with a denom.
+		AssetAlreadyRegistered,
+		/// The asset is not registered with a denom.
+		AssetNotRegistered,
+		/// The denom is mapped to another asset.
+		DenomInUse,
+		/// The proof is not supported by the runtime.
+		UnsupportedProof,
+	}
+
+	/// Asset to transfer.
+	#[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+	pub enum TransferAsset<AssetId, Denom> {
+		/// The native token.
+		Native,
+		/// An asset by id.
+		Id(AssetId),
+		/// An asset by its Cosmos denom.
+		Denom(Denom),
 	}
 
 	/// Mapping from addresses to accounts.
@@ -137,6 +217,10 @@ pub mod pallet {
 	#[pallet::storage]
 	pub type AssetIndex<T: Config> = StorageMap<_, Twox64Concat, DenomOf<T>, AssetIdOf<T>>;
 
+	/// Registered assets by the address of their SPL mint.
+	#[pallet::storage]
+	pub type SplMintIndex<T: Config> = StorageMap<_, Twox64Concat, H256, AssetIdOf<T>>;
+
 	#[pallet::call]
 	impl<T: Config> Pallet<T>
 	where
@@ -296,54 +380,345 @@ pub mod pallet {
 			let id: <T as pallet_cosmos::Config>::AssetId = id.into();
 			let denom: DenomOf<T> =
 				denom.try_into().map_err(|_| DispatchError::Other("Too long denom"))?;
-			T::AssetMap::try_insert(id, denom)
+			T::AssetMap::try_insert(id.clone(), denom)
 				.map_err(|_| DispatchError::Other("Failed to insert into asset map"))?;
+			SplMintIndex::<T>::insert(Self::spl_mint(&id), id);
 
 			Ok(())
 		}
 
+		/// Transfers `value` of `asset` to the account of `dest`.
+		///
+		/// The encoding of `asset` is compatible with the former `Option` of an asset id.
 		#[pallet::call_index(3)]
-		#[pallet::weight({
-			use pallet_assets::weights::WeightInfo as _;
-			use pallet_balances::weights::WeightInfo as _;
-
-			match id {
-				Some(_) => <T as pallet_assets::Config>::WeightInfo::transfer(),
-				None => <T as pallet_balances::Config>::WeightInfo::transfer_keep_alive()
-			}
-		})]
+		#[pallet::weight(Pallet::<T>::transfer_weight(asset))]
 		pub fn transfer(
 			origin: OriginFor<T>,
-			id: Option<T::AssetIdParameter>,
+			asset: TransferAsset<T::AssetIdParameter, DenomOf<T>>,
 			dest: VarAddress,
 			#[pallet::compact] value: BalanceOf<T>,
 		) -> DispatchResult {
 			let who = ensure_signed(origin)?;
-			let dest: T::AccountId = match dest {
+
+			Self::do_transfer(&who, asset, dest, value)
+		}
+
+		/// Transfers each `(asset, dest, value)` of `transfers`, failing if any of them fails.
+		#[pallet::call_index(7)]
+		#[pallet::weight(transfers.iter().fold(Weight::zero(), |weight, (asset, _, _)| {
+			weight.saturating_add(Pallet::<T>::transfer_weight(asset))
+		}))]
+		pub fn transfer_all(
+			origin: OriginFor<T>,
+			transfers: Vec<(
+				TransferAsset<T::AssetIdParameter, DenomOf<T>>,
+				VarAddress,
+				BalanceOf<T>,
+			)>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			for (asset, dest, value) in transfers {
+				Self::do_transfer(&who, asset, dest, value)?;
+			}
+
+			Ok(())
+		}
+
+		/// Sets the metadata of a registered asset.
+		#[pallet::call_index(8)]
+		#[pallet::weight({
+			use pallet_assets::weights::WeightInfo;
+
+			<T as pallet_assets::Config>::WeightInfo::force_set_metadata(name.len() as u32, symbol.len() as u32)
+		})]
+		pub fn set_asset_metadata(
+			origin: OriginFor<T>,
+			id: T::AssetIdParameter,
+			name: Vec<u8>,
+			symbol: Vec<u8>,
+			decimals: u8,
+			is_frozen: bool,
+		) -> DispatchResult {
+			T::AssetManagerOrigin::ensure_origin(origin)?;
+
+			pallet_assets::Pallet::<T>::force_set_metadata(
+				frame_system::RawOrigin::Root.into(),
+				id,
+				name,
+				symbol,
+				decimals,
+				is_frozen,
+			)
+		}
+
+		/// Renames the denom of a registered asset.
+		#[pallet::call_index(9)]
+		#[pallet::weight(T::DbWeight::get().reads_writes(2, 3))]
+		pub fn set_asset_denom(
+			origin: OriginFor<T>,
+			id: T::AssetIdParameter,
+			denom: Vec<u8>,
+		) -> DispatchResult {
+			T::AssetManagerOrigin::ensure_origin(origin)?;
+
+			let id: AssetIdOf<T> = id.into();
+			let denom: DenomOf<T> =
+				denom.try_into().map_err(|_| DispatchError::Other("Too long denom"))?;
+			ensure!(T::AssetMap::get(id.clone()).is_some(), Error::<T>::AssetNotRegistered);
+			T::AssetMap::remove(id.clone());
+			T::AssetMap::try_insert(id, denom).map_err(|_| Error::<T>::DenomInUse)?;
+
+			Ok(())
+		}
+
+		/// Unregisters an asset and starts destroying it.
+		///
+		/// The accounts and approvals of the asset should then be destroyed with `pallet_assets`.
+		#[pallet::call_index(10)]
+		#[pallet::weight({
+			use pallet_assets::weights::WeightInfo;
+
+			<T as pallet_assets::Config>::WeightInfo::start_destroy()
+				.saturating_add(T::DbWeight::get().reads_writes(1, 3))
+		})]
+		pub fn remove_asset(origin: OriginFor<T>, id: T::AssetIdParameter) -> DispatchResult {
+			use frame_support::traits::fungibles::Destroy;
+
+			T::AssetManagerOrigin::ensure_origin(origin)?;
+
+			let id: AssetIdOf<T> = id.into();
+			ensure!(T::AssetMap::get(id.clone()).is_some(), Error::<T>::AssetNotRegistered);
+			T::AssetMap::remove(id.clone());
+			SplMintIndex::<T>::remove(Self::spl_mint(&id));
+			<pallet_assets::Pallet<T> as Destroy<T::AccountId>>::start_destroy(id, None)
+		}
+
+		/// Registers an existing asset of `pallet_assets` with a denom.
+		#[pallet::call_index(11)]
+		#[pallet::weight(T::DbWeight::get().reads_writes(3, 3))]
+		pub fn register_asset(
+			origin: OriginFor<T>,
+			id: T::AssetIdParameter,
+			denom: Vec<u8>,
+		) -> DispatchResult {
+			use frame_support::traits::fungibles::Inspect;
+
+			T::AssetManagerOrigin::ensure_origin(origin)?;
+
+			let id: AssetIdOf<T> = id.into();
+			let denom: DenomOf<T> =
+				denom.try_into().map_err(|_| DispatchError::Other("Too long denom"))?;
+			ensure!(
+				<pallet_assets::Pallet<T> as Inspect<T::AccountId>>::asset_exists(id.clone()),
+				Error::<T>::AssetNotFound
+			);
+			ensure!(T::AssetMap::get(id.clone()).is_none(), Error::<T>::AssetAlreadyRegistered);
+			T::AssetMap::try_insert(id.clone(), denom).map_err(|_| Error::<T>::DenomInUse)?;
+			SplMintIndex::<T>::insert(Self::spl_mint(&id), id);
+
+			Ok(())
+		}
+
+		/// Links `address` to the origin account, proving ownership of its key with `proof`
+		/// over the [`LinkPayload`] of the account, whose nonce is the one of the account at
+		/// dispatch.
+		///
+		/// The balance of the interim account of the address is drained into the account.
+		// TODO: Benchmark the weight.
+		#[pallet::call_index(5)]
+		#[pallet::weight(
+			T::DbWeight::get().reads_writes(5, 5).saturating_add(T::DrainBalance::weight())
+		)]
+		pub fn link(origin: OriginFor<T>, address: VarAddress, proof: LinkProof) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(
+				cfg!(feature = "nostr") || !matches!(proof, LinkProof::Nip01 { .. }),
+				Error::<T>::UnsupportedProof
+			);
+
+			let interim: T::AccountId = match &address {
+				VarAddress::Cosmos(address) => address.into_account_truncating(),
+				VarAddress::Ethereum(address) => address.into_account_truncating(),
+				#[cfg(feature = "nostr")]
+				VarAddress::Nostr(address) => address.into_account_truncating(),
+				VarAddress::Solana(address) => H256::from(address.clone()).into(),
+				_ => return Err(Error::<T>::UnsupportedAddress.into()),
+			};
+			ensure!(T::AddressMap::find_key(&address).is_none(), Error::<T>::AddressAlreadyLinked);
+			ensure!(
+				!T::AddressMap::get(&who).iter().any(|linked| {
+					core::mem::discriminant(linked) == core::mem::discriminant(&address)
+				}),
+				Error::<T>::AddressKindAlreadyLinked
+			);
+
+			let payload = LinkPayload {
+				account: who.encode(),
+				genesis_hash: frame_system::Pallet::<T>::block_hash(BlockNumberFor::<T>::zero())
+					.as_ref()
+					.to_vec(),
+				nonce: frame_system::Pallet::<T>::account_nonce(&who).unique_saturated_into(),
+				chain_id: <T as pallet_evm::Config>::ChainId::get(),
+			};
+			ensure!(proof.verify(&address, &payload), Error::<T>::InvalidProof);
+
+			if interim != who {
+				T::DrainBalance::drain_balance(&interim, &who)
+					.map_err(|_| Error::<T>::DrainFailed)?;
+			}
+			T::AddressMap::try_insert(&who, address).map_err(|_| Error::<T>::TooManyAddresses)?;
+
+			Ok(())
+		}
+
+		/// Unlinks `address` from the origin account.
+		#[pallet::call_index(6)]
+		#[pallet::weight(T::DbWeight::get().reads_writes(1, 2))]
+		pub fn unlink(origin: OriginFor<T>, address: VarAddress) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			ensure!(T::AddressMap::remove(&who, address), Error::<T>::AddressNotLinked);
+
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		fn transfer_weight(asset: &TransferAsset<T::AssetIdParameter, DenomOf<T>>) -> Weight {
+			use pallet_assets::weights::WeightInfo as _;
+			use pallet_balances::weights::WeightInfo as _;
+
+			// Reads the address map for unified addresses.
+			let weight = T::DbWeight::get().reads(1);
+			match asset {
+				TransferAsset::Native => weight.saturating_add(
+					<T as pallet_balances::Config>::WeightInfo::transfer_keep_alive(),
+				),
+				TransferAsset::Id(_) =>
+					weight.saturating_add(<T as pallet_assets::Config>::WeightInfo::transfer()),
+				TransferAsset::Denom(_) => weight
+					.saturating_add(T::DbWeight::get().reads(1))
+					.saturating_add(<T as pallet_assets::Config>::WeightInfo::transfer()),
+			}
+		}
+	}
+
+	impl<T: Config> Pallet<T>
+	where
+		T::AccountId: From<H256>,
+	{
+		/// Returns the account of `address`, resolving unified addresses through the address
+		/// map.
+		pub fn account_of(address: &VarAddress) -> Result<T::AccountId, DispatchError> {
+			let account = match address {
+				VarAddress::Polkadot(address) =>
+					H256::from(<[u8; 32]>::from(address.clone())).into(),
 				VarAddress::Cosmos(address) =>
-					<T as pallet_cosmos::Config>::AddressMapping::into_account_id(address.into()),
+					<T as pallet_cosmos::Config>::AddressMapping::into_account_id(
+						address.clone().into(),
+					),
 				VarAddress::Ethereum(address) =>
-					<T as pallet_evm::Config>::AddressMapping::into_account_id(address.into()),
-				VarAddress::Polkadot(address) => H256::from(<[u8; 32]>::from(address)).into(),
+					<T as pallet_evm::Config>::AddressMapping::into_account_id(
+						address.clone().into(),
+					),
 				#[cfg(feature = "nostr")]
-				VarAddress::Nostr(ref address) =>
-					T::AddressMap::find_key(&dest).unwrap_or(address.into_account_truncating()),
-				VarAddress::Solana(address) => H256::from(address).into(),
-				_ => unreachable!(),
+				VarAddress::Nostr(nostr) => T::AddressMap::find_key(address)
+					.unwrap_or_else(|| nostr.into_account_truncating()),
+				VarAddress::Solana(solana) => T::AddressMap::find_key(address)
+					.unwrap_or_else(|| H256::from(solana.clone()).into()),
+				_ => return Err(Error::<T>::UnsupportedAddress.into()),
+			};
+			Ok(account)
+		}
+
+		fn do_transfer(
+			who: &T::AccountId,
+			asset: TransferAsset<T::AssetIdParameter, DenomOf<T>>,
+			dest: VarAddress,
+			value: BalanceOf<T>,
+		) -> DispatchResult {
+			let to = Self::account_of(&dest)?;
+			let asset: Option<AssetIdOf<T>> = match asset {
+				TransferAsset::Native => None,
+				TransferAsset::Id(id) => Some(id.into()),
+				TransferAsset::Denom(denom) =>
+					Some(T::AssetMap::find_key(denom).ok_or(Error::<T>::UnknownDenom)?),
 			};
 
-			match id {
+			match asset.clone() {
 				Some(id) => <pallet_assets::Pallet<T> as Mutate<T::AccountId>>::transfer(
-					id.into(),
-					&who,
-					&dest,
+					id,
+					who,
+					&to,
 					value.into(),
 					Expendable,
 				)
-				.map(|_| ()),
-				None => pallet_balances::Pallet::<T>::transfer(&who, &dest, value.into(), Preserve)
-					.map(|_| ()),
+				.map(|_| ())?,
+				None => pallet_balances::Pallet::<T>::transfer(who, &to, value.into(), Preserve)
+					.map(|_| ())?,
 			}
+
+			Self::deposit_event(Event::<T>::Transferred {
+				from: who.clone(),
+				to: dest,
+				asset,
+				amount: value,
+			});
+
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T>
+	where
+		T: AddressToAssetId<AssetIdOf<T>>,
+	{
+		/// Returns the canonical representations in each VM of the asset matching `query`.
+		pub fn asset_representations(
+			query: AssetQuery<AssetIdOf<T>>,
+		) -> Option<AssetRepresentations<AssetIdOf<T>>> {
+			use frame_support::traits::fungibles::Inspect;
+
+			let id = match query {
+				AssetQuery::Id(id) => id,
+				AssetQuery::Erc20(address) => T::address_to_asset_id(address)?,
+				AssetQuery::Denom(denom) =>
+					T::AssetMap::find_key(DenomOf::<T>::try_from(denom).ok()?)?,
+				AssetQuery::SplMint(mint) => SplMintIndex::<T>::get(mint)?,
+			};
+			<pallet_assets::Pallet<T> as Inspect<T::AccountId>>::asset_exists(id.clone()).then(
+				|| AssetRepresentations {
+					erc20: T::asset_id_to_address(id.clone()),
+					denom: T::AssetMap::get(id.clone()).map(Into::into),
+					spl_mint: Self::spl_mint(&id),
+					id,
+				},
+			)
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// Returns the address of the SPL mint of an asset.
+		///
+		/// It is the program derived address of the Babel dispatch program with the seeds
+		/// `b"mint"` and the SCALE-encoded asset id.
+		pub fn spl_mint(id: &AssetIdOf<T>) -> H256 {
+			let (mint, _bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+				&[b"mint".as_slice(), &id.encode()],
+				&crate::solana::program::ID,
+			);
+			H256(mint.to_bytes())
+		}
+	}
+
+	impl<T: Config> AssetDenom<AssetIdOf<T>> for Pallet<T> {
+		fn denom(asset_id: AssetIdOf<T>) -> Option<Vec<u8>> {
+			T::AssetMap::get(asset_id).map(Into::into)
+		}
+
+		fn asset_id(denom: &[u8]) -> Option<AssetIdOf<T>> {
+			T::AssetMap::find_key(DenomOf::<T>::try_from(denom.to_vec()).ok()?)
 		}
 	}
 }
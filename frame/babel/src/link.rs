@@ -0,0 +1,190 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Proofs of ownership of foreign keys for linking addresses to accounts.
+
+use crate::VarAddress;
+use alloc::{
+	format,
+	string::{String, ToString},
+	vec::Vec,
+};
+use parity_scale_codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use sp_core::{ecdsa, ed25519, H256};
+use sp_io::hashing::{keccak_256, sha2_256};
+use sp_runtime::RuntimeDebug;
+
+/// Payload signed by a foreign key to link its address to an account.
+pub struct LinkPayload {
+	/// SCALE-encoded account to link the address to.
+	pub account: Vec<u8>,
+	/// Genesis hash of the chain.
+	pub genesis_hash: Vec<u8>,
+	/// Nonce of the account at dispatch.
+	///
+	/// The nonce is incremented before the transaction is dispatched, so the signer signs the
+	/// nonce of the linking transaction plus one.
+	pub nonce: u64,
+	/// EVM chain id, used for the EIP-712 domain.
+	pub chain_id: u64,
+}
+
+impl LinkPayload {
+	/// Human-readable message signed by EIP-191, ADR-036, ed25519 and NIP-01 signers.
+	pub fn message(&self) -> String {
+		format!(
+			"Link to Babel account 0x{} on chain 0x{} with nonce {}",
+			sp_core::hexdisplay::HexDisplay::from(&self.account),
+			sp_core::hexdisplay::HexDisplay::from(&self.genesis_hash),
+			self.nonce,
+		)
+	}
+
+	/// EIP-712 digest of the `Link(bytes account,bytes genesisHash,uint256 nonce)` struct in
+	/// the `Babel` domain.
+	pub fn eip712_digest(&self) -> [u8; 32] {
+		let domain_type_hash =
+			keccak_256(b"EIP712Domain(string name,string version,uint256 chainId)");
+		let mut domain = Vec::with_capacity(32 * 4);
+		domain.extend_from_slice(&domain_type_hash);
+		domain.extend_from_slice(&keccak_256(b"Babel"));
+		domain.extend_from_slice(&keccak_256(b"1"));
+		domain.extend_from_slice(&uint256(self.chain_id));
+		let domain_separator = keccak_256(&domain);
+
+		let type_hash = keccak_256(b"Link(bytes account,bytes genesisHash,uint256 nonce)");
+		let mut data = Vec::with_capacity(32 * 4);
+		data.extend_from_slice(&type_hash);
+		data.extend_from_slice(&keccak_256(&self.account));
+		data.extend_from_slice(&keccak_256(&self.genesis_hash));
+		data.extend_from_slice(&uint256(self.nonce));
+		let struct_hash = keccak_256(&data);
+
+		let mut digest = Vec::with_capacity(2 + 32 * 2);
+		digest.extend_from_slice(b"\x19\x01");
+		digest.extend_from_slice(&domain_separator);
+		digest.extend_from_slice(&struct_hash);
+		keccak_256(&digest)
+	}
+
+	/// Amino JSON sign doc of an ADR-036 `MsgSignData` of the message, with sorted keys.
+	pub fn adr036_sign_doc(&self, signer: &str) -> String {
+		use base64::{prelude::BASE64_STANDARD, Engine};
+
+		format!(
+			"{{\"account_number\":\"0\",\"chain_id\":\"\",\"fee\":{{\"amount\":[],\"gas\":\"0\"}},\"memo\":\"\",\"msgs\":[{{\"type\":\"sign/MsgSignData\",\"value\":{{\"data\":\"{}\",\"signer\":\"{}\"}}}}],\"sequence\":\"0\"}}",
+			BASE64_STANDARD.encode(self.message()),
+			signer,
+		)
+	}
+}
+
+/// Signature of a foreign key over a [`LinkPayload`].
+#[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub enum LinkProof {
+	/// EIP-191 `personal_sign` signature over the link message.
+	Eip191(ecdsa::Signature),
+	/// EIP-712 `signTypedData` signature over the `Link` struct.
+	Eip712(ecdsa::Signature),
+	/// ADR-036 arbitrary data signature over the link message.
+	Adr036 { public: ecdsa::Public, signature: [u8; 64] },
+	/// ed25519 `signMessage` signature over the link message.
+	SignMessage(ed25519::Signature),
+	/// Signature of a NIP-01 event whose content is the link message.
+	///
+	/// Only verified by runtimes built with the `nostr` feature.
+	Nip01 { created_at: u64, signature: [u8; 64] },
+}
+
+impl LinkProof {
+	/// Verifies that the proof is signed by the key of `address` over `payload`.
+	pub fn verify(&self, address: &VarAddress, payload: &LinkPayload) -> bool {
+		match (self, address) {
+			(Self::Eip191(signature), VarAddress::Ethereum(address)) => {
+				let message = payload.message();
+				let mut prefixed =
+					format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+				prefixed.extend_from_slice(message.as_bytes());
+				recover_ethereum_address(signature, &keccak_256(&prefixed)).as_ref() ==
+					Some(address)
+			},
+			(Self::Eip712(signature), VarAddress::Ethereum(address)) =>
+				recover_ethereum_address(signature, &payload.eip712_digest()).as_ref() ==
+					Some(address),
+			(Self::Adr036 { public, signature }, VarAddress::Cosmos(address)) => {
+				if np_babel::CosmosAddress::from(*public) != *address {
+					return false;
+				}
+				let hash = sha2_256(payload.adr036_sign_doc(&address.to_string()).as_bytes());
+				// The recovery id is not part of Cosmos signatures.
+				(0u8..2).any(|v| {
+					let mut sig = [0u8; 65];
+					sig[..64].copy_from_slice(signature);
+					sig[64] = v;
+					sp_io::crypto::secp256k1_ecdsa_recover_compressed(&sig, &hash)
+						.is_ok_and(|recovered| recovered == public.0)
+				})
+			},
+			(Self::SignMessage(signature), VarAddress::Solana(address)) => {
+				let public = ed25519::Public::from_raw(H256::from(address.clone()).0);
+				sp_io::crypto::ed25519_verify(signature, payload.message().as_bytes(), &public)
+			},
+			#[cfg(feature = "nostr")]
+			(Self::Nip01 { created_at, signature }, VarAddress::Nostr(address)) => {
+				use k256::schnorr::{Signature, VerifyingKey};
+
+				let public = H256::from(address.clone());
+				let event = format!(
+					"[0,\"{}\",{},1,[],\"{}\"]",
+					sp_core::hexdisplay::HexDisplay::from(&public.0),
+					created_at,
+					payload.message(),
+				);
+				let id = sha2_256(event.as_bytes());
+				let (Ok(key), Ok(signature)) =
+					(VerifyingKey::from_bytes(&public.0), Signature::try_from(&signature[..]))
+				else {
+					return false;
+				};
+				key.verify_raw(&id, &signature).is_ok()
+			},
+			_ => false,
+		}
+	}
+}
+
+fn uint256(value: u64) -> [u8; 32] {
+	let mut word = [0u8; 32];
+	word[24..].copy_from_slice(&value.to_be_bytes());
+	word
+}
+
+fn recover_ethereum_address(
+	signature: &ecdsa::Signature,
+	digest: &[u8; 32],
+) -> Option<np_babel::EthereumAddress> {
+	let mut sig = signature.0;
+	// Wallets may encode the recovery id as 27 or 28.
+	if sig[64] >= 27 {
+		sig[64] -= 27;
+	}
+	sp_io::crypto::secp256k1_ecdsa_recover_compressed(&sig, digest)
+		.ok()
+		.map(|public| ecdsa::Public::from_raw(public).into())
+}
@@ -0,0 +1,67 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Storage migrations of the Babel pallet.
+
+/// Indexes the registered assets by the address of their SPL mint.
+pub mod v1 {
+	use crate::*;
+	use core::marker::PhantomData;
+	use frame_support::{
+		migrations::VersionedMigration, pallet_prelude::*, traits::UncheckedOnRuntimeUpgrade,
+	};
+	#[cfg(feature = "try-runtime")]
+	use {alloc::vec::Vec, sp_runtime::TryRuntimeError};
+
+	/// Inserts every asset of `AssetMapStorage` into `SplMintIndex`.
+	///
+	/// Deriving the SPL mint of an asset takes up to 255 hashes, and the assets are registered
+	/// by `AssetManagerOrigin`, so the migration runs in a single block.
+	pub struct UncheckedMigrateToV1<T>(PhantomData<T>);
+
+	impl<T: Config> UncheckedOnRuntimeUpgrade for UncheckedMigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			let mut count = 0u64;
+			for id in AssetMapStorage::<T>::iter_keys() {
+				SplMintIndex::<T>::insert(Pallet::<T>::spl_mint(&id), id);
+				count += 1;
+			}
+
+			T::DbWeight::get().reads_writes(count, count)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(_state: Vec<u8>) -> Result<(), TryRuntimeError> {
+			ensure!(
+				AssetMapStorage::<T>::iter_keys().all(|id| {
+					SplMintIndex::<T>::get(Pallet::<T>::spl_mint(&id)).as_ref() == Some(&id)
+				}),
+				"Registered asset not indexed by its SPL mint"
+			);
+			Ok(())
+		}
+	}
+
+	pub type MigrateToV1<T> = VersionedMigration<
+		0,
+		1,
+		UncheckedMigrateToV1<T>,
+		Pallet<T>,
+		<T as frame_system::Config>::DbWeight,
+	>;
+}
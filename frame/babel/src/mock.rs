@@ -26,19 +26,24 @@ use cosmos_sdk_proto::{
 	Any,
 };
 use frame_babel::{
+	call_filter::{CallIndex, NestedCalls},
 	cosmos::{
 		address::{AccountToAddr, AddressMapping as CosmosAddressMapping},
 		precompile::Precompiles,
 	},
-	ethereum::{AddressMapping as EthereumAddressMapping, EnsureAddress},
+	ethereum::{
+		AddressMapping as EthereumAddressMapping, AddressToAssetId, BabelPrecompiles,
+		EnsureAddress, Erc20Metadata, ASSET_PRECOMPILE_ADDRESS_PREFIX,
+	},
 	extensions::unify_account,
+	solana::program::Builtins,
 	VarAddress,
 };
 use frame_support::{
 	derive_impl,
 	instances::{Instance1, Instance2},
 	parameter_types,
-	traits::{ConstU32, Contains, NeverEnsureOrigin},
+	traits::{ConstU32, Contains, NeverEnsureOrigin, PalletInfoAccess},
 	PalletId,
 };
 use frame_system::EnsureRoot;
@@ -65,12 +70,11 @@ use pallet_cosmos_x_wasm::msgs::{
 	MsgStoreCodeHandler, MsgUpdateAdminHandler,
 };
 use pallet_cosmwasm::instrument::CostRules;
-use pallet_solana::Pubkey;
 use solana_sdk::hash::Hash;
-use sp_core::{ConstU128, Pair, H256};
+use sp_core::{ConstU128, Pair, H160, H256};
 use sp_runtime::{
 	traits::{Convert, ConvertBack, IdentityLookup, TryConvert, TryConvertBack},
-	BoundedVec, BuildStorage,
+	BoundedVec, BuildStorage, Weight,
 };
 
 pub type AccountId = AccountId32<MultiSigner>;
@@ -106,6 +110,8 @@ mod runtime {
 
 	#[runtime::pallet_index(20)]
 	pub type Sudo = pallet_sudo;
+	#[runtime::pallet_index(21)]
+	pub type Utility = pallet_utility;
 
 	#[runtime::pallet_index(30)]
 	pub type Ethereum = pallet_ethereum;
@@ -165,9 +171,20 @@ impl pallet_assets::Config for Test {
 #[derive_impl(pallet_sudo::config_preludes::TestDefaultConfig)]
 impl pallet_sudo::Config for Test {}
 
+impl pallet_utility::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type RuntimeCall = RuntimeCall;
+	type PalletsOrigin = OriginCaller;
+	type WeightInfo = ();
+}
+
 #[derive_impl(pallet_ethereum::config_preludes::TestDefaultConfig)]
 impl pallet_ethereum::Config for Test {}
 
+parameter_types! {
+	pub BabelPrecompilesValue: BabelPrecompiles<Test> = BabelPrecompiles::<_>::new();
+}
+
 #[derive_impl(pallet_evm::config_preludes::TestDefaultConfig)]
 impl pallet_evm::Config for Test {
 	type CallOrigin = EnsureAddress<AccountId>;
@@ -176,10 +193,30 @@ impl pallet_evm::Config for Test {
 	type AccountProvider = pallet_evm::FrameSystemAccountProvider<Self>;
 	type BlockHashMapping = pallet_evm::SubstrateBlockHashMapping<Self>;
 	type Currency = Balances;
+	type PrecompilesType = BabelPrecompiles<Self>;
+	type PrecompilesValue = BabelPrecompilesValue;
 	type Runner = pallet_evm::runner::stack::Runner<Self>;
 	type Timestamp = Timestamp;
 }
 
+impl Erc20Metadata for Test {
+	fn name() -> &'static str {
+		"Test"
+	}
+
+	fn symbol() -> &'static str {
+		"TEST"
+	}
+
+	fn decimals() -> u8 {
+		18
+	}
+
+	fn is_native_currency() -> bool {
+		true
+	}
+}
+
 parameter_types! {
 	pub const NativeDenom: &'static str = "antt";
 }
@@ -328,19 +365,6 @@ impl pallet_cosmwasm::Config for Test {
 	type NativeDenom = NativeDenom;
 }
 
-pub struct AccountIdConversion;
-impl Convert<Pubkey, AccountId> for AccountIdConversion {
-	fn convert(pubkey: Pubkey) -> AccountId {
-		AccountId::new(pubkey.to_bytes())
-	}
-}
-
-impl ConvertBack<Pubkey, AccountId> for AccountIdConversion {
-	fn convert_back(account_id: AccountId) -> Pubkey {
-		Pubkey::from(<[u8; 32]>::from(account_id))
-	}
-}
-
 pub struct HashConversion;
 impl Convert<Hash, Blockhash> for HashConversion {
 	fn convert(hash: Hash) -> Blockhash {
@@ -355,11 +379,69 @@ impl ConvertBack<Hash, Blockhash> for HashConversion {
 
 #[derive_impl(pallet_solana::config_preludes::TestDefaultConfig)]
 impl pallet_solana::Config for Test {
-	type AccountIdConversion = AccountIdConversion;
+	type AccountIdConversion = frame_babel::solana::AccountIdConversion<Self>;
 	type HashConversion = HashConversion;
 	type Balance = <Self as pallet_balances::Config>::Balance;
 	type Currency = Balances;
 	type DecimalMultiplier = ConstU128<1_000_000_000>;
+	type BuiltinPrograms = Builtins<Self>;
+}
+
+pub struct WeightToComputeUnits;
+impl Convert<Weight, u64> for WeightToComputeUnits {
+	fn convert(weight: Weight) -> u64 {
+		weight.ref_time() / 10_000
+	}
+}
+
+parameter_types! {
+	pub AllowedCalls: Vec<CallIndex> = vec![
+		(Balances::index() as u8, None),
+		// sudo
+		(Sudo::index() as u8, Some(0)),
+		// batch
+		(Utility::index() as u8, Some(0)),
+	];
+	pub AllowedCallsByCaller: Vec<(VarAddress, Vec<CallIndex>)> = vec![
+		// transfer
+		(VarAddress::Ethereum(evm_caller().into()), vec![(Assets::index() as u8, Some(8))]),
+	];
+}
+
+pub struct RuntimeNestedCalls;
+impl NestedCalls<RuntimeCall> for RuntimeNestedCalls {
+	fn nested_calls(call: &RuntimeCall) -> Option<Vec<&RuntimeCall>> {
+		match call {
+			RuntimeCall::Sudo(pallet_sudo::Call::sudo { call }) => Some(vec![call.as_ref()]),
+			RuntimeCall::Utility(
+				pallet_utility::Call::batch { calls } |
+				pallet_utility::Call::batch_all { calls } |
+				pallet_utility::Call::force_batch { calls },
+			) => Some(calls.iter().collect()),
+			_ => None,
+		}
+	}
+}
+
+pub type CallFilter =
+	frame_babel::call_filter::CallFilter<AllowedCalls, AllowedCallsByCaller, RuntimeNestedCalls>;
+
+impl frame_babel::ethereum::precompile::Config for Test {
+	type DispatchValidator = CallFilter;
+	type DecodeLimit = ConstU32<8>;
+	type StorageFilter = ();
+	type AssetDenom = Babel;
+}
+
+impl frame_babel::cosmos::precompile::Config for Test {
+	type DispatchValidator = CallFilter;
+	type StorageFilter = ();
+}
+
+impl frame_babel::solana::program::Config for Test {
+	type DispatchValidator = CallFilter;
+	type DecodeLimit = ConstU32<8>;
+	type WeightToComputeUnits = WeightToComputeUnits;
 }
 
 #[derive_impl(pallet_multimap::config_preludes::TestDefaultConfig)]
@@ -376,20 +458,44 @@ impl pallet_multimap::Config<Instance2> for Test {
 }
 
 impl frame_babel::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
 	type AddressMap = frame_babel::AddressMap<Self>;
 	type AssetMap = frame_babel::AssetMap<Self>;
 	type Balance = Balance;
+	type DrainBalance = frame_babel::drain::Drain<Self>;
+	type MaxAssetsToDrain = ConstU32<2>;
+	type AssetManagerOrigin = EnsureRoot<AccountId>;
 }
 
 impl unify_account::Config for Test {
 	type AddressMap = frame_babel::AddressMap<Self>;
-	type DrainBalance = Balances;
+	type DrainBalance = frame_babel::drain::Drain<Self>;
+}
+
+impl AddressToAssetId<AssetId> for Test {
+	fn address_to_asset_id(address: H160) -> Option<AssetId> {
+		let (prefix, id) = address.as_bytes().split_at(16);
+		(prefix == ASSET_PRECOMPILE_ADDRESS_PREFIX)
+			.then(|| AssetId::from_be_bytes(id.try_into().unwrap()))
+	}
+
+	fn asset_id_to_address(asset_id: AssetId) -> H160 {
+		let mut address = [0u8; 20];
+		address[..16].copy_from_slice(ASSET_PRECOMPILE_ADDRESS_PREFIX);
+		address[16..].copy_from_slice(&asset_id.to_be_bytes());
+		H160(address)
+	}
 }
 
 pub fn alice() -> AccountId {
 	sp_keyring::sr25519::Keyring::Alice.pair().public().into()
 }
 
+/// Ethereum address allowed to dispatch asset transfers.
+pub fn evm_caller() -> H160 {
+	H160::repeat_byte(0x11)
+}
+
 pub fn new_test_ext() -> sp_io::TestExternalities {
 	let mut t = frame_system::GenesisConfig::<Test>::default().build_storage().unwrap();
 	pallet_balances::GenesisConfig::<Test> { balances: vec![(alice(), 10000)] }
@@ -0,0 +1,70 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+#[cfg(feature = "solana")]
+pub mod program;
+
+use crate::{extensions::unify_account, VarAddress};
+use core::marker::PhantomData;
+use np_babel::SolanaAddress;
+use np_multimap::traits::UniqueMultimap;
+use solana_sdk::pubkey::Pubkey;
+use sp_core::H256;
+use sp_runtime::traits::{Convert, ConvertBack};
+
+/// Converts between Solana pubkeys and accounts, resolving unified addresses through the
+/// address map.
+pub struct AccountIdConversion<T>(PhantomData<T>);
+
+impl<T> Convert<Pubkey, T::AccountId> for AccountIdConversion<T>
+where
+	T: unify_account::Config,
+{
+	fn convert(pubkey: Pubkey) -> T::AccountId {
+		let address = SolanaAddress::from(H256::from(pubkey.to_bytes()));
+		T::AddressMap::find_key(VarAddress::Solana(address.clone()))
+			.unwrap_or_else(|| H256::from(address).into())
+	}
+}
+
+impl<T> Convert<T::AccountId, Pubkey> for AccountIdConversion<T>
+where
+	T: unify_account::Config,
+	T::AccountId: Into<H256>,
+{
+	fn convert(account: T::AccountId) -> Pubkey {
+		let address = T::AddressMap::get(&account)
+			.into_iter()
+			.find_map(|address| match address {
+				VarAddress::Solana(address) => Some(H256::from(address)),
+				_ => None,
+			})
+			.unwrap_or_else(|| account.into());
+		Pubkey::from(address.0)
+	}
+}
+
+impl<T> ConvertBack<Pubkey, T::AccountId> for AccountIdConversion<T>
+where
+	T: unify_account::Config,
+	T::AccountId: Into<H256>,
+{
+	fn convert_back(account: T::AccountId) -> Pubkey {
+		<Self as Convert<T::AccountId, Pubkey>>::convert(account)
+	}
+}
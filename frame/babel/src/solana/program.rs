@@ -0,0 +1,121 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Babel built-in Solana program dispatching runtime calls.
+
+use crate::{call_filter::DispatchValidate, VarAddress};
+use alloc::{boxed::Box, format, vec, vec::Vec};
+use core::marker::PhantomData;
+use frame_support::dispatch::{GetDispatchInfo, PostDispatchInfo};
+use pallet_solana::{
+	runtime::builtins::{BuiltinProgram, BuiltinPrograms},
+	solana_program_runtime::{ic_msg, invoke_context::InvokeContext},
+	solana_rbpf::{declare_builtin_function, memory_region::MemoryMapping},
+};
+use parity_scale_codec::DecodeLimit;
+use solana_sdk::{instruction::InstructionError, pubkey, pubkey::Pubkey};
+use sp_core::H256;
+use sp_runtime::traits::{Convert, Dispatchable, Get};
+use sp_weights::Weight;
+
+/// Program id of the Babel dispatch program.
+pub const ID: Pubkey = pubkey!("Babe1Dispatch111111111111111111111111111111");
+
+pub trait Config:
+	frame_system::Config<
+		RuntimeCall: Dispatchable<PostInfo = PostDispatchInfo> + GetDispatchInfo,
+		RuntimeOrigin: From<Option<Self::AccountId>>,
+	> + pallet_solana::Config
+{
+	type DispatchValidator: DispatchValidate<Self::AccountId, Self::RuntimeCall>;
+	type DecodeLimit: Get<u32>;
+	/// Converts the weight of a dispatched call to compute units.
+	type WeightToComputeUnits: Convert<Weight, u64>;
+}
+
+/// Builtin programs provided by Babel.
+pub struct Builtins<T>(PhantomData<T>);
+
+impl<T: Config> BuiltinPrograms for Builtins<T> {
+	fn builtin_programs() -> Vec<BuiltinProgram> {
+		vec![BuiltinProgram {
+			program_id: ID,
+			name: "babel_dispatch_program",
+			entrypoint: Entrypoint::vm::<T>,
+		}]
+	}
+}
+
+declare_builtin_function!(
+	Entrypoint<T: Config>,
+	fn rust(
+		invoke_context: &mut InvokeContext,
+		_arg0: u64,
+		_arg1: u64,
+		_arg2: u64,
+		_arg3: u64,
+		_arg4: u64,
+		_memory_mapping: &mut MemoryMapping,
+	) -> Result<u64, Box<dyn core::error::Error>> {
+		process_instruction::<T>(invoke_context)
+			.map(|_| 0)
+			.map_err(|err| Box::new(err) as Box<dyn core::error::Error>)
+	}
+);
+
+/// Dispatches a SCALE-encoded runtime call in the instruction data with the first instruction
+/// account as origin, which must be a signer.
+fn process_instruction<T: Config>(
+	invoke_context: &mut InvokeContext,
+) -> Result<(), InstructionError> {
+	let transaction_context = &invoke_context.transaction_context;
+	let instruction_context = transaction_context.get_current_instruction_context()?;
+
+	let call = T::RuntimeCall::decode_with_depth_limit(
+		T::DecodeLimit::get(),
+		&mut instruction_context.get_instruction_data(),
+	)
+	.map_err(|_| InstructionError::InvalidInstructionData)?;
+
+	instruction_context.check_number_of_instruction_accounts(1)?;
+	if !instruction_context.is_instruction_account_signer(0)? {
+		return Err(InstructionError::MissingRequiredSignature);
+	}
+	let signer = *transaction_context.get_key_of_account_at_index(
+		instruction_context.get_index_of_instruction_account_in_transaction(0)?,
+	)?;
+
+	let info = call.get_dispatch_info();
+	invoke_context
+		.consume_checked(T::WeightToComputeUnits::convert(info.total_weight()))
+		.map_err(|_| InstructionError::ComputationalBudgetExceeded)?;
+
+	let caller = VarAddress::Solana(H256::from(signer.to_bytes()).into());
+	let origin = <T as pallet_solana::Config>::AccountIdConversion::convert(signer);
+	if let Err(e) = T::DispatchValidator::validate_before_dispatch(&caller, &origin, &call) {
+		ic_msg!(invoke_context, <&'static str>::from(e));
+		return Err(InstructionError::InvalidInstructionData);
+	}
+
+	call.dispatch(Some(origin).into()).map_err(|e| {
+		ic_msg!(invoke_context, &format!("dispatch execution failed: {}", <&'static str>::from(e)));
+		InstructionError::ProgramFailedToComplete
+	})?;
+
+	Ok(())
+}
@@ -19,7 +19,7 @@
 use crate::{mock::*, *};
 use frame_support::{assert_ok, traits::fungible::Inspect};
 use np_babel::EthereumAddress;
-use sp_core::{ecdsa, sha2_256};
+use sp_core::{ecdsa, sha2_256, H160};
 use sp_runtime::traits::AccountIdConversion;
 
 fn dev_public() -> ecdsa::Public {
@@ -39,7 +39,7 @@ fn transfer_to_ethereum_address_works() {
 	new_test_ext().execute_with(|| {
 		assert_ok!(Babel::transfer(
 			RuntimeOrigin::signed(alice()),
-			None,
+			TransferAsset::Native,
 			VarAddress::Ethereum(address),
 			100
 		));
@@ -52,6 +52,169 @@ fn transfer_to_ethereum_address_works() {
 	});
 }
 
+#[test]
+fn transfer_all_by_denom_works() {
+	use frame_support::assert_noop;
+	use pallet_cosmos::types::DenomOf;
+
+	let address = VarAddress::ethereum(dev_public());
+	let interim: AccountId = EthereumAddress::from(dev_public()).into_account_truncating();
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(Babel::force_create_asset(
+			RuntimeOrigin::root(),
+			1,
+			b"Test".to_vec(),
+			b"TEST".to_vec(),
+			b"utest".to_vec(),
+			6,
+			false,
+			true,
+			alice(),
+			1
+		));
+		assert_ok!(Assets::mint(RuntimeOrigin::signed(alice()), 1, alice(), 1000));
+
+		let denom: DenomOf<Test> = b"utest".to_vec().try_into().unwrap();
+		assert_ok!(Babel::transfer_all(
+			RuntimeOrigin::signed(alice()),
+			vec![
+				(TransferAsset::Native, address.clone(), 100),
+				(TransferAsset::Denom(denom), address.clone(), 10),
+			]
+		));
+		assert_eq!(Balances::balance(&interim), 100);
+		assert_eq!(Assets::balance(1, &interim), 10);
+		System::assert_last_event(
+			Event::<Test>::Transferred {
+				from: alice(),
+				to: address.clone(),
+				asset: Some(1),
+				amount: 10,
+			}
+			.into(),
+		);
+
+		let unknown: DenomOf<Test> = b"uunknown".to_vec().try_into().unwrap();
+		assert_noop!(
+			Babel::transfer(
+				RuntimeOrigin::signed(alice()),
+				TransferAsset::Denom(unknown),
+				address,
+				10
+			),
+			Error::<Test>::UnknownDenom
+		);
+	});
+}
+
+#[test]
+fn asset_lifecycle_works() {
+	use frame_support::{
+		assert_noop,
+		traits::fungibles::{metadata::Inspect as _, Inspect as _},
+	};
+	use np_babel::asset::{AssetQuery, AssetRepresentations};
+	use pallet_cosmos::types::DenomOf;
+	use sp_runtime::DispatchError;
+
+	new_test_ext().execute_with(|| {
+		assert_ok!(Assets::force_create(RuntimeOrigin::root(), 1, alice(), true, 1));
+		assert_noop!(
+			Babel::register_asset(RuntimeOrigin::signed(alice()), 1, b"utest".to_vec()),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			Babel::register_asset(RuntimeOrigin::root(), 2, b"utest".to_vec()),
+			Error::<Test>::AssetNotFound
+		);
+		assert_ok!(Babel::register_asset(RuntimeOrigin::root(), 1, b"utest".to_vec()));
+		assert_noop!(
+			Babel::register_asset(RuntimeOrigin::root(), 1, b"uother".to_vec()),
+			Error::<Test>::AssetAlreadyRegistered
+		);
+
+		assert_ok!(Babel::set_asset_metadata(
+			RuntimeOrigin::root(),
+			1,
+			b"Test".to_vec(),
+			b"TEST".to_vec(),
+			6,
+			false
+		));
+		assert_eq!(Assets::decimals(1), 6);
+
+		assert_ok!(Babel::set_asset_denom(RuntimeOrigin::root(), 1, b"urenamed".to_vec()));
+		assert_eq!(
+			AssetIndex::<Test>::get(DenomOf::<Test>::truncate_from(b"utest".to_vec())),
+			None
+		);
+
+		let representations = AssetRepresentations {
+			id: 1,
+			erc20: <Test as ethereum::AddressToAssetId<_>>::asset_id_to_address(1),
+			denom: Some(b"urenamed".to_vec()),
+			spl_mint: Babel::spl_mint(&1),
+		};
+		for query in [
+			AssetQuery::Id(1),
+			AssetQuery::Erc20(representations.erc20),
+			AssetQuery::Denom(b"urenamed".to_vec()),
+			AssetQuery::SplMint(representations.spl_mint),
+		] {
+			assert_eq!(Babel::asset_representations(query), Some(representations.clone()));
+		}
+
+		assert_ok!(Babel::remove_asset(RuntimeOrigin::root(), 1));
+		assert_eq!(AssetMapStorage::<Test>::get(1), None);
+		assert_eq!(SplMintIndex::<Test>::get(Babel::spl_mint(&1)), None);
+		assert_eq!(Babel::asset_representations(AssetQuery::SplMint(Babel::spl_mint(&1))), None);
+		assert_eq!(
+			AssetIndex::<Test>::get(DenomOf::<Test>::truncate_from(b"urenamed".to_vec())),
+			None
+		);
+		assert!(Assets::asset_exists(1));
+		assert_noop!(
+			Babel::remove_asset(RuntimeOrigin::root(), 1),
+			Error::<Test>::AssetNotRegistered
+		);
+	});
+}
+
+#[test]
+fn migrate_to_v1_indexes_spl_mints() {
+	use frame_support::traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion};
+	use pallet_cosmos::types::DenomOf;
+
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(0).put::<Babel>();
+		AssetMapStorage::<Test>::insert(1, DenomOf::<Test>::truncate_from(b"utest".to_vec()));
+
+		migrations::v1::MigrateToV1::<Test>::on_runtime_upgrade();
+
+		assert_eq!(Babel::on_chain_storage_version(), 1);
+		assert_eq!(SplMintIndex::<Test>::get(Babel::spl_mint(&1)), Some(1));
+	});
+}
+
+#[test]
+fn spl_mint_is_program_derived_address() {
+	use parity_scale_codec::Encode;
+	use solana_sdk::pubkey::Pubkey;
+
+	let mint = Pubkey::from(Babel::spl_mint(&1).0);
+	assert!(!mint.is_on_curve());
+	assert_eq!(
+		mint,
+		Pubkey::find_program_address(
+			&[b"mint".as_slice(), &(1 as AssetId).encode()],
+			&solana::program::ID
+		)
+		.0
+	);
+}
+
 #[cfg(feature = "nostr")]
 #[test]
 fn transfer_to_nostr_address_works() {
@@ -67,7 +230,7 @@ fn transfer_to_nostr_address_works() {
 	new_test_ext().execute_with(|| {
 		assert_ok!(Babel::transfer(
 			RuntimeOrigin::signed(alice()),
-			None,
+			TransferAsset::Native,
 			VarAddress::Nostr(address),
 			100
 		));
@@ -80,6 +243,58 @@ fn transfer_to_nostr_address_works() {
 	});
 }
 
+#[test]
+fn transfer_to_solana_address_works() {
+	use sp_core::{ed25519, Pair};
+
+	let public = ed25519::Pair::from_string("//Alice", None).unwrap().public();
+	let account = AccountId::from(public);
+
+	new_test_ext().execute_with(|| {
+		assert_ok!(Babel::transfer(
+			RuntimeOrigin::signed(alice()),
+			TransferAsset::Native,
+			VarAddress::solana(public),
+			100
+		));
+		assert_eq!(Balances::balance(&account), 100);
+
+		assert_ok!(UnifyAccount::<Test>::unify_ed25519(&account));
+		assert_eq!(Balances::balance(&account), 100);
+		assert_eq!(
+			<Test as Config>::AddressMap::find_key(VarAddress::solana(public)),
+			Some(account)
+		);
+	});
+}
+
+#[test]
+fn solana_account_id_conversion_resolves_linked_address() {
+	use solana_sdk::pubkey::Pubkey;
+	use sp_core::{ed25519, Pair};
+	use sp_runtime::traits::{Convert, ConvertBack};
+
+	type Conversion = <Test as pallet_solana::Config>::AccountIdConversion;
+
+	let pair = ed25519::Pair::from_seed(&[5u8; 32]);
+	let pubkey = Pubkey::from(pair.public().0);
+
+	new_test_ext().execute_with(|| {
+		assert_eq!(Conversion::convert(pubkey), AccountId::from(pair.public()));
+		assert_ne!(Conversion::convert_back(alice()), pubkey);
+
+		let proof =
+			link::LinkProof::SignMessage(pair.sign(link_payload(&alice()).message().as_bytes()));
+		assert_ok!(Babel::link(
+			RuntimeOrigin::signed(alice()),
+			VarAddress::solana(pair.public()),
+			proof
+		));
+		assert_eq!(Conversion::convert(pubkey), alice());
+		assert_eq!(Conversion::convert_back(alice()), pubkey);
+	});
+}
+
 #[test]
 fn ecdsa_verify_prehashed() {
 	let signature = const_hex::decode("f7e0d198c62821cc5817c8e935f523308301e29819f5d882f3249b9e173a614f38000ddbff446c0abfa7c7d019dbb17072b28933fc8187c973fbf03d0459f76e").unwrap();
@@ -97,3 +312,847 @@ fn ecdsa_verify_prehashed() {
 		));
 	});
 }
+
+#[test]
+fn solana_program_dispatches_runtime_call() {
+	use frame_support::traits::{fungible::Mutate, Hooks};
+	use pallet_solana::runtime::bank::Bank;
+	use parity_scale_codec::Encode;
+	use solana_sdk::{
+		hash::Hash,
+		instruction::{AccountMeta, Instruction},
+		message::SimpleAddressLoader,
+		reserved_account_keys::ReservedAccountKeys,
+		signature::{Keypair, Signer},
+		transaction::{MessageHash, SanitizedTransaction, Transaction, VersionedTransaction},
+	};
+	use sp_runtime::traits::Convert;
+
+	let keypair = Keypair::new();
+	let signer = <Test as pallet_solana::Config>::AccountIdConversion::convert(keypair.pubkey());
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(2);
+		Solana::on_initialize(2);
+		assert_ok!(Balances::mint_into(&signer, 1_000_000_000_000_000_000));
+
+		let call = RuntimeCall::Balances(pallet_balances::Call::transfer_keep_alive {
+			dest: alice(),
+			value: 100,
+		});
+		let instruction = Instruction::new_with_bytes(
+			solana::program::ID,
+			&call.encode(),
+			vec![AccountMeta::new_readonly(keypair.pubkey(), true)],
+		);
+		let mut tx = Transaction::new_with_payer(&[instruction], Some(&keypair.pubkey()));
+		tx.sign(&[&keypair], Hash::default());
+		let sanitized_tx = SanitizedTransaction::try_create(
+			VersionedTransaction::from(tx),
+			MessageHash::Compute,
+			None,
+			SimpleAddressLoader::Disabled,
+			&ReservedAccountKeys::empty_key_set(),
+		)
+		.unwrap();
+
+		let bank = Bank::<Test>::new(pallet_solana::Slot::<Test>::get());
+		assert_ok!(bank.load_execute_and_commit_sanitized_transaction(&sanitized_tx));
+		assert_eq!(Balances::balance(&alice()), 10100);
+		assert_eq!(Balances::balance(&signer), 1_000_000_000_000_000_000 - 100);
+	});
+}
+
+#[test]
+fn call_filter_works() {
+	use call_filter::DispatchValidate;
+	use sp_core::Pair;
+
+	let bob: AccountId = sp_keyring::sr25519::Keyring::Bob.pair().public().into();
+	let caller = VarAddress::Ethereum(evm_caller().into());
+	let other = VarAddress::Ethereum(H160::repeat_byte(0x22).into());
+	let allowed = |caller: &VarAddress, call: &RuntimeCall| {
+		<CallFilter as DispatchValidate<AccountId, RuntimeCall>>::validate_before_dispatch(
+			caller,
+			&alice(),
+			call,
+		)
+		.is_ok()
+	};
+	let sudo = |call: RuntimeCall| RuntimeCall::Sudo(pallet_sudo::Call::sudo { call: call.into() });
+	let batch =
+		|calls: Vec<RuntimeCall>| RuntimeCall::Utility(pallet_utility::Call::batch { calls });
+
+	let transfer = RuntimeCall::Balances(pallet_balances::Call::transfer_keep_alive {
+		dest: bob.clone(),
+		value: 100,
+	});
+	let asset_transfer = RuntimeCall::Assets(pallet_assets::Call::transfer {
+		id: 1u32.into(),
+		target: bob.clone(),
+		amount: 100,
+	});
+	let remark = RuntimeCall::System(frame_system::Call::remark { remark: vec![] });
+
+	new_test_ext().execute_with(|| {
+		// Allowed for any caller.
+		assert!(allowed(&caller, &transfer));
+		assert!(allowed(&other, &transfer));
+		// Allowed for the listed caller only.
+		assert!(allowed(&caller, &asset_transfer));
+		assert!(!allowed(&other, &asset_transfer));
+		// Not allowed.
+		assert!(!allowed(&caller, &remark));
+
+		// Nested calls are filtered as well.
+		assert!(allowed(&other, &sudo(transfer.clone())));
+		assert!(allowed(&caller, &sudo(asset_transfer.clone())));
+		assert!(!allowed(&other, &sudo(asset_transfer.clone())));
+		assert!(!allowed(&caller, &sudo(remark.clone())));
+		assert!(allowed(&other, &sudo(sudo(transfer.clone()))));
+		assert!(!allowed(&other, &sudo(sudo(sudo(transfer.clone())))));
+
+		// An allowed batch does not let the calls in it bypass the filter.
+		assert!(allowed(&other, &batch(vec![transfer.clone(), transfer.clone()])));
+		assert!(allowed(&caller, &batch(vec![transfer.clone(), asset_transfer.clone()])));
+		assert!(!allowed(&other, &batch(vec![transfer.clone(), asset_transfer])));
+		assert!(!allowed(&caller, &batch(vec![transfer.clone(), remark.clone()])));
+		assert!(!allowed(&caller, &batch(vec![sudo(remark)])));
+		assert!(!allowed(&caller, &batch(vec![batch(vec![batch(vec![transfer])])])));
+	});
+}
+
+#[test]
+fn evm_dispatch_is_filtered() {
+	use ethereum::precompile::BabelCall;
+	use fp_evm::{ExitError, ExitReason};
+	use frame_support::traits::fungible::Mutate;
+	use pallet_evm::{AddressMapping, Runner};
+	use parity_scale_codec::Encode;
+	use sp_core::U256;
+
+	let caller = evm_caller();
+	let origin = <Test as pallet_evm::Config>::AddressMapping::into_account_id(caller);
+	let dispatch = |call: RuntimeCall| {
+		<Test as pallet_evm::Config>::Runner::call(
+			caller,
+			H160::from_low_u64_be(0x400),
+			BabelCall::<Test>::dispatch { input: call.encode().into() }.into(),
+			U256::zero(),
+			1_000_000,
+			None,
+			None,
+			None,
+			Vec::new(),
+			false,
+			false,
+			None,
+			None,
+			<Test as pallet_evm::Config>::config(),
+		)
+		.unwrap()
+		.exit_reason
+	};
+
+	new_test_ext().execute_with(|| {
+		assert_ok!(Balances::mint_into(&origin, 1000));
+
+		let transfer = RuntimeCall::Balances(pallet_balances::Call::transfer_keep_alive {
+			dest: alice(),
+			value: 100,
+		});
+		assert!(matches!(dispatch(transfer), ExitReason::Succeed(_)));
+		assert_eq!(Balances::balance(&origin), 900);
+		assert_eq!(Balances::balance(&alice()), 10100);
+
+		let remark = RuntimeCall::System(frame_system::Call::remark { remark: vec![] });
+		let batch = RuntimeCall::Utility(pallet_utility::Call::batch { calls: vec![remark] });
+		assert!(matches!(
+			dispatch(batch),
+			ExitReason::Error(ExitError::Other(e)) if e == "call filtered"
+		));
+	});
+}
+
+#[test]
+fn assets_precompile_resolves_denoms() {
+	use ethereum::precompile::BabelCall;
+	use fp_evm::ExitReason;
+	use pallet_evm::Runner;
+	use precompile_utils::{
+		prelude::{keccak256, Address, UnboundedString},
+		solidity,
+	};
+	use sp_core::U256;
+
+	let call = |target: H160, input: Vec<u8>| {
+		<Test as pallet_evm::Config>::Runner::call(
+			evm_caller(),
+			target,
+			input,
+			U256::zero(),
+			1_000_000,
+			None,
+			None,
+			None,
+			Vec::new(),
+			false,
+			false,
+			None,
+			None,
+			<Test as pallet_evm::Config>::config(),
+		)
+		.unwrap()
+	};
+	let erc20_address = |denom: &str| {
+		call(
+			H160::from_low_u64_be(0x400),
+			BabelCall::<Test>::erc20_address { denom: denom.into() }.into(),
+		)
+	};
+	let denom = |asset: H160| call(asset, keccak256!("denom()")[..4].to_vec());
+
+	new_test_ext().execute_with(|| {
+		let asset = <Test as ethereum::AddressToAssetId<_>>::asset_id_to_address(1);
+		assert_ok!(Assets::force_create(RuntimeOrigin::root(), 1, alice(), true, 1));
+		assert!(matches!(denom(asset).exit_reason, ExitReason::Revert(_)));
+		assert!(matches!(erc20_address("utest").exit_reason, ExitReason::Revert(_)));
+
+		assert_ok!(Babel::register_asset(RuntimeOrigin::root(), 1, b"utest".to_vec()));
+		let info = denom(asset);
+		assert!(matches!(info.exit_reason, ExitReason::Succeed(_)));
+		let output = solidity::decode_return_value::<UnboundedString>(&info.value).unwrap();
+		assert_eq!(output.as_bytes(), b"utest");
+
+		let info = erc20_address("utest");
+		assert!(matches!(info.exit_reason, ExitReason::Succeed(_)));
+		let output = solidity::decode_return_value::<Address>(&info.value).unwrap();
+		assert_eq!(H160::from(output), asset);
+		assert!(matches!(erc20_address("uother").exit_reason, ExitReason::Revert(_)));
+
+		// Addresses of nonexistent assets aren't precompiles.
+		let info = denom(<Test as ethereum::AddressToAssetId<_>>::asset_id_to_address(2));
+		assert!(matches!(info.exit_reason, ExitReason::Succeed(_)));
+		assert!(info.value.is_empty());
+	});
+}
+
+#[test]
+fn evm_executes_and_queries_cosmwasm_contract() {
+	use ethereum::cosmwasm::CosmwasmCall;
+	use fp_evm::ExitReason;
+	use frame_support::traits::fungible::Mutate;
+	use pallet_evm::{AddressMapping, Runner};
+	use precompile_utils::{prelude::UnboundedBytes, solidity};
+	use sp_core::U256;
+	use sp_runtime::traits::Convert;
+
+	let caller = evm_caller();
+	let origin = <Test as pallet_evm::Config>::AddressMapping::into_account_id(caller);
+	let call = |input: Vec<u8>| {
+		<Test as pallet_evm::Config>::Runner::call(
+			caller,
+			H160::from_low_u64_be(0x402),
+			input,
+			U256::zero(),
+			100_000_000,
+			None,
+			None,
+			None,
+			Vec::new(),
+			false,
+			false,
+			None,
+			None,
+			<Test as pallet_evm::Config>::config(),
+		)
+		.unwrap()
+	};
+	let addr = |who: AccountId| <Test as pallet_cosmwasm::Config>::AccountToAddr::convert(who);
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(Balances::mint_into(&origin, 1_000_000_000_000_000_000));
+
+		let code = include_bytes!("../../../vendor/composable/vm-wasmi/fixtures/cw20_base.wasm");
+		assert_ok!(pallet_cosmwasm::Pallet::<Test>::upload(
+			RuntimeOrigin::signed(origin.clone()),
+			code.to_vec().try_into().unwrap(),
+		));
+		let code_id = System::events()
+			.into_iter()
+			.find_map(|record| match record.event {
+				RuntimeEvent::Cosmwasm(pallet_cosmwasm::Event::Uploaded { code_id, .. }) =>
+					Some(code_id),
+				_ => None,
+			})
+			.unwrap();
+
+		let msg = format!(
+			concat!(
+				r#"{{"name":"Test","symbol":"TEST","decimals":6,"#,
+				r#""initial_balances":[{{"address":"{}","amount":"1000"}}],"#,
+				r#""mint":null,"marketing":null}}"#,
+			),
+			addr(origin.clone())
+		);
+		let instantiate = CosmwasmCall::<Test>::instantiate {
+			code_id,
+			admin: "".into(),
+			label: "cw20".into(),
+			msg: msg.as_bytes().into(),
+			funds: vec![],
+			salt: b"salt".as_slice().into(),
+		};
+		assert!(matches!(call(instantiate.into()).exit_reason, ExitReason::Succeed(_)));
+		let contract = System::events()
+			.into_iter()
+			.find_map(|record| match record.event {
+				RuntimeEvent::Cosmwasm(pallet_cosmwasm::Event::Instantiated {
+					contract, ..
+				}) => Some(contract),
+				_ => None,
+			})
+			.unwrap();
+
+		let balance = |who: AccountId| {
+			let msg = format!(r#"{{"balance":{{"address":"{}"}}}}"#, addr(who));
+			let query = CosmwasmCall::<Test>::query {
+				contract: addr(contract.clone()).as_str().into(),
+				msg: msg.as_bytes().into(),
+			};
+			let info = call(query.into());
+			assert!(matches!(info.exit_reason, ExitReason::Succeed(_)));
+			let response = solidity::decode_return_value::<UnboundedBytes>(&info.value).unwrap();
+			String::from_utf8(response.as_bytes().to_vec()).unwrap()
+		};
+		assert_eq!(balance(origin.clone()), r#"{"balance":"1000"}"#);
+
+		let transfer = |amount: u128| CosmwasmCall::<Test>::execute {
+			contract: addr(contract.clone()).as_str().into(),
+			msg: format!(
+				r#"{{"transfer":{{"recipient":"{}","amount":"{}"}}}}"#,
+				addr(alice()),
+				amount
+			)
+			.as_bytes()
+			.into(),
+			funds: vec![],
+		};
+		assert!(matches!(call(transfer(100).into()).exit_reason, ExitReason::Succeed(_)));
+		assert_eq!(balance(origin.clone()), r#"{"balance":"900"}"#);
+		assert_eq!(balance(alice()), r#"{"balance":"100"}"#);
+
+		// A failing execution reverts without changing the state of the contract.
+		assert!(matches!(call(transfer(1000).into()).exit_reason, ExitReason::Revert(_)));
+		assert_eq!(balance(origin), r#"{"balance":"900"}"#);
+		assert_eq!(balance(alice()), r#"{"balance":"100"}"#);
+	});
+}
+
+#[test]
+fn cosmwasm_calls_evm_contract() {
+	use cosmos::precompile::{EvmExecuteMsg, EvmQueryMsg};
+	use cosmwasm_std::{Empty, QueryRequest, Uint128, WasmQuery};
+	use frame_support::{traits::fungible::Mutate, PalletId};
+	use pallet_cosmwasm::runtimes::vm::InitialStorageMutability;
+	use pallet_evm::AddressMapping;
+	use precompile_utils::{prelude::Address, solidity};
+	use sp_core::U256;
+	use sp_runtime::traits::Convert;
+
+	const GAS: u64 = 100_000_000_000;
+	const ERC20: &str = "0x0000000000000000000000000000000000000401";
+
+	let who = AccountId::from(dev_public());
+	let source: H160 = EthereumAddress::from(dev_public()).into();
+	let recipient = H160::repeat_byte(0x22);
+	let evm: AccountId = PalletId(*b"evm_call").into_account_truncating();
+
+	let execute = |sender: &AccountId, msg: EvmExecuteMsg| {
+		let mut shared = pallet_cosmwasm::Pallet::<Test>::do_create_vm_shared(
+			GAS,
+			InitialStorageMutability::ReadWrite,
+		);
+		pallet_cosmwasm::Pallet::<Test>::do_execute(
+			&mut shared,
+			sender.clone(),
+			evm.clone(),
+			Default::default(),
+			serde_json_wasm::to_vec(&msg).unwrap().try_into().unwrap(),
+		)
+	};
+	let balance_of = |address: H160| {
+		let msg = EvmQueryMsg::Call {
+			to: ERC20.into(),
+			data: solidity::encode_with_selector(0x70a08231u32, Address(address)).into(),
+			gas_limit: 100_000,
+		};
+		let request = QueryRequest::<Empty>::Wasm(WasmQuery::Smart {
+			contract_addr: <Test as pallet_cosmwasm::Config>::AccountToAddr::convert(evm.clone()),
+			msg: serde_json_wasm::to_vec(&msg).unwrap().into(),
+		});
+		let mut shared = pallet_cosmwasm::Pallet::<Test>::do_create_vm_shared(
+			GAS,
+			InitialStorageMutability::ReadOnly,
+		);
+		let response = pallet_cosmwasm::Pallet::<Test>::do_query(
+			&mut shared,
+			evm.clone(),
+			&serde_json_wasm::to_vec(&request).unwrap(),
+		)
+		.unwrap();
+		solidity::decode_return_value::<U256>(&Vec::<u8>::from(response)).unwrap()
+	};
+
+	new_test_ext().execute_with(|| {
+		assert_ok!(UnifyAccount::<Test>::unify_ecdsa(&who));
+		assert_ok!(Balances::mint_into(&who, 1000));
+		assert_eq!(balance_of(source), U256::from(1000));
+
+		// The sender calls the contract from its linked Ethereum address.
+		assert_ok!(execute(
+			&who,
+			EvmExecuteMsg::Call {
+				to: ERC20.into(),
+				data: solidity::encode_with_selector(
+					0xa9059cbbu32,
+					(Address(recipient), U256::from(100)),
+				)
+				.into(),
+				value: Uint128::zero(),
+				gas_limit: 100_000,
+			},
+		));
+		assert_eq!(balance_of(source), U256::from(900));
+		assert_eq!(balance_of(recipient), U256::from(100));
+
+		assert_ok!(execute(
+			&who,
+			EvmExecuteMsg::Call {
+				to: const_hex::encode_prefixed(recipient),
+				data: Default::default(),
+				value: Uint128::new(50),
+				gas_limit: 100_000,
+			},
+		));
+		assert_eq!(Balances::balance(&who), 850);
+		let recipient_account =
+			<Test as pallet_evm::Config>::AddressMapping::into_account_id(recipient);
+		assert_eq!(Balances::balance(&recipient_account), 150);
+
+		// Accounts without a linked Ethereum address can't call contracts, with or without value.
+		assert_ok!(Balances::mint_into(&alice(), 1000));
+		assert!(execute(
+			&alice(),
+			EvmExecuteMsg::Call {
+				to: const_hex::encode_prefixed(recipient),
+				data: Default::default(),
+				value: Uint128::new(50),
+				gas_limit: 100_000,
+			},
+		)
+		.is_err());
+		assert!(execute(
+			&alice(),
+			EvmExecuteMsg::Call {
+				to: ERC20.into(),
+				data: solidity::encode_with_selector(
+					0xa9059cbbu32,
+					(Address(recipient), U256::from(50)),
+				)
+				.into(),
+				value: Uint128::zero(),
+				gas_limit: 100_000,
+			},
+		)
+		.is_err());
+		assert_eq!(Balances::balance(&recipient_account), 150);
+	});
+}
+
+#[test]
+fn cosmwasm_queries_dispatch_contract() {
+	use cosmos::precompile::{
+		AssetMetadataResponse, NonceResponse, QueryMsg, StorageResponse, UnifiedAddressResponse,
+	};
+	use cosmwasm_std::{Binary, Empty, QueryRequest, WasmQuery};
+	use frame_support::{storage::unhashed, PalletId};
+	use pallet_cosmwasm::runtimes::vm::InitialStorageMutability;
+	use parity_scale_codec::Encode;
+	use sp_io::hashing::twox_128;
+	use sp_runtime::traits::Convert;
+
+	const GAS: u64 = 100_000_000_000;
+
+	let dispatch: AccountId = PalletId(*b"dispatch").into_account_truncating();
+	let addr = |who: AccountId| <Test as pallet_cosmwasm::Config>::AccountToAddr::convert(who);
+	// Returns the response to the query and the gas it used.
+	let query = |msg: QueryMsg| {
+		let request = QueryRequest::<Empty>::Wasm(WasmQuery::Smart {
+			contract_addr: addr(dispatch.clone()),
+			msg: serde_json_wasm::to_vec(&msg).unwrap().into(),
+		});
+		let mut shared = pallet_cosmwasm::Pallet::<Test>::do_create_vm_shared(
+			GAS,
+			InitialStorageMutability::ReadOnly,
+		);
+		let response = pallet_cosmwasm::Pallet::<Test>::do_query(
+			&mut shared,
+			dispatch.clone(),
+			&serde_json_wasm::to_vec(&request).unwrap(),
+		)
+		.map(Vec::<u8>::from);
+		(response, GAS - shared.gas.remaining())
+	};
+
+	new_test_ext().execute_with(|| {
+		let small = [&twox_128(b"Test")[..], b"small"].concat();
+		let large = [&twox_128(b"Test")[..], b"large"].concat();
+		unhashed::put_raw(&small, &[1u8; 10]);
+		unhashed::put_raw(&large, &[1u8; 1000]);
+
+		let (response, small_gas) = query(QueryMsg::Storage { key: small.into() });
+		let response: StorageResponse = serde_json_wasm::from_slice(&response.unwrap()).unwrap();
+		assert_eq!(response.value, Some(Binary::from([1u8; 10])));
+		let (response, large_gas) = query(QueryMsg::Storage { key: large.into() });
+		let response: StorageResponse = serde_json_wasm::from_slice(&response.unwrap()).unwrap();
+		assert_eq!(response.value, Some(Binary::from([1u8; 1000])));
+		// Reads are charged for every byte of the value.
+		assert!(large_gas >= small_gas + 990);
+
+		let (response, _) =
+			query(QueryMsg::Storage { key: [&twox_128(b"Test")[..], b"none"].concat().into() });
+		let response: StorageResponse = serde_json_wasm::from_slice(&response.unwrap()).unwrap();
+		assert_eq!(response.value, None);
+		let (response, _) = query(QueryMsg::Storage { key: twox_128(b"Evm").into() });
+		assert!(response.is_err());
+
+		let who = AccountId::from(dev_public());
+		assert_ok!(UnifyAccount::<Test>::unify_ecdsa(&who));
+		let (response, _) = query(QueryMsg::UnifiedAddress {
+			address: VarAddress::Ethereum(dev_public().into()).encode().into(),
+		});
+		let response: UnifiedAddressResponse =
+			serde_json_wasm::from_slice(&response.unwrap()).unwrap();
+		assert_eq!(response.account, Some(who.encode().into()));
+		assert!(response
+			.addresses
+			.contains(&VarAddress::Cosmos(dev_public().into()).encode().into()));
+		let (response, _) = query(QueryMsg::UnifiedAddress {
+			address: VarAddress::Ethereum(H160::repeat_byte(0x22).into()).encode().into(),
+		});
+		let response: UnifiedAddressResponse =
+			serde_json_wasm::from_slice(&response.unwrap()).unwrap();
+		assert_eq!(response, UnifiedAddressResponse { account: None, addresses: vec![] });
+
+		assert_ok!(Assets::force_create(RuntimeOrigin::root(), 1, alice(), true, 1));
+		assert_ok!(Babel::register_asset(RuntimeOrigin::root(), 1, b"utest".to_vec()));
+		assert_ok!(Babel::set_asset_metadata(
+			RuntimeOrigin::root(),
+			1,
+			b"Test".to_vec(),
+			b"TEST".to_vec(),
+			6,
+			false
+		));
+		let (response, _) = query(QueryMsg::AssetMetadata { denom: "utest".into() });
+		let response: AssetMetadataResponse =
+			serde_json_wasm::from_slice(&response.unwrap()).unwrap();
+		assert_eq!(
+			response,
+			AssetMetadataResponse { name: "Test".into(), symbol: "TEST".into(), decimals: 6 }
+		);
+		let (response, _) = query(QueryMsg::AssetMetadata { denom: "uother".into() });
+		assert!(response.is_err());
+
+		System::inc_account_nonce(alice());
+		System::inc_account_nonce(alice());
+		let (response, _) = query(QueryMsg::Nonce { address: addr(alice()) });
+		let response: NonceResponse = serde_json_wasm::from_slice(&response.unwrap()).unwrap();
+		assert_eq!(response.nonce, 2);
+	});
+}
+
+fn link_payload(who: &AccountId) -> link::LinkPayload {
+	use frame_support::traits::Get;
+	use parity_scale_codec::Encode;
+
+	link::LinkPayload {
+		account: who.encode(),
+		genesis_hash: System::block_hash(0).as_ref().to_vec(),
+		nonce: System::account_nonce(who).into(),
+		chain_id: <Test as pallet_evm::Config>::ChainId::get(),
+	}
+}
+
+#[test]
+fn link_ethereum_address_works() {
+	use frame_support::assert_noop;
+	use sp_core::Pair;
+	use sp_io::hashing::keccak_256;
+
+	let pair = ecdsa::Pair::from_seed(&[1u8; 32]);
+	let address = VarAddress::Ethereum(EthereumAddress::from(pair.public()));
+	let interim: AccountId = EthereumAddress::from(pair.public()).into_account_truncating();
+
+	new_test_ext().execute_with(|| {
+		assert_ok!(Babel::transfer(
+			RuntimeOrigin::signed(alice()),
+			TransferAsset::Native,
+			address.clone(),
+			100
+		));
+		assert_eq!(Balances::balance(&interim), 100);
+
+		let message = link_payload(&alice()).message();
+		let prefixed = [
+			format!("\x19Ethereum Signed Message:\n{}", message.len()).as_bytes(),
+			message.as_bytes(),
+		]
+		.concat();
+		let proof = link::LinkProof::Eip191(pair.sign_prehashed(&keccak_256(&prefixed)));
+		assert_ok!(Babel::link(RuntimeOrigin::signed(alice()), address.clone(), proof.clone()));
+		assert_eq!(Balances::balance(&interim), 0);
+		assert_eq!(Balances::balance(&alice()), 10000);
+		assert_eq!(<Test as Config>::AddressMap::find_key(&address), Some(alice()));
+
+		assert_noop!(
+			Babel::link(RuntimeOrigin::signed(alice()), address.clone(), proof),
+			Error::<Test>::AddressAlreadyLinked
+		);
+
+		let other = ecdsa::Pair::from_seed(&[2u8; 32]);
+		let proof =
+			link::LinkProof::Eip712(other.sign_prehashed(&link_payload(&alice()).eip712_digest()));
+		assert_noop!(
+			Babel::link(
+				RuntimeOrigin::signed(alice()),
+				VarAddress::Ethereum(EthereumAddress::from(other.public())),
+				proof
+			),
+			Error::<Test>::AddressKindAlreadyLinked
+		);
+
+		assert_ok!(Babel::unlink(RuntimeOrigin::signed(alice()), address.clone()));
+		assert_eq!(<Test as Config>::AddressMap::find_key(&address), None);
+		assert_noop!(
+			Babel::unlink(RuntimeOrigin::signed(alice()), address),
+			Error::<Test>::AddressNotLinked
+		);
+	});
+}
+
+#[test]
+fn link_drains_assets_of_interim_account() {
+	use drain::Drained;
+	use pallet_solana::{AccountData, AccountMeta, AccountMetadata};
+	use sp_core::Pair;
+	use sp_io::hashing::keccak_256;
+
+	let pair = ecdsa::Pair::from_seed(&[6u8; 32]);
+	let address = VarAddress::ethereum(pair.public());
+	let interim: AccountId = EthereumAddress::from(pair.public()).into_account_truncating();
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		for id in 1..=2 {
+			assert_ok!(Assets::force_create(RuntimeOrigin::root(), id, alice(), true, 1));
+			assert_ok!(Assets::mint(RuntimeOrigin::signed(alice()), id, alice(), 100));
+		}
+		assert_ok!(Babel::transfer_all(
+			RuntimeOrigin::signed(alice()),
+			vec![
+				(TransferAsset::Native, address.clone(), 100),
+				(TransferAsset::Id(1), address.clone(), 50),
+			]
+		));
+		// Assets transferred without Babel are drained as well.
+		assert_ok!(Assets::transfer(RuntimeOrigin::signed(alice()), 2, interim.clone(), 30));
+		AccountMeta::<Test>::insert(
+			&interim,
+			AccountMetadata {
+				rent_epoch: 0,
+				owner: solana_sdk::system_program::id(),
+				executable: false,
+			},
+		);
+		AccountData::<Test>::insert(
+			&interim,
+			frame_support::BoundedVec::truncate_from(vec![1, 2, 3]),
+		);
+		System::inc_sufficients(&interim);
+
+		let message = link_payload(&alice()).message();
+		let prefixed = [
+			format!("\x19Ethereum Signed Message:\n{}", message.len()).as_bytes(),
+			message.as_bytes(),
+		]
+		.concat();
+		let proof = link::LinkProof::Eip191(pair.sign_prehashed(&keccak_256(&prefixed)));
+		assert_ok!(Babel::link(RuntimeOrigin::signed(alice()), address, proof));
+
+		assert_eq!(Balances::balance(&interim), 0);
+		assert_eq!(Balances::balance(&alice()), 10000);
+		assert_eq!(Assets::balance(1, &interim), 0);
+		assert_eq!(Assets::balance(1, &alice()), 100);
+		assert_eq!(Assets::balance(2, &interim), 0);
+		assert_eq!(Assets::balance(2, &alice()), 100);
+		assert!(!AccountMeta::<Test>::contains_key(&interim));
+		assert_eq!(AccountData::<Test>::get(&alice()).into_inner(), vec![1, 2, 3]);
+		assert!(!System::account_exists(&interim));
+		assert_eq!(System::sufficients(&alice()), 1);
+		let (from, to, assets) = System::events()
+			.into_iter()
+			.find_map(|record| match record.event {
+				RuntimeEvent::Babel(Event::Drained { from, to, assets }) =>
+					Some((from, to, assets)),
+				_ => None,
+			})
+			.unwrap();
+		assert_eq!((from, to), (interim, alice()));
+		// Assets are drained in the order of the asset storage.
+		assert_eq!(assets.len(), 4);
+		assert_eq!(assets[0], Drained::SolanaAccount);
+		assert!(assets[1..3].contains(&Drained::Asset(1, 50)));
+		assert!(assets[1..3].contains(&Drained::Asset(2, 30)));
+		assert_eq!(assets[3], Drained::Native(100));
+	});
+}
+
+#[test]
+fn drain_fails_if_solana_account_exists() {
+	use extensions::unify_account::DrainBalance;
+	use pallet_solana::{AccountMeta, AccountMetadata};
+
+	let interim: AccountId = EthereumAddress::from(dev_public()).into_account_truncating();
+	let meta = AccountMetadata {
+		rent_epoch: 0,
+		owner: solana_sdk::system_program::id(),
+		executable: false,
+	};
+
+	new_test_ext().execute_with(|| {
+		AccountMeta::<Test>::insert(&interim, meta.clone());
+		AccountMeta::<Test>::insert(alice(), meta);
+		assert_eq!(
+			<Test as Config>::DrainBalance::drain_balance(&interim, &alice()),
+			Err("account draining failed: solana account exists")
+		);
+		assert!(AccountMeta::<Test>::contains_key(&interim));
+	});
+}
+
+#[test]
+fn link_ethereum_address_with_eip712_works() {
+	use sp_core::Pair;
+
+	let pair = ecdsa::Pair::from_seed(&[2u8; 32]);
+	let address = VarAddress::Ethereum(EthereumAddress::from(pair.public()));
+
+	new_test_ext().execute_with(|| {
+		let proof =
+			link::LinkProof::Eip712(pair.sign_prehashed(&link_payload(&alice()).eip712_digest()));
+		assert_ok!(Babel::link(RuntimeOrigin::signed(alice()), address.clone(), proof));
+		assert_eq!(<Test as Config>::AddressMap::find_key(&address), Some(alice()));
+	});
+}
+
+#[cfg(feature = "nostr")]
+#[test]
+fn link_nostr_address_works() {
+	use k256::schnorr::SigningKey;
+	use np_babel::NostrAddress;
+	use sp_core::H256;
+
+	let key = SigningKey::from_bytes(&[5u8; 32]).unwrap();
+	let public = H256::from_slice(&key.verifying_key().to_bytes());
+	let address = VarAddress::Nostr(NostrAddress::from(public));
+	let interim: AccountId = NostrAddress::from(public).into_account_truncating();
+
+	new_test_ext().execute_with(|| {
+		assert_ok!(Babel::transfer(
+			RuntimeOrigin::signed(alice()),
+			TransferAsset::Native,
+			address.clone(),
+			100
+		));
+		assert_eq!(Balances::balance(&interim), 100);
+
+		let created_at = 1_700_000_000;
+		let event = format!(
+			"[0,\"{}\",{},1,[],\"{}\"]",
+			sp_core::hexdisplay::HexDisplay::from(&public.0),
+			created_at,
+			link_payload(&alice()).message(),
+		);
+		let signature = key.sign_raw(&sha2_256(event.as_bytes()), &[0u8; 32]).unwrap().to_bytes();
+		let proof = link::LinkProof::Nip01 { created_at, signature };
+		assert_ok!(Babel::link(RuntimeOrigin::signed(alice()), address.clone(), proof));
+		assert_eq!(Balances::balance(&interim), 0);
+		assert_eq!(<Test as Config>::AddressMap::find_key(&address), Some(alice()));
+	});
+}
+
+#[cfg(not(feature = "nostr"))]
+#[test]
+fn nip01_proof_is_unsupported_without_nostr() {
+	use frame_support::assert_noop;
+
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			Babel::link(
+				RuntimeOrigin::signed(alice()),
+				VarAddress::Ethereum(H160::repeat_byte(1).into()),
+				link::LinkProof::Nip01 { created_at: 0, signature: [0u8; 64] }
+			),
+			Error::<Test>::UnsupportedProof
+		);
+	});
+}
+
+#[test]
+fn link_cosmos_address_works() {
+	use np_babel::CosmosAddress;
+	use sp_core::Pair;
+
+	let pair = ecdsa::Pair::from_seed(&[3u8; 32]);
+	let cosmos_address = CosmosAddress::from(pair.public());
+	let address = VarAddress::cosmos(pair.public());
+
+	new_test_ext().execute_with(|| {
+		let sign_doc = link_payload(&alice()).adr036_sign_doc(&cosmos_address.to_string());
+		let signature = pair.sign_prehashed(&sha2_256(sign_doc.as_bytes()));
+		let proof = link::LinkProof::Adr036 {
+			public: pair.public(),
+			signature: signature.0[..64].try_into().unwrap(),
+		};
+		assert_ok!(Babel::link(RuntimeOrigin::signed(alice()), address.clone(), proof));
+		assert_eq!(<Test as Config>::AddressMap::find_key(&address), Some(alice()));
+	});
+}
+
+#[test]
+fn link_solana_address_works() {
+	use frame_support::assert_noop;
+	use sp_core::{ed25519, Pair};
+
+	let pair = ed25519::Pair::from_seed(&[4u8; 32]);
+	let address = VarAddress::solana(pair.public());
+
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			Babel::link(
+				RuntimeOrigin::signed(alice()),
+				address.clone(),
+				link::LinkProof::SignMessage(pair.sign(b"Link to another account"))
+			),
+			Error::<Test>::InvalidProof
+		);
+
+		let proof =
+			link::LinkProof::SignMessage(pair.sign(link_payload(&alice()).message().as_bytes()));
+		assert_ok!(Babel::link(RuntimeOrigin::signed(alice()), address.clone(), proof));
+		assert_eq!(<Test as Config>::AddressMap::find_key(&address), Some(alice()));
+	});
+}
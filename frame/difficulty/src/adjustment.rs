@@ -0,0 +1,182 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Difficulty adjustment algorithms.
+
+use core::marker::PhantomData;
+use frame_support::{pallet_prelude::*, traits::Get};
+use np_arithmetic::{U256, U512};
+
+/// The block difficulty adjustment started at.
+#[derive(
+	Clone, Copy, Default, PartialEq, Eq, Encode, Decode, MaxEncodedLen, TypeInfo, RuntimeDebug,
+)]
+pub struct Anchor {
+	pub number: u64,
+	pub timestamp: u64,
+	pub difficulty: U256,
+}
+
+/// Identifier of a difficulty adjustment algorithm.
+pub type AdjustmentId = [u8; 4];
+
+/// Difficulty adjustment algorithm.
+pub trait DifficultyAdjustment {
+	/// Identifier of the algorithm, which moves the anchor to the block it changes at.
+	fn id() -> AdjustmentId;
+
+	/// Number of the most recent blocks the algorithm looks back.
+	fn window() -> u32;
+
+	/// Returns the difficulty of the block following `number`.
+	///
+	/// `window` holds the timestamp and difficulty of the most recent blocks up to `number`,
+	/// oldest first, and is never empty.
+	fn next_difficulty(
+		number: u64,
+		window: &[(u64, U256)],
+		anchor: &Anchor,
+		target_block_time: u64,
+	) -> U256;
+}
+
+/// Maximum solve time counted, in multiples of the target block time.
+const MAX_SOLVE_TIME: u64 = 6;
+
+/// LWMA-3 over the last `N` solve times.
+///
+/// <https://github.com/zawy12/difficulty-algorithms/issues/3>
+///
+/// ```text
+/// next_D = avg_D * T * (N + 1) * N / 2 / sum(i * t_i)
+/// where
+///   t_i = solve time of the i-th block in the window, in [1, 6T]
+///   T   = desired average block time
+/// ```
+///
+/// Timestamps are made monotonic before taking solve times, so that a timestamp in the past
+/// cannot offset one in the future. The difficulty rises by at least 8% after three blocks
+/// solved in less than 80% of a block time.
+pub struct Lwma3<N>(PhantomData<N>);
+
+impl<N: Get<u32>> DifficultyAdjustment for Lwma3<N> {
+	fn id() -> AdjustmentId {
+		*b"lwm3"
+	}
+
+	fn window() -> u32 {
+		N::get().saturating_add(1)
+	}
+
+	fn next_difficulty(
+		_number: u64,
+		window: &[(u64, U256)],
+		_anchor: &Anchor,
+		target_block_time: u64,
+	) -> U256 {
+		let (_, last_difficulty) = window[window.len() - 1];
+		let n = window.len() as u64 - 1;
+		if n == 0 {
+			return last_difficulty
+		}
+
+		let mut previous = window[0].0;
+		let mut weighted = 0u64;
+		let mut difficulties = U256::zero();
+		let mut recent = 0u64;
+		for (i, &(timestamp, difficulty)) in window[1..].iter().enumerate() {
+			let timestamp = timestamp.max(previous.saturating_add(1));
+			let solve_time = (timestamp - previous).min(MAX_SOLVE_TIME * target_block_time);
+			previous = timestamp;
+
+			weighted = weighted.saturating_add((i as u64 + 1).saturating_mul(solve_time));
+			difficulties = difficulties.saturating_add(difficulty);
+			if i as u64 + 3 >= n {
+				recent = recent.saturating_add(solve_time);
+			}
+		}
+
+		let k = n * (n + 1) / 2;
+		// Limits the rise of the difficulty to 10 times in a window.
+		let weighted = weighted.max(k * target_block_time / 10);
+		let next = (difficulties / n)
+			.saturating_mul(U256::from(target_block_time))
+			.saturating_mul(U256::from(k)) /
+			U256::from(weighted);
+
+		if n >= 3 && recent < target_block_time * 8 / 10 {
+			next.max(last_difficulty.saturating_mul(U256::from(108)) / 100)
+		} else {
+			next
+		}
+	}
+}
+
+/// ASERT (aserti3-2d), halving or doubling the difficulty for each `HalfLife` milliseconds the
+/// chain is ahead of or behind the schedule since the anchor.
+///
+/// <https://upgradespecs.bitcoincashnode.org/2020-11-15-asert/>
+///
+/// ```text
+/// next_target = anchor_target * 2^((t - T * (h - anchor_h)) / half_life)
+/// ```
+pub struct Asert<HalfLife>(PhantomData<HalfLife>);
+
+impl<HalfLife: Get<u64>> DifficultyAdjustment for Asert<HalfLife> {
+	fn id() -> AdjustmentId {
+		*b"asrt"
+	}
+
+	fn window() -> u32 {
+		1
+	}
+
+	fn next_difficulty(
+		number: u64,
+		window: &[(u64, U256)],
+		anchor: &Anchor,
+		target_block_time: u64,
+	) -> U256 {
+		let (timestamp, _) = window[window.len() - 1];
+		let time_delta = timestamp as i128 - anchor.timestamp as i128;
+		let height_delta = number.saturating_sub(anchor.number) as i128;
+		let exponent = (time_delta - target_block_time as i128 * height_delta) * 65536 /
+			HalfLife::get().max(1) as i128;
+
+		// 2^x for x in [0, 1), approximated by a cubic polynomial in 16.16 fixed point.
+		let shifts = exponent >> 16;
+		let frac = (exponent & 0xffff) as u128;
+		let factor = 65536 +
+			((195_766_423_245_049 * frac +
+				971_821_376 * frac * frac +
+				5127 * frac * frac * frac +
+				(1 << 47)) >>
+				48);
+
+		let anchor_target = U256::max_value() / anchor.difficulty.max(U256::one());
+		let target = anchor_target.full_mul(U256::from(factor as u64));
+		let target = match shifts {
+			s if s < 0 => target >> (-s).min(511) as usize,
+			s if s <= 200 => target << s as usize,
+			_ => U512::max_value(),
+		} >> 16;
+
+		let target = U256::try_from(target).unwrap_or(U256::max_value()).max(U256::one());
+		U256::max_value() / target
+	}
+}
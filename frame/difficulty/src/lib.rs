@@ -0,0 +1,223 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Difficulty adjustment with interchangeable algorithms.
+//!
+//! The pallet keeps the timestamps and difficulties of a bounded window of recent blocks, and
+//! runs the [`DifficultyAdjustment`] of the runtime over it whenever the timestamp is set.
+//! [`Lwma3`] and [`Asert`] are provided.
+//!
+//! A timestamp must be greater than the median of the last `MedianTimeSpan` timestamps. How far
+//! ahead of the wall clock a timestamp may be is checked by the node; the algorithms bound the
+//! solve times they count so that a timestamp in the future has a limited effect.
+//!
+//! Whichever algorithm is used, the runtime implements `DifficultyApi` with
+//! [`Pallet::difficulty`], and `TimestampValidationApi` with [`Pallet::median_time_past`] and
+//! [`Pallet::block_timestamp`] for the node to check timestamps on import.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod adjustment;
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod tests;
+
+pub use adjustment::{AdjustmentId, Anchor, Asert, DifficultyAdjustment, Lwma3};
+pub use pallet::*;
+
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use frame_support::{
+	traits::{Get, IsSubType, OnTimestampSet},
+	BoundedVec,
+};
+use np_arithmetic::{
+	traits::{SaturatedConversion, UniqueSaturatedFrom},
+	U256,
+};
+use np_consensus_pow::timestamp;
+use parity_scale_codec::FullCodec;
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+	use frame_support::pallet_prelude::*;
+
+	#[pallet::pallet]
+	pub struct Pallet<T>(_);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config + pallet_timestamp::Config {
+		/// Difficulty for cryptographic puzzles in PoW consensus.
+		type Difficulty: FullCodec
+			+ Default
+			+ Copy
+			+ MaybeSerializeDeserialize
+			+ Debug
+			+ MaxEncodedLen
+			+ TypeInfo
+			+ UniqueSaturatedFrom<U256>
+			+ Into<U256>
+			+ PartialOrd;
+
+		/// Difficulty adjustment algorithm.
+		type Adjustment: DifficultyAdjustment;
+
+		/// Desired block time in milliseconds.
+		#[pallet::constant]
+		type TargetBlockTime: Get<Self::Moment>;
+
+		/// Minimum difficulty to be adjusted to.
+		#[pallet::constant]
+		type MinDifficulty: Get<Self::Difficulty>;
+
+		/// Number of recent blocks kept for the adjustment and the median time past.
+		///
+		/// This must be greater or equal than the window of the adjustment and
+		/// `MedianTimeSpan`.
+		#[pallet::constant]
+		type MaxWindow: Get<u32>;
+
+		/// Number of recent timestamps the median time past is taken over.
+		#[pallet::constant]
+		type MedianTimeSpan: Get<u32>;
+	}
+
+	/// Target difficulty for the next block.
+	#[pallet::storage]
+	pub type Difficulty<T: Config> = StorageValue<_, T::Difficulty, ValueQuery>;
+
+	/// Timestamps and difficulties of the most recent blocks, oldest first.
+	#[pallet::storage]
+	pub type Window<T: Config> =
+		StorageValue<_, BoundedVec<(T::Moment, T::Difficulty), T::MaxWindow>, ValueQuery>;
+
+	/// The block difficulty adjustment started at, with the algorithm it started.
+	///
+	/// The anchor moves to the block where the algorithm changes, so that the new one does not
+	/// account for the blocks adjusted by the previous one.
+	#[pallet::storage]
+	pub type AnchorBlock<T: Config> = StorageValue<_, (AdjustmentId, Anchor), OptionQuery>;
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig<T: Config> {
+		pub initial_difficulty: T::Difficulty,
+	}
+
+	impl<T: Config> Default for GenesisConfig<T> {
+		fn default() -> Self {
+			GenesisConfig { initial_difficulty: T::Difficulty::saturated_from(U256::from(10000)) }
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> BuildGenesisConfig for GenesisConfig<T> {
+		fn build(&self) {
+			assert!(self.initial_difficulty.into() != U256::from(0));
+			Difficulty::<T>::put(self.initial_difficulty);
+		}
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn integrity_test() {
+			let max_window = T::MaxWindow::get();
+			assert!(max_window >= T::Adjustment::window());
+			assert!(max_window >= T::MedianTimeSpan::get());
+			assert!(T::MedianTimeSpan::get() > 0);
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Target difficulty for the next block.
+	pub fn difficulty() -> T::Difficulty {
+		Difficulty::<T>::get()
+	}
+
+	/// Median of the last `MedianTimeSpan` timestamps, if any.
+	pub fn median_time_past() -> Option<T::Moment> {
+		let window = Window::<T>::get().into_inner();
+		timestamp::median_time_past(
+			window.into_iter().map(|(timestamp, _)| timestamp),
+			T::MedianTimeSpan::get(),
+		)
+	}
+
+	/// Timestamp set by the `pallet_timestamp` call among `calls`.
+	pub fn block_timestamp<'a>(
+		calls: impl IntoIterator<Item = &'a <T as frame_system::Config>::RuntimeCall>,
+	) -> Option<T::Moment>
+	where
+		<T as frame_system::Config>::RuntimeCall: IsSubType<pallet_timestamp::Call<T>>,
+	{
+		calls.into_iter().find_map(|call| match call.is_sub_type() {
+			Some(pallet_timestamp::Call::set { now }) => Some(*now),
+			_ => None,
+		})
+	}
+}
+
+impl<T: Config> OnTimestampSet<T::Moment> for Pallet<T> {
+	fn on_timestamp_set(now: T::Moment) {
+		if let Some(median_time_past) = Self::median_time_past() {
+			assert!(now > median_time_past, "Timestamp must be greater than the median time past");
+		}
+
+		let difficulty = Difficulty::<T>::get();
+		let number = frame_system::Pallet::<T>::block_number().saturated_into::<u64>();
+		let anchor = match AnchorBlock::<T>::get() {
+			Some((id, anchor)) if id == T::Adjustment::id() => anchor,
+			_ => {
+				let anchor = Anchor {
+					number,
+					timestamp: now.saturated_into(),
+					difficulty: difficulty.into(),
+				};
+				AnchorBlock::<T>::put((T::Adjustment::id(), anchor));
+				anchor
+			},
+		};
+
+		let mut window = Window::<T>::get().into_inner();
+		timestamp::push_bounded(&mut window, (now, difficulty), T::MaxWindow::get());
+
+		let span = (T::Adjustment::window() as usize).clamp(1, window.len());
+		let recent: Vec<_> = window[window.len() - span..]
+			.iter()
+			.map(|(timestamp, difficulty)| ((*timestamp).saturated_into(), (*difficulty).into()))
+			.collect();
+		let next = T::Adjustment::next_difficulty(
+			number,
+			&recent,
+			&anchor,
+			T::TargetBlockTime::get().saturated_into(),
+		);
+
+		let mut difficulty = T::Difficulty::saturated_from(next);
+		if difficulty < T::MinDifficulty::get() {
+			difficulty = T::MinDifficulty::get();
+		}
+
+		Difficulty::<T>::put(difficulty);
+		Window::<T>::put(BoundedVec::truncate_from(window));
+	}
+}
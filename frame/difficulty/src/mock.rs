@@ -0,0 +1,112 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+#![cfg(test)]
+
+use crate as pallet_difficulty;
+
+use super::*;
+use frame_support::{
+	derive_impl, parameter_types,
+	sp_runtime::BuildStorage,
+	traits::{ConstU32, ConstU64},
+};
+use sp_io::TestExternalities;
+
+#[frame_support::runtime]
+mod runtime {
+	#[runtime::runtime]
+	#[runtime::derive(RuntimeCall, RuntimeEvent, RuntimeError, RuntimeOrigin, RuntimeTask)]
+	pub struct Test;
+
+	#[runtime::pallet_index(0)]
+	pub type System = frame_system;
+
+	#[runtime::pallet_index(1)]
+	pub type Timestamp = pallet_timestamp;
+
+	#[runtime::pallet_index(2)]
+	pub type Difficulty = pallet_difficulty;
+}
+
+type Block = frame_system::mocking::MockBlock<Test>;
+
+#[derive_impl(frame_system::config_preludes::TestDefaultConfig)]
+impl frame_system::Config for Test {
+	type Block = Block;
+}
+
+#[derive_impl(pallet_timestamp::config_preludes::TestDefaultConfig)]
+impl pallet_timestamp::Config for Test {
+	type OnTimestampSet = Difficulty;
+}
+
+parameter_types! {
+	pub MinDifficulty: U256 = U256::from(1000);
+	pub storage UseAsert: bool = false;
+}
+
+pub type MockLwma3 = Lwma3<ConstU32<3>>;
+pub type MockAsert = Asert<ConstU64<100_000>>;
+
+/// LWMA-3, or ASERT once `UseAsert` is set as a runtime upgrade would.
+pub struct Adjustment;
+
+impl DifficultyAdjustment for Adjustment {
+	fn id() -> AdjustmentId {
+		if UseAsert::get() {
+			MockAsert::id()
+		} else {
+			MockLwma3::id()
+		}
+	}
+
+	fn window() -> u32 {
+		MockLwma3::window().max(MockAsert::window())
+	}
+
+	fn next_difficulty(
+		number: u64,
+		window: &[(u64, U256)],
+		anchor: &Anchor,
+		target_block_time: u64,
+	) -> U256 {
+		if UseAsert::get() {
+			MockAsert::next_difficulty(number, window, anchor, target_block_time)
+		} else {
+			MockLwma3::next_difficulty(number, window, anchor, target_block_time)
+		}
+	}
+}
+
+impl Config for Test {
+	type Difficulty = U256;
+	type Adjustment = Adjustment;
+	type TargetBlockTime = ConstU64<10_000>;
+	type MinDifficulty = MinDifficulty;
+	type MaxWindow = ConstU32<5>;
+	type MedianTimeSpan = ConstU32<3>;
+}
+
+pub fn new_test_ext() -> TestExternalities {
+	let mut t = frame_system::GenesisConfig::<Test>::default().build_storage().unwrap();
+	pallet_difficulty::GenesisConfig::<Test> { initial_difficulty: U256::from(10_000_000) }
+		.assimilate_storage(&mut t)
+		.unwrap();
+	TestExternalities::new(t)
+}
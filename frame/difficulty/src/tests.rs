@@ -0,0 +1,229 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+#![cfg(test)]
+
+use super::{
+	mock::{Difficulty as Pallet, *},
+	Anchor, AnchorBlock, Asert, DifficultyAdjustment, Lwma3, Window,
+};
+use frame_support::traits::{ConstU32, ConstU64, OnTimestampSet};
+use np_arithmetic::U256;
+
+const TARGET_BLOCK_TIME: u64 = 10_000;
+
+fn set_timestamp(number: u64, now: u64) {
+	System::set_block_number(number);
+	Pallet::on_timestamp_set(now);
+}
+
+#[test]
+fn window_should_be_bounded() {
+	new_test_ext().execute_with(|| {
+		for number in 1..=7 {
+			set_timestamp(number, number * TARGET_BLOCK_TIME);
+		}
+
+		let timestamps: Vec<_> = Window::<Test>::get().iter().map(|(t, _)| *t).collect();
+		assert_eq!(timestamps, vec![30_000, 40_000, 50_000, 60_000, 70_000]);
+		assert_eq!(
+			AnchorBlock::<Test>::get(),
+			Some((
+				MockLwma3::id(),
+				Anchor { number: 1, timestamp: 10_000, difficulty: U256::from(10_000_000) }
+			))
+		);
+		// Blocks on target keep the difficulty.
+		assert_eq!(Pallet::difficulty(), U256::from(10_000_000));
+	});
+}
+
+#[test]
+fn anchor_should_move_when_adjustment_changes() {
+	new_test_ext().execute_with(|| {
+		// Blocks solved in half the target time raise the difficulty.
+		for number in 1..=3 {
+			set_timestamp(number, number * TARGET_BLOCK_TIME / 2);
+		}
+		assert_eq!(AnchorBlock::<Test>::get().map(|(id, _)| id), Some(MockLwma3::id()));
+		let difficulty = Pallet::difficulty();
+		assert!(difficulty > U256::from(10_000_000));
+
+		UseAsert::set(&true);
+		set_timestamp(4, 4 * TARGET_BLOCK_TIME / 2);
+		assert_eq!(
+			AnchorBlock::<Test>::get(),
+			Some((MockAsert::id(), Anchor { number: 4, timestamp: 20_000, difficulty }))
+		);
+		// Not behind the schedule of the new anchor, unlike the one of the first block.
+		assert_eq!(Pallet::difficulty(), difficulty);
+
+		set_timestamp(5, 20_000 + TARGET_BLOCK_TIME);
+		assert_eq!(Pallet::difficulty(), difficulty);
+		assert_eq!(AnchorBlock::<Test>::get().map(|(_, anchor)| anchor.number), Some(4));
+	});
+}
+
+#[test]
+fn median_time_past_should_work() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(Pallet::median_time_past(), None);
+
+		set_timestamp(1, 10_000);
+		set_timestamp(2, 20_000);
+		set_timestamp(3, 40_000);
+		assert_eq!(Pallet::median_time_past(), Some(20_000));
+
+		set_timestamp(4, 30_000);
+		assert_eq!(Pallet::median_time_past(), Some(30_000));
+	});
+}
+
+#[test]
+#[should_panic(expected = "Timestamp must be greater than the median time past")]
+fn timestamp_at_median_time_past_should_fail() {
+	new_test_ext().execute_with(|| {
+		set_timestamp(1, 10_000);
+		set_timestamp(2, 20_000);
+		set_timestamp(3, 40_000);
+		set_timestamp(4, 20_000);
+	});
+}
+
+#[test]
+fn block_timestamp_should_work() {
+	let calls = [
+		RuntimeCall::System(frame_system::Call::remark { remark: vec![] }),
+		RuntimeCall::Timestamp(pallet_timestamp::Call::set { now: 42 }),
+	];
+	assert_eq!(Pallet::block_timestamp(&calls), Some(42));
+	assert_eq!(Pallet::block_timestamp(&calls[..1]), None);
+}
+
+#[test]
+fn difficulty_should_not_go_below_minimum() {
+	new_test_ext().execute_with(|| {
+		for number in 1..=20 {
+			set_timestamp(number, number * 100 * TARGET_BLOCK_TIME);
+		}
+		assert_eq!(Pallet::difficulty(), MinDifficulty::get());
+	});
+}
+
+#[test]
+fn lwma3_should_work() {
+	let blocks = |timestamps: &[u64]| -> Vec<(u64, U256)> {
+		timestamps.iter().map(|t| (*t, U256::from(1000))).collect()
+	};
+
+	// The difficulty rises at most 10 times in a window.
+	let window = blocks(&[0, 1000, 2000]);
+	let next = Lwma3::<ConstU32<2>>::next_difficulty(2, &window, &Anchor::default(), 10_000);
+	assert_eq!(next, U256::from(10_000));
+
+	// Timestamps in the past count as one millisecond after the previous, weighted twice.
+	let window = blocks(&[0, 60_000, 0]);
+	let next = Lwma3::<ConstU32<2>>::next_difficulty(2, &window, &Anchor::default(), 10_000);
+	assert_eq!(next, U256::from(1000 * 10_000 * 3 / 60_002));
+
+	// Three fast blocks raise the difficulty by 8% at least.
+	let window = blocks(&[0, 60_000, 120_000, 122_000, 124_000, 126_000]);
+	let next = Lwma3::<ConstU32<5>>::next_difficulty(5, &window, &Anchor::default(), 10_000);
+	assert_eq!(next, U256::from(1080));
+}
+
+#[test]
+fn asert_should_follow_schedule() {
+	type Adjustment = Asert<ConstU64<100_000>>;
+	let anchor = Anchor { number: 10, timestamp: 100_000, difficulty: U256::from(1 << 20) };
+
+	let next = |number: u64, timestamp: u64| {
+		Adjustment::next_difficulty(number, &[(timestamp, U256::zero())], &anchor, 10_000)
+	};
+	assert_eq!(next(10, 100_000), U256::from(1 << 20));
+	assert_eq!(next(20, 200_000), U256::from(1 << 20));
+	// One half-life behind the schedule halves the difficulty, ahead of it doubles.
+	assert_eq!(next(20, 300_000), U256::from(1 << 19));
+	assert_eq!(next(20, 100_000), U256::from(1 << 21));
+}
+
+/// Simulates `blocks` blocks mined at `hashrate(number)` hashes per millisecond, returning their
+/// solve times and the difficulties after them.
+fn simulate<A: DifficultyAdjustment>(
+	blocks: u64,
+	hashrate: impl Fn(u64) -> u64,
+) -> (Vec<u64>, Vec<U256>) {
+	let mut seed = 0x2545_f491_4f6c_dd1du64;
+	let mut random = move || {
+		seed ^= seed << 13;
+		seed ^= seed >> 7;
+		seed ^= seed << 17;
+		((seed >> 11) + 1) as f64 / (1u64 << 53) as f64
+	};
+
+	let mut difficulty = U256::from(TARGET_BLOCK_TIME * 1000);
+	let anchor = Anchor { number: 0, timestamp: 0, difficulty };
+	let mut timestamp = 0;
+	let mut window = Vec::new();
+	let (mut solve_times, mut difficulties) = (Vec::new(), Vec::new());
+	for number in 1..=blocks {
+		// Solve times are exponentially distributed around difficulty / hashrate.
+		let mean = difficulty.low_u64() as f64 / hashrate(number) as f64;
+		let solve_time = (-random().ln() * mean) as u64;
+
+		timestamp += solve_time;
+		window.push((timestamp, difficulty));
+		let excess = window.len().saturating_sub(A::window() as usize);
+		window.drain(..excess);
+		difficulty = A::next_difficulty(number, &window, &anchor, TARGET_BLOCK_TIME);
+
+		solve_times.push(solve_time);
+		difficulties.push(difficulty);
+	}
+	(solve_times, difficulties)
+}
+
+/// Checks that block times converge to the target after the hashrate changes tenfold.
+fn check_convergence<A: DifficultyAdjustment>() {
+	for shock in [10_000, 100] {
+		let (solve_times, difficulties) =
+			simulate::<A>(3000, |number| if number < 1000 { 1000 } else { shock });
+
+		let average =
+			|solve_times: &[u64]| solve_times.iter().sum::<u64>() / solve_times.len() as u64;
+		let before = average(&solve_times[..1000]);
+		let after = average(&solve_times[1500..]);
+		assert!(before.abs_diff(TARGET_BLOCK_TIME) < TARGET_BLOCK_TIME / 10, "{before}");
+		assert!(after.abs_diff(TARGET_BLOCK_TIME) < TARGET_BLOCK_TIME / 10, "{after}");
+
+		let expected = U256::from(shock * TARGET_BLOCK_TIME);
+		let difficulty =
+			difficulties[2500..].iter().fold(U256::zero(), |sum, d| sum + *d) / U256::from(500);
+		assert!(difficulty > expected * 8 / 10 && difficulty < expected * 12 / 10, "{difficulty}");
+	}
+}
+
+#[test]
+fn lwma3_should_converge_after_hashrate_shock() {
+	check_convergence::<Lwma3<ConstU32<60>>>();
+}
+
+#[test]
+fn asert_should_converge_after_hashrate_shock() {
+	check_convergence::<Asert<ConstU64<{ 100 * TARGET_BLOCK_TIME }>>>();
+}
@@ -15,7 +15,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{mock::*, traits::UniqueMultimap, Error};
+use crate::{
+	mock::*,
+	traits::{UniqueMap, UniqueMultimap},
+	Error,
+};
 use std::collections::BTreeSet;
 
 #[test]
@@ -38,3 +42,31 @@ fn unique_multimap_works() {
 		assert!(Multimap::remove_all(0));
 	});
 }
+
+#[test]
+fn unique_map_replaces_values() {
+	new_test_ext().execute_with(|| {
+		assert!(matches!(<Multimap as UniqueMap<_, _>>::try_insert(0, 1000), Ok(true)));
+		assert!(matches!(<Multimap as UniqueMap<_, _>>::try_insert(0, 1001), Ok(true)));
+		assert_eq!(<Multimap as UniqueMap<_, _>>::get(0), Some(1001));
+		assert_eq!(<Multimap as UniqueMap<_, _>>::find_key(1001), Some(0));
+		// the replaced value is no longer indexed
+		assert_eq!(<Multimap as UniqueMap<_, _>>::find_key(1000), None);
+		assert!(matches!(<Multimap as UniqueMap<_, _>>::try_insert(1, 1000), Ok(true)));
+
+		// every value of the key is replaced
+		assert!(matches!(<Multimap as UniqueMultimap<_, _>>::try_insert(2, 2000), Ok(true)));
+		assert!(matches!(<Multimap as UniqueMultimap<_, _>>::try_insert(2, 2001), Ok(true)));
+		assert!(matches!(<Multimap as UniqueMap<_, _>>::try_insert(2, 2002), Ok(true)));
+		assert_eq!(<Multimap as UniqueMultimap<_, _>>::get(2), BTreeSet::from_iter(vec![2002]));
+		assert_eq!(<Multimap as UniqueMap<_, _>>::find_key(2000), None);
+		assert_eq!(<Multimap as UniqueMap<_, _>>::find_key(2001), None);
+
+		// duplicate value leaves the map unchanged
+		assert!(matches!(
+			<Multimap as UniqueMap<_, _>>::try_insert(2, 1000),
+			Err(Error::<Test>::DuplicateValue)
+		));
+		assert_eq!(<Multimap as UniqueMap<_, _>>::get(2), Some(2002));
+	});
+}
@@ -87,9 +87,13 @@ impl<T: Config<I>, I: 'static> UniqueMap<T::Key, T::Value> for Pallet<T, I> {
 				Error::<T, I>::DuplicateValue
 			);
 
-			*values = BTreeSet::from([value.clone()])
+			let replaced = BTreeSet::from([value.clone()])
 				.try_into()
 				.map_err(|_| Error::<T, I>::CapacityOverflow)?;
+			core::mem::replace(values, replaced)
+				.into_iter()
+				.filter(|v| *v != value)
+				.for_each(Index::<T, I>::remove);
 			Index::<T, I>::insert(value, key.clone());
 
 			Ok(true)
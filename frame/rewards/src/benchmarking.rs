@@ -19,12 +19,18 @@
 #![cfg(feature = "runtime-benchmarks")]
 
 use super::*;
+use alloc::vec;
 use frame_benchmarking::v2::*;
 use frame_support::{
-	sp_runtime::traits::{Get, One},
+	sp_runtime::{
+		traits::{Get, Header as _, One},
+		Digest, DigestItem,
+	},
 	traits::Hooks,
 };
-use frame_system::RawOrigin;
+use frame_system::{pallet_prelude::HeaderFor, RawOrigin};
+use np_consensus_pow::POW_ENGINE_ID;
+use parity_scale_codec::Encode;
 
 const SEED: u32 = 0;
 
@@ -33,10 +39,42 @@ mod benchmarks {
 	use super::*;
 
 	#[benchmark]
-	fn coinbase(n: Linear<1, { T::MaxRewardSplits::get() }>) -> Result<(), BenchmarkError> {
+	fn coinbase(
+		n: Linear<1, { T::MaxRewardSplits::get() }>,
+		u: Linear<0, { T::MaxUncles::get() }>,
+	) -> Result<(), BenchmarkError> {
 		type System<T> = frame_system::Pallet<T>;
 
-		let mut reward = T::EmissionSchedule::get();
+		// Uncles are siblings of the parent block, with their author encoded in the `PreRuntime`
+		// digest as the node does, padded to the maximum length.
+		let number = T::UncleGenerations::get().max(One::one()) + One::one();
+		System::<T>::set_block_number(number);
+		let parent = number - One::one();
+		let grandparent = parent - One::one();
+		frame_system::BlockHash::<T>::insert(grandparent, T::Hash::default());
+		frame_system::BlockHash::<T>::insert(parent, T::Hash::default());
+		let uncles: Vec<HeaderFor<T>> = (0..u)
+			.map(|index| {
+				let author: T::AccountId = account("uncle", index, SEED);
+				let digest =
+					Digest { logs: vec![DigestItem::PreRuntime(POW_ENGINE_ID, author.encode())] };
+				let mut uncle = HeaderFor::<T>::new(
+					parent,
+					Default::default(),
+					Default::default(),
+					T::Hash::default(),
+					digest,
+				);
+				// At most 5 bytes of item tag and length prefix are added along with the padding.
+				let padding =
+					(T::MaxUncleLen::get() as usize).saturating_sub(uncle.encoded_size() + 5);
+				uncle.digest_mut().push(DigestItem::Other(vec![0; padding]));
+				uncle
+			})
+			.collect();
+
+		let mut reward = Pallet::<T>::block_reward()
+			.saturating_add(Pallet::<T>::nephew_reward(Pallet::<T>::emission(), u));
 		let mut rewards: Vec<(T::AccountId, BalanceOf<T>)> = Vec::new();
 		let payout = T::MinPayout::get();
 		for index in 1..n {
@@ -47,37 +85,78 @@ mod benchmarks {
 		rewards.push((account("miner", n, SEED), reward));
 
 		#[extrinsic_call]
-		_(RawOrigin::None, rewards.clone());
+		_(RawOrigin::None, rewards.clone(), uncles);
 
 		assert_eq!(Processed::<T>::get(), true);
-		assert_eq!(Rewards::<T>::get(System::<T>::block_number()), rewards);
-		assert_eq!(RewardLocks::<T>::get(account::<T::AccountId>("miner", n, SEED)), Some(payout));
+		assert_eq!(Rewards::<T>::get(number), rewards);
+		assert_eq!(Uncles::<T>::get(number).len() as u32, u);
+		let schedule = Pallet::<T>::reward_schedule(&account("miner", n, SEED));
+		assert_eq!(schedule.locked, payout);
 
 		Ok(())
 	}
 
 	#[benchmark]
-	fn on_finalize(n: Linear<1, { T::MaxRewardSplits::get() }>) {
+	fn on_finalize() {
 		type Rewards<T> = Pallet<T>;
 		type System<T> = frame_system::Pallet<T>;
 
-		let number = System::<T>::block_number() + One::one();
-		let mut reward = T::EmissionSchedule::get();
-		let mut rewards: Vec<(T::AccountId, BalanceOf<T>)> = Vec::new();
-		let payout = T::MinPayout::get();
-		for index in 1..n {
-			reward -= payout;
-			rewards.push((account("miner", index, SEED), payout));
-		}
-		rewards.push((account("miner", n, SEED), reward));
-		Rewards::<T>::insert_coinbase(number, rewards);
+		let number = System::<T>::block_number();
+		Processed::<T>::put(true);
 
 		#[block]
 		{
 			Rewards::<T>::on_finalize(number);
-			Rewards::<T>::on_initialize(number + T::MaturationTime::get());
 		}
 	}
 
+	#[benchmark]
+	fn set_emission_curve() -> Result<(), BenchmarkError> {
+		let origin =
+			T::UpdateOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+		let curve = T::DefaultEmissionCurve::get();
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, curve.clone());
+
+		assert_eq!(Curve::<T>::get(), curve);
+
+		Ok(())
+	}
+
+	#[benchmark]
+	fn block_reward() {
+		Curve::<T>::put(T::DefaultEmissionCurve::get());
+		CollectedFees::<T>::put(T::MinPayout::get());
+
+		#[block]
+		{
+			Pallet::<T>::block_reward();
+		}
+	}
+
+	#[benchmark]
+	fn claim() -> Result<(), BenchmarkError> {
+		type Rewards<T> = Pallet<T>;
+		type System<T> = frame_system::Pallet<T>;
+
+		let caller: T::AccountId = whitelisted_caller();
+		let miner: T::AccountId = account("miner", 0, SEED);
+		let payout = T::MinPayout::get();
+		let mut number = System::<T>::block_number();
+		for _ in 0..T::MaxMaturing::get() {
+			number += One::one();
+			Rewards::<T>::insert_coinbase(number, vec![(miner.clone(), payout)]);
+		}
+		System::<T>::set_block_number(number + T::MaturationTime::get());
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), miner.clone());
+
+		assert_eq!(Maturing::<T>::get(&miner).len(), 0);
+
+		Ok(())
+	}
+
 	impl_benchmark_test_suite!(Rewards, crate::mock::new_test_ext(), crate::mock::Text,);
 }
@@ -0,0 +1,134 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Emission curves of the block reward.
+
+use frame_support::pallet_prelude::*;
+use np_arithmetic::{
+	traits::{AtLeast32BitUnsigned, SaturatedConversion, Saturating, Zero},
+	PerThing, Perbill, Perquintill,
+};
+
+/// Coin emission schedule.
+pub trait EmissionCurve<BlockNumber, Balance> {
+	/// Returns the coins minted for the block `number`, given the total issuance before it.
+	fn emission(&self, number: BlockNumber, total_issuance: Balance) -> Balance;
+}
+
+/// Constant emission.
+#[derive(
+	Clone, Copy, Default, PartialEq, Eq, Encode, Decode, MaxEncodedLen, TypeInfo, RuntimeDebug,
+)]
+pub struct Constant<Balance>(pub Balance);
+
+impl<BlockNumber, Balance: Copy> EmissionCurve<BlockNumber, Balance> for Constant<Balance> {
+	fn emission(&self, _number: BlockNumber, _total_issuance: Balance) -> Balance {
+		self.0
+	}
+}
+
+/// Emission halving every `interval` blocks.
+#[derive(
+	Clone, Copy, Default, PartialEq, Eq, Encode, Decode, MaxEncodedLen, TypeInfo, RuntimeDebug,
+)]
+pub struct Halving<BlockNumber, Balance> {
+	pub initial: Balance,
+	pub interval: BlockNumber,
+}
+
+impl<BlockNumber, Balance> EmissionCurve<BlockNumber, Balance> for Halving<BlockNumber, Balance>
+where
+	BlockNumber: AtLeast32BitUnsigned + Copy,
+	Balance: AtLeast32BitUnsigned + Copy,
+{
+	fn emission(&self, number: BlockNumber, _total_issuance: Balance) -> Balance {
+		if self.interval.is_zero() {
+			return self.initial
+		}
+
+		let halvings = (number / self.interval).saturated_into::<u32>();
+		self.initial.checked_shr(halvings).unwrap_or_else(Zero::zero)
+	}
+}
+
+/// Emission decaying by `decay` every `period` blocks.
+#[derive(
+	Clone, Copy, Default, PartialEq, Eq, Encode, Decode, MaxEncodedLen, TypeInfo, RuntimeDebug,
+)]
+pub struct ExponentialDecay<BlockNumber, Balance> {
+	pub initial: Balance,
+	pub decay: Perbill,
+	pub period: BlockNumber,
+}
+
+impl<BlockNumber, Balance> EmissionCurve<BlockNumber, Balance>
+	for ExponentialDecay<BlockNumber, Balance>
+where
+	BlockNumber: AtLeast32BitUnsigned + Copy,
+	Balance: AtLeast32BitUnsigned + Copy,
+{
+	fn emission(&self, number: BlockNumber, _total_issuance: Balance) -> Balance {
+		if self.period.is_zero() {
+			return self.initial
+		}
+
+		let periods = (number / self.period).saturated_into::<usize>();
+		(Perbill::one() - self.decay).saturating_pow(periods).mul_floor(self.initial)
+	}
+}
+
+/// Emission of `rate` of the total issuance per block.
+#[derive(
+	Clone, Copy, Default, PartialEq, Eq, Encode, Decode, MaxEncodedLen, TypeInfo, RuntimeDebug,
+)]
+pub struct Inflation {
+	pub rate: Perquintill,
+}
+
+impl<BlockNumber, Balance> EmissionCurve<BlockNumber, Balance> for Inflation
+where
+	Balance: AtLeast32BitUnsigned + Copy,
+{
+	fn emission(&self, _number: BlockNumber, total_issuance: Balance) -> Balance {
+		self.rate.mul_floor(total_issuance)
+	}
+}
+
+/// Emission of `curve`, never going below the emission of `tail`.
+#[derive(
+	Clone, Copy, Default, PartialEq, Eq, Encode, Decode, MaxEncodedLen, TypeInfo, RuntimeDebug,
+)]
+pub struct TailEmission<Curve, Tail> {
+	pub curve: Curve,
+	pub tail: Tail,
+}
+
+impl<BlockNumber, Balance, Curve, Tail> EmissionCurve<BlockNumber, Balance>
+	for TailEmission<Curve, Tail>
+where
+	BlockNumber: Copy,
+	Balance: Ord + Copy,
+	Curve: EmissionCurve<BlockNumber, Balance>,
+	Tail: EmissionCurve<BlockNumber, Balance>,
+{
+	fn emission(&self, number: BlockNumber, total_issuance: Balance) -> Balance {
+		self.curve
+			.emission(number, total_issuance)
+			.max(self.tail.emission(number, total_issuance))
+	}
+}
@@ -0,0 +1,100 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Estimated weights for `pallet_rewards`.
+//!
+//! These weights are NOT measured. They are estimated from the storage accesses of the calls
+//! added after the benchmarks of [`weights`](crate::weights) were run, and are to be replaced by
+//! the weights generated from `benchmarking.rs`.
+
+use frame_support::{
+	traits::Get,
+	weights::{constants::RocksDbWeight, RuntimeDbWeight, Weight},
+};
+
+use crate::weights::SubstrateWeight;
+
+pub trait EstimatedWeightInfo {
+	/// Weight added to the coinbase by `u` uncles.
+	fn coinbase_uncles(u: u32) -> Weight;
+	fn set_emission_curve() -> Weight;
+	/// Weight of evaluating the emission curve and taking the collected fees.
+	fn block_reward() -> Weight;
+	fn claim() -> Weight;
+}
+
+/// Weight of `u` uncles, each reading the hashes of the block numbers it may be the child of and
+/// the uncles recently included, and rewarding its miner.
+fn coinbase_uncles(db: RuntimeDbWeight, u: u32) -> Weight {
+	Weight::from_parts(61_248_910, 4123)
+		.saturating_mul(u.into())
+		.saturating_add(db.reads((10_u64).saturating_mul(u.into())))
+		.saturating_add(db.writes((3_u64).saturating_mul(u.into())))
+		// Updates the uncles recently included.
+		.saturating_add(db.writes(1))
+}
+
+fn set_emission_curve(db: RuntimeDbWeight) -> Weight {
+	Weight::from_parts(5_420_000, 0).saturating_add(db.writes(1))
+}
+
+fn block_reward(db: RuntimeDbWeight) -> Weight {
+	// Reads the curve, the total issuance and the collected fees, which are taken.
+	Weight::from_parts(5_060_000, 1501).saturating_add(db.reads_writes(3, 1))
+}
+
+fn claim(db: RuntimeDbWeight) -> Weight {
+	// Reads and updates the maturing rewards, the freezes and the account, and reads the locks.
+	Weight::from_parts(42_350_000, 5113).saturating_add(db.reads_writes(4, 3))
+}
+
+impl<T: frame_system::Config> EstimatedWeightInfo for SubstrateWeight<T> {
+	fn coinbase_uncles(u: u32) -> Weight {
+		coinbase_uncles(T::DbWeight::get(), u)
+	}
+
+	fn set_emission_curve() -> Weight {
+		set_emission_curve(T::DbWeight::get())
+	}
+
+	fn block_reward() -> Weight {
+		block_reward(T::DbWeight::get())
+	}
+
+	fn claim() -> Weight {
+		claim(T::DbWeight::get())
+	}
+}
+
+impl EstimatedWeightInfo for () {
+	fn coinbase_uncles(u: u32) -> Weight {
+		coinbase_uncles(RocksDbWeight::get(), u)
+	}
+
+	fn set_emission_curve() -> Weight {
+		set_emission_curve(RocksDbWeight::get())
+	}
+
+	fn block_reward() -> Weight {
+		block_reward(RocksDbWeight::get())
+	}
+
+	fn claim() -> Weight {
+		claim(RocksDbWeight::get())
+	}
+}
@@ -23,24 +23,45 @@
 extern crate alloc;
 
 mod benchmarking;
+pub mod emission;
+pub mod estimated_weights;
+pub mod migrations;
 mod mock;
 mod tests;
 pub mod weights;
+pub use estimated_weights::EstimatedWeightInfo;
 pub use weights::WeightInfo;
 
+pub use emission::EmissionCurve;
 pub use pallet::*;
 
 use alloc::collections::BTreeMap;
-use frame_support::traits::{Currency, LockIdentifier, LockableCurrency, WithdrawReasons};
-use np_arithmetic::traits::{AtLeast32BitUnsigned, CheckedAdd, SaturatingMulDiv, Zero};
-use np_rewards::{split_reward, InherentError, InherentType, INHERENT_IDENTIFIER};
+use frame_support::{
+	sp_runtime::traits::Header as HeaderT,
+	storage::with_storage_layer,
+	traits::{
+		fungible::{self, Inspect, InspectFreeze, Mutate, MutateFreeze},
+		FindAuthor, Imbalance, IsSubType, OnUnbalanced,
+	},
+};
+use np_arithmetic::{
+	traits::{
+		AtLeast32BitUnsigned, CheckedAdd, CheckedSub, One, SaturatedConversion, Saturating,
+		SaturatingMulDiv, Zero,
+	},
+	Perbill,
+};
+use np_consensus_pow::{UnclesInherentType, UNCLES_INHERENT_IDENTIFIER};
+use np_rewards::{split_reward, InherentError, InherentType, RewardSchedule, INHERENT_IDENTIFIER};
 use parity_scale_codec::FullCodec;
 use sp_inherents::{InherentData, InherentIdentifier};
 
-const LOCK_IDENTIFIER: LockIdentifier = *b"rewards_";
-
 pub type BalanceOf<T> =
-	<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+	<<T as Config>::Currency as fungible::Inspect<<T as frame_system::Config>::AccountId>>::Balance;
+pub type CreditOf<T> =
+	fungible::Credit<<T as frame_system::Config>::AccountId, <T as Config>::Currency>;
+
+const LOG_TARGET: &str = "runtime::rewards";
 
 #[frame_support::pallet]
 pub mod pallet {
@@ -49,16 +70,38 @@ pub mod pallet {
 	use frame_support::pallet_prelude::*;
 	use frame_system::pallet_prelude::*;
 
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
 	#[pallet::pallet]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	#[pallet::config]
 	pub trait Config: frame_system::Config {
-		/// Coin emission schedule.
-		type EmissionSchedule: Get<BalanceOf<Self>>;
+		/// Coin emission curve.
+		type EmissionCurve: EmissionCurve<BlockNumberFor<Self>, BalanceOf<Self>>
+			+ Parameter
+			+ Member
+			+ MaxEncodedLen;
+
+		/// Emission curve used until one is set by governance.
+		type DefaultEmissionCurve: Get<Self::EmissionCurve>;
+
+		/// Origin allowed to update the emission curve.
+		type UpdateOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+		/// Portion of the transaction fees added to the reward of the next block.
+		///
+		/// Fees are only collected if this pallet handles them as `OnUnbalanced`.
+		#[pallet::constant]
+		type FeeShare: Get<Perbill>;
+
+		/// Overarching freeze reason.
+		type RuntimeFreezeReason: From<FreezeReason>;
 
 		/// Currency type of this pallet.
-		type Currency: LockableCurrency<Self::AccountId>;
+		type Currency: fungible::Mutate<Self::AccountId>
+			+ fungible::MutateFreeze<Self::AccountId, Id = Self::RuntimeFreezeReason>;
 
 		/// Minimum payout amount.
 		///
@@ -73,11 +116,44 @@ pub mod pallet {
 		#[pallet::constant]
 		type MaxRewardSplits: Get<u32>;
 
+		/// Maximum number of maturing rewards per account.
+		///
+		/// Rewards beyond the limit are merged into the latest one, maturing with it.
+		#[pallet::constant]
+		type MaxMaturing: Get<u32>;
+
+		/// Find the author of an uncle from its pre-runtime digests.
+		type FindAuthor: FindAuthor<Self::AccountId>;
+
+		/// Maximum number of uncles a block may include.
+		#[pallet::constant]
+		type MaxUncles: Get<u32>;
+
+		/// Maximum encoded length of an uncle header.
+		///
+		/// The coinbase is benchmarked with uncles of this length.
+		#[pallet::constant]
+		type MaxUncleLen: Get<u32>;
+
+		/// Number of generations an uncle may be behind the block including it.
+		#[pallet::constant]
+		type UncleGenerations: Get<BlockNumberFor<Self>>;
+
+		/// Portion of the block emission rewarded to an uncle one generation behind.
+		///
+		/// The uncle reward decreases linearly with the generations the uncle is behind.
+		#[pallet::constant]
+		type UncleReward: Get<Perbill>;
+
+		/// Portion of the block emission rewarded to the block for each uncle it includes.
+		#[pallet::constant]
+		type NephewReward: Get<Perbill>;
+
 		/// Miner's contribution to generate the proof of the block.
 		type Share: FullCodec + Copy + AtLeast32BitUnsigned;
 
-		/// Weight information for extrinsics in this pallet.
-		type WeightInfo: WeightInfo;
+		/// Weight information for extrinsics in this pallet, measured or estimated.
+		type WeightInfo: WeightInfo + EstimatedWeightInfo;
 	}
 
 	#[pallet::error]
@@ -86,11 +162,45 @@ pub mod pallet {
 		InvalidReward,
 		/// Coinbase contains too many reward splits.
 		TooManyRewardSplits,
+		/// No matured rewards to claim.
+		NothingToClaim,
+		/// Coinbase contains too many uncles.
+		TooManyUncles,
+		/// Uncle is not behind the block including it.
+		TooHighUncle,
+		/// Uncle is too many generations behind the block including it.
+		OldUncle,
+		/// Uncle is not a child of the canonical chain, or is part of it.
+		InvalidUncleParent,
+		/// Uncle was already included.
+		UncleAlreadyIncluded,
+		/// Author of the uncle not found.
+		UncleWithoutAuthor,
+		/// Uncle header is longer than `MaxUncleLen`.
+		UncleTooLong,
+	}
+
+	/// A reason for the pallet freezing funds.
+	#[pallet::composite_enum]
+	pub enum FreezeReason {
+		/// Newly minted coins that are not yet spendable.
+		#[codec(index = 0)]
+		Maturing,
 	}
 
 	#[pallet::storage]
 	pub type Processed<T: Config> = StorageValue<_, bool, ValueQuery>;
 
+	/// Current coin emission curve.
+	#[pallet::storage]
+	pub type Curve<T: Config> =
+		StorageValue<_, T::EmissionCurve, ValueQuery, T::DefaultEmissionCurve>;
+
+	/// Transaction fees to be added to the next coinbase.
+	#[pallet::storage]
+	pub type CollectedFees<T: Config> = StorageValue<_, BalanceOf<T>, ValueQuery>;
+
+	/// Rewards of the blocks that are still maturing.
 	#[pallet::storage]
 	#[pallet::getter(fn rewards)]
 	pub type Rewards<T: Config> = StorageMap<
@@ -101,17 +211,38 @@ pub mod pallet {
 		ValueQuery,
 	>;
 
+	/// Hashes of the uncles included by the recent blocks.
 	#[pallet::storage]
-	#[pallet::getter(fn reward_locks)]
-	pub type RewardLocks<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, BalanceOf<T>>;
+	pub type Uncles<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		BlockNumberFor<T>,
+		BoundedVec<T::Hash, T::MaxUncles>,
+		ValueQuery,
+	>;
+
+	/// Maturing rewards of each account, by the block number they mature at.
+	#[pallet::storage]
+	pub type Maturing<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		BoundedVec<(BlockNumberFor<T>, BalanceOf<T>), T::MaxMaturing>,
+		ValueQuery,
+	>;
 
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
 		#[pallet::call_index(0)]
-		#[pallet::weight(T::WeightInfo::coinbase(rewards.len() as u32))]
+		#[pallet::weight(
+			T::WeightInfo::coinbase(rewards.len() as u32)
+				.saturating_add(T::WeightInfo::coinbase_uncles(uncles.len() as u32))
+				.saturating_add(T::WeightInfo::block_reward())
+		)]
 		pub fn coinbase(
 			origin: OriginFor<T>,
 			rewards: Vec<(T::AccountId, BalanceOf<T>)>,
+			uncles: Vec<HeaderFor<T>>,
 		) -> DispatchResult {
 			ensure_none(origin)?;
 			ensure!(!Processed::<T>::exists(), "multiple coinbase not allowed");
@@ -119,78 +250,98 @@ pub mod pallet {
 				rewards.len() <= T::MaxRewardSplits::get() as usize,
 				Error::<T>::TooManyRewardSplits
 			);
+			ensure!(uncles.len() <= T::MaxUncles::get() as usize, Error::<T>::TooManyUncles);
+
+			let number = frame_system::Pallet::<T>::block_number();
+			let emission = Self::emission();
+			let reward = Self::block_reward()
+				.saturating_add(Self::nephew_reward(emission, uncles.len() as u32));
+			let reward_given = rewards
+				.iter()
+				.fold(BalanceOf::<T>::zero(), |sum, (_, value)| sum.saturating_add(*value));
+			ensure!(reward_given == reward, Error::<T>::InvalidReward);
+
+			let mut included = Vec::with_capacity(uncles.len());
+			for uncle in &uncles {
+				let author = Self::check_uncle(uncle, &included)?;
+				included.push(uncle.hash());
+
+				let value = Self::uncle_reward(emission, number - *uncle.number());
+				if value >= T::MinPayout::get() {
+					Self::pay(&author, value, number);
+				}
+			}
 
-			let reward = T::EmissionSchedule::get();
-			let mut reward_given = BalanceOf::<T>::zero();
 			for (dest, value) in &rewards {
-				drop(T::Currency::deposit_creating(dest, *value));
-				reward_given += *value;
-
-				RewardLocks::<T>::mutate(dest, |lock| {
-					let new_lock = match lock.take() {
-						Some(lock) => lock + *value,
-						None => *value,
-					};
-					T::Currency::set_lock(
-						LOCK_IDENTIFIER,
-						dest,
-						new_lock,
-						WithdrawReasons::except(WithdrawReasons::TRANSACTION_PAYMENT),
-					);
-					*lock = Some(new_lock);
-				});
+				Self::pay(dest, *value, number);
 			}
-			ensure!(reward_given == reward, Error::<T>::InvalidReward);
+			CollectedFees::<T>::kill();
 
+			if let Some(matured) = number.checked_sub(&T::MaturationTime::get()) {
+				Rewards::<T>::remove(matured);
+			}
+			if let Some(stale) = number.checked_sub(&T::UncleGenerations::get()) {
+				Uncles::<T>::remove(stale);
+			}
+			if !included.is_empty() {
+				Uncles::<T>::insert(number, BoundedVec::truncate_from(included));
+			}
 			Rewards::<T>::insert(
-				frame_system::Pallet::<T>::block_number(),
+				number,
 				BoundedVec::<_, T::MaxRewardSplits>::try_from(rewards).unwrap(),
 			);
 
 			Processed::<T>::put(true);
 			Ok(())
 		}
+
+		/// Set the coin emission curve, effective from the next coinbase.
+		#[pallet::call_index(1)]
+		#[pallet::weight(T::WeightInfo::set_emission_curve())]
+		pub fn set_emission_curve(origin: OriginFor<T>, curve: T::EmissionCurve) -> DispatchResult {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			Curve::<T>::put(curve);
+			Ok(())
+		}
+
+		/// Unfreeze the matured rewards of `who`.
+		///
+		/// The call is feeless when `who` claims matured rewards of its own, as they may be all the
+		/// balance the fee could be paid with. Claims for others are charged, so that they cannot
+		/// fill blocks for free. The runtime must wrap its payment extension in
+		/// `SkipCheckIfFeeless` for it to take effect.
+		#[pallet::call_index(2)]
+		#[pallet::weight(T::WeightInfo::claim())]
+		#[pallet::feeless_if(|origin: &OriginFor<T>, who: &T::AccountId| -> bool {
+			ensure_signed(origin.clone()).is_ok_and(|signer| &signer == who) &&
+				Pallet::<T>::has_matured(who)
+		})]
+		pub fn claim(origin: OriginFor<T>, who: T::AccountId) -> DispatchResult {
+			ensure_signed(origin)?;
+			let unlocked = Self::unlock(&who)?;
+			ensure!(!unlocked.is_zero(), Error::<T>::NothingToClaim);
+			Ok(())
+		}
 	}
 
 	#[pallet::hooks]
 	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
-		fn on_initialize(number: BlockNumberFor<T>) -> Weight {
-			let reward_splits = if number > T::MaturationTime::get() {
-				{
-					let unlocked_number = number - T::MaturationTime::get();
-
-					let rewards = Rewards::<T>::take(unlocked_number);
-					let reward_splits = rewards.len() as u64;
-
-					for (dest, value) in rewards {
-						RewardLocks::<T>::mutate(&dest, |lock| {
-							let locked = lock.unwrap();
-							if locked > value {
-								T::Currency::set_lock(
-									LOCK_IDENTIFIER,
-									&dest,
-									locked - value,
-									WithdrawReasons::except(WithdrawReasons::TRANSACTION_PAYMENT),
-								);
-								*lock = Some(locked - value);
-							} else {
-								T::Currency::remove_lock(LOCK_IDENTIFIER, &dest);
-								*lock = None;
-							}
-						});
-					}
-					reward_splits
-				}
-			} else {
-				0
-			};
-
-			T::WeightInfo::on_finalize(reward_splits as u32)
+		fn on_initialize(_: BlockNumberFor<T>) -> Weight {
+			// Bounded by `on_finalize` measured when it unlocked the rewards of the block.
+			T::WeightInfo::on_finalize(0)
 		}
 
 		fn on_finalize(_: BlockNumberFor<T>) {
 			assert!(Processed::<T>::take(), "coinbase must be processed");
 		}
+
+		fn integrity_test() {
+			assert!(T::MaxMaturing::get() > 0, "MaxMaturing must be greater than zero");
+			assert!(
+				T::MinPayout::get() >= T::Currency::minimum_balance(),
+				"MinPayout must be greater or equal than existential deposit"
+			);
+		}
 	}
 
 	#[pallet::inherent]
@@ -208,7 +359,31 @@ pub mod pallet {
 				.expect("Rewards inherent data not correctly encoded")
 				.expect("Rewards inherent data must be provided");
 
-			let reward = T::EmissionSchedule::get();
+			let emission = Self::emission();
+			let candidates = data
+				.get_data::<UnclesInherentType<HeaderFor<T>>>(&UNCLES_INHERENT_IDENTIFIER)
+				.ok()
+				.flatten()
+				.unwrap_or_default();
+
+			// Include the valid uncles, in the order they are provided.
+			let mut uncles = Vec::new();
+			let mut included = Vec::new();
+			for uncle in candidates {
+				if uncles.len() >= T::MaxUncles::get() as usize {
+					break
+				}
+				if Self::check_uncle(&uncle, &included).is_ok() {
+					included.push(uncle.hash());
+					uncles.push(uncle);
+				}
+			}
+
+			let reward = Self::block_reward()
+				.saturating_add(Self::nephew_reward(emission, uncles.len() as u32));
+			if reward.is_zero() {
+				return Some(Call::coinbase { rewards: Vec::new(), uncles })
+			}
 
 			// Prune zero shares and ensure the number of shares is within the limit.
 			shares.retain(|_, share| !share.is_zero());
@@ -237,22 +412,23 @@ pub mod pallet {
 				return None
 			}
 
-			Some(Call::coinbase { rewards })
+			Some(Call::coinbase { rewards, uncles })
 		}
 
 		fn check_inherent(call: &Self::Call, _data: &InherentData) -> Result<(), Self::Error> {
-			if let Call::coinbase { rewards } = call {
-				let expected_reward = T::EmissionSchedule::get();
-				let reward = rewards
+			if let Call::coinbase { rewards, uncles } = call {
+				// The reward amount depends on the block number, which is not yet set when checking
+				// inherents, so it is checked on dispatch instead.
+				rewards
 					.iter()
 					.try_fold(BalanceOf::<T>::zero(), |sum, (_, value)| sum.checked_add(value))
 					.ok_or(InherentError::InvalidReward)?;
-				if reward != expected_reward {
-					return Err(InherentError::InvalidReward);
-				}
 				if BTreeMap::from_iter(rewards.iter().cloned()).len() != rewards.len() {
 					return Err(InherentError::DuplicateBeneficiary);
 				}
+				if uncles.len() > T::MaxUncles::get() as usize {
+					return Err(InherentError::TooManyUncles)
+				}
 			}
 
 			Ok(())
@@ -264,6 +440,171 @@ pub mod pallet {
 	}
 
 	impl<T: Config> Pallet<T> {
+		/// Emission of the curve for the current block.
+		pub fn emission() -> BalanceOf<T> {
+			let number = frame_system::Pallet::<T>::block_number();
+			Curve::<T>::get().emission(number, T::Currency::total_issuance())
+		}
+
+		/// Reward of the current block: the emission of the curve and the collected fees.
+		///
+		/// The block is rewarded the nephew reward on top of it for each uncle it includes.
+		pub fn block_reward() -> BalanceOf<T> {
+			Self::emission().saturating_add(CollectedFees::<T>::get())
+		}
+
+		/// Reward of an uncle `depth` generations behind, given the block `emission`.
+		pub fn uncle_reward(emission: BalanceOf<T>, depth: BlockNumberFor<T>) -> BalanceOf<T> {
+			let generations = T::UncleGenerations::get();
+			if depth.is_zero() || depth > generations {
+				return Zero::zero()
+			}
+
+			let generations = generations.saturated_into::<u32>();
+			let remaining = generations.saturating_add(1).saturating_sub(depth.saturated_into());
+			Perbill::from_rational(remaining, generations) * (T::UncleReward::get() * emission)
+		}
+
+		/// Reward of the block for including `uncles`, given the block `emission`.
+		pub fn nephew_reward(emission: BalanceOf<T>, uncles: u32) -> BalanceOf<T> {
+			(T::NephewReward::get() * emission).saturating_mul(uncles.into())
+		}
+
+		/// Checks that `uncle` can be included in the current block along with the `included`
+		/// ones, returning its author.
+		///
+		/// The seal of the uncle is not checked here; block import verifies it with the PoW
+		/// algorithm.
+		pub fn check_uncle(
+			uncle: &HeaderFor<T>,
+			included: &[T::Hash],
+		) -> Result<T::AccountId, DispatchError> {
+			ensure!(
+				uncle.encoded_size() <= T::MaxUncleLen::get() as usize,
+				Error::<T>::UncleTooLong
+			);
+
+			let now = frame_system::Pallet::<T>::block_number();
+			let number = *uncle.number();
+			ensure!(number < now, Error::<T>::TooHighUncle);
+			ensure!(now - number <= T::UncleGenerations::get(), Error::<T>::OldUncle);
+			ensure!(!number.is_zero(), Error::<T>::InvalidUncleParent);
+
+			let hash = uncle.hash();
+			let parent = frame_system::Pallet::<T>::block_hash(number - One::one());
+			ensure!(
+				*uncle.parent_hash() == parent &&
+					frame_system::Pallet::<T>::block_hash(number) != hash,
+				Error::<T>::InvalidUncleParent
+			);
+
+			let mut including = number;
+			while including < now {
+				including += One::one();
+				ensure!(
+					!Uncles::<T>::get(including).contains(&hash),
+					Error::<T>::UncleAlreadyIncluded
+				);
+			}
+			ensure!(!included.contains(&hash), Error::<T>::UncleAlreadyIncluded);
+
+			let digests = uncle.digest().logs().iter().filter_map(|d| d.as_pre_runtime());
+			T::FindAuthor::find_author(digests).ok_or_else(|| Error::<T>::UncleWithoutAuthor.into())
+		}
+
+		/// Uncles included by the coinbase among `calls`.
+		pub fn uncles<'a>(calls: impl IntoIterator<Item = &'a T::RuntimeCall>) -> Vec<HeaderFor<T>>
+		where
+			T::RuntimeCall: IsSubType<Call<T>>,
+		{
+			calls
+				.into_iter()
+				.filter_map(|call| match call.is_sub_type() {
+					Some(Call::coinbase { uncles, .. }) => Some(uncles.clone()),
+					_ => None,
+				})
+				.flatten()
+				.collect()
+		}
+
+		/// Rewards of `who` that are frozen and their maturation schedule.
+		pub fn reward_schedule(
+			who: &T::AccountId,
+		) -> RewardSchedule<BlockNumberFor<T>, BalanceOf<T>> {
+			RewardSchedule {
+				locked: T::Currency::balance_frozen(&FreezeReason::Maturing.into(), who),
+				maturing: Maturing::<T>::get(who).into_inner(),
+			}
+		}
+
+		/// Returns true if `who` has matured rewards to claim.
+		pub fn has_matured(who: &T::AccountId) -> bool {
+			let now = frame_system::Pallet::<T>::block_number();
+			Maturing::<T>::get(who).first().is_some_and(|(at, _)| *at <= now)
+		}
+
+		/// Mints the reward `value` to `dest` at block `number` and freezes it until it matures.
+		///
+		/// A payout that cannot be made is skipped instead of failing the coinbase, which would
+		/// make the block invalid.
+		fn pay(dest: &T::AccountId, value: BalanceOf<T>, number: BlockNumberFor<T>) {
+			let result = with_storage_layer(|| {
+				Self::unlock(dest)?;
+				T::Currency::mint_into(dest, value)?;
+				Self::lock(dest, value, number)
+			});
+			if let Err(e) = result {
+				log::warn!(target: LOG_TARGET, "Skipped reward {:?} of {:?}: {:?}", value, dest, e);
+			}
+		}
+
+		/// Freezes the reward `value` of `dest` minted at block `number` until it matures.
+		fn lock(
+			dest: &T::AccountId,
+			value: BalanceOf<T>,
+			number: BlockNumberFor<T>,
+		) -> DispatchResult {
+			let matures_at = number.saturating_add(T::MaturationTime::get());
+			Maturing::<T>::mutate(dest, |maturing| {
+				let full = maturing.is_full();
+				match maturing.last_mut() {
+					Some((at, locked)) if full || *at == matures_at => {
+						*at = matures_at;
+						*locked = locked.saturating_add(value);
+					},
+					_ => {
+						let _ = maturing.try_push((matures_at, value));
+					},
+				}
+			});
+			T::Currency::increase_frozen(&FreezeReason::Maturing.into(), dest, value)
+		}
+
+		/// Unfreezes the matured rewards of `who`, returning the amount unfrozen.
+		pub fn unlock(who: &T::AccountId) -> Result<BalanceOf<T>, DispatchError> {
+			let now = frame_system::Pallet::<T>::block_number();
+			let mut maturing = Maturing::<T>::get(who);
+			if maturing.first().is_none_or(|(at, _)| *at > now) {
+				return Ok(Zero::zero())
+			}
+
+			maturing.retain(|(at, _)| *at > now);
+			let locked = maturing
+				.iter()
+				.fold(BalanceOf::<T>::zero(), |sum, (_, v)| sum.saturating_add(*v));
+			let reason: T::RuntimeFreezeReason = FreezeReason::Maturing.into();
+			let unlocked = T::Currency::balance_frozen(&reason, who).saturating_sub(locked);
+
+			if maturing.is_empty() {
+				Maturing::<T>::remove(who);
+				T::Currency::thaw(&reason, who)?;
+			} else {
+				Maturing::<T>::insert(who, maturing);
+				T::Currency::set_freeze(&reason, who, locked)?;
+			}
+			Ok(unlocked)
+		}
+
 		/// Pushes the coinbase rewards. Only use for tests.
 		#[cfg(any(feature = "runtime-benchmarks", feature = "std"))]
 		pub fn insert_coinbase(
@@ -271,20 +612,8 @@ pub mod pallet {
 			rewards: Vec<(T::AccountId, BalanceOf<T>)>,
 		) {
 			for (dest, value) in &rewards {
-				drop(T::Currency::deposit_creating(dest, *value));
-				RewardLocks::<T>::mutate(dest, |lock| {
-					let new_lock = match lock.take() {
-						Some(lock) => lock + *value,
-						None => *value,
-					};
-					T::Currency::set_lock(
-						LOCK_IDENTIFIER,
-						dest,
-						new_lock,
-						WithdrawReasons::except(WithdrawReasons::TRANSACTION_PAYMENT),
-					);
-					*lock = Some(new_lock);
-				});
+				T::Currency::mint_into(dest, *value).unwrap();
+				Self::lock(dest, *value, number).unwrap();
 			}
 			Rewards::<T>::insert(
 				number,
@@ -294,3 +623,11 @@ pub mod pallet {
 		}
 	}
 }
+
+/// Burns the transaction fees, minting `FeeShare` of them back in the next coinbase.
+impl<T: Config> OnUnbalanced<CreditOf<T>> for Pallet<T> {
+	fn on_nonzero_unbalanced(amount: CreditOf<T>) {
+		let share = T::FeeShare::get() * amount.peek();
+		CollectedFees::<T>::mutate(|fees| *fees = fees.saturating_add(share));
+	}
+}
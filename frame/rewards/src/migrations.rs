@@ -0,0 +1,189 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Storage migrations of the rewards pallet.
+
+/// Migrates the reward locks of `LockableCurrency` to freezes with a maturation schedule.
+pub mod v1 {
+	use crate::*;
+	use alloc::vec::Vec;
+	use core::marker::PhantomData;
+	use frame_support::{
+		migrations::{MigrationId, SteppedMigration, SteppedMigrationError},
+		pallet_prelude::*,
+		storage_alias,
+		traits::{Defensive, LockIdentifier, LockableCurrency},
+		weights::WeightMeter,
+	};
+	use frame_system::pallet_prelude::BlockNumberFor;
+
+	pub const LOCK_IDENTIFIER: LockIdentifier = *b"rewards_";
+
+	const PALLET_MIGRATIONS_ID: &[u8; 14] = b"pallet-rewards";
+
+	#[storage_alias]
+	pub type RewardLocks<T: Config> = StorageMap<
+		Pallet<T>,
+		Blake2_128Concat,
+		<T as frame_system::Config>::AccountId,
+		BalanceOf<T>,
+	>;
+
+	/// Moves each `RewardLocks` entry to a freeze, scheduling its maturation from `Rewards`.
+	///
+	/// `OldCurrency` is the `LockableCurrency` that held the locks. The migration is stepped by
+	/// `pallet-migrations`, over as many blocks as it takes.
+	///
+	/// One account is migrated per step, which reads the rewards of every block still maturing,
+	/// that is up to `MaturationTime + 1` entries of `MaxRewardSplits` splits each. The number of
+	/// steps is the number of accounts with a reward lock, which is unbounded as every account
+	/// that was ever rewarded may hold one.
+	///
+	/// The cursor is the block the migration started at. Rewards paid from then on are frozen by
+	/// the coinbase already, so only the earlier ones are scheduled with the lock.
+	pub struct MigrateToV1<T, OldCurrency>(PhantomData<(T, OldCurrency)>);
+
+	impl<T, OldCurrency> SteppedMigration for MigrateToV1<T, OldCurrency>
+	where
+		T: Config,
+		OldCurrency: LockableCurrency<T::AccountId, Balance = BalanceOf<T>>,
+	{
+		type Cursor = BlockNumberFor<T>;
+		type Identifier = MigrationId<14>;
+
+		fn id() -> Self::Identifier {
+			MigrationId { pallet_id: *PALLET_MIGRATIONS_ID, version_from: 0, version_to: 1 }
+		}
+
+		fn step(
+			cursor: Option<Self::Cursor>,
+			meter: &mut WeightMeter,
+		) -> Result<Option<Self::Cursor>, SteppedMigrationError> {
+			if Pallet::<T>::on_chain_storage_version() != 0 {
+				return Ok(None)
+			}
+
+			let required = step_weight::<T>();
+			if meter.remaining().any_lt(required) {
+				return Err(SteppedMigrationError::InsufficientWeight { required })
+			}
+
+			let start = cursor.unwrap_or_else(frame_system::Pallet::<T>::block_number);
+			while meter.try_consume(required).is_ok() {
+				let Some((who, locked)) = RewardLocks::<T>::drain().next() else {
+					StorageVersion::new(1).put::<Pallet<T>>();
+					return Ok(None)
+				};
+				migrate_account::<T, OldCurrency>(who, locked, start);
+			}
+
+			Ok(Some(start))
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, frame_support::sp_runtime::TryRuntimeError> {
+			let accounts = RewardLocks::<T>::iter_keys().collect::<Vec<_>>();
+			Ok(accounts.encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), frame_support::sp_runtime::TryRuntimeError> {
+			let accounts = Vec::<T::AccountId>::decode(&mut &state[..])
+				.map_err(|_| "Failed to decode the locked accounts")?;
+
+			ensure!(Pallet::<T>::on_chain_storage_version() == 1, "Storage version not updated");
+			ensure!(RewardLocks::<T>::iter_keys().next().is_none(), "Reward locks left");
+			for who in accounts {
+				let maturing = Maturing::<T>::get(&who);
+				ensure!(maturing.windows(2).all(|w| w[0].0 < w[1].0), "Maturing not sorted");
+				let total = maturing
+					.iter()
+					.fold(BalanceOf::<T>::zero(), |sum, (_, v)| sum.saturating_add(*v));
+				let frozen = T::Currency::balance_frozen(&FreezeReason::Maturing.into(), &who);
+				ensure!(frozen == total, "Freeze does not match the maturing rewards");
+			}
+			Ok(())
+		}
+	}
+
+	/// Weight of migrating one account.
+	fn step_weight<T: Config>() -> Weight {
+		let blocks = T::MaturationTime::get().saturated_into::<u64>().saturating_add(1);
+		let rewards =
+			BoundedVec::<(T::AccountId, BalanceOf<T>), T::MaxRewardSplits>::max_encoded_len();
+		// Reads the lock, the rewards of each block, the maturing rewards, the freezes, the locks
+		// and the account, and writes all but the rewards.
+		T::DbWeight::get()
+			.reads_writes(blocks.saturating_add(5), 5)
+			.saturating_add(Weight::from_parts(0, blocks.saturating_mul(rewards as u64)))
+	}
+
+	/// Replaces the reward lock of `who` by a freeze, adding the rewards of the blocks before
+	/// `start` to those maturing already.
+	fn migrate_account<T, OldCurrency>(
+		who: T::AccountId,
+		locked: BalanceOf<T>,
+		start: BlockNumberFor<T>,
+	) where
+		T: Config,
+		OldCurrency: LockableCurrency<T::AccountId, Balance = BalanceOf<T>>,
+	{
+		OldCurrency::remove_lock(LOCK_IDENTIFIER, &who);
+
+		let mut scheduled = BalanceOf::<T>::zero();
+		let mut maturing = Maturing::<T>::get(&who).into_inner();
+		for (number, rewards) in Rewards::<T>::iter().filter(|(number, _)| *number < start) {
+			let matures_at = number.saturating_add(T::MaturationTime::get());
+			for (_, value) in rewards.into_iter().filter(|(dest, _)| *dest == who) {
+				scheduled = scheduled.saturating_add(value);
+				maturing.push((matures_at, value));
+			}
+		}
+		if locked > scheduled {
+			// Locked without a known maturation, claimable since the migration started.
+			maturing.push((start, locked - scheduled));
+		}
+		let maturing = merge::<T>(maturing);
+
+		let total = maturing
+			.iter()
+			.fold(BalanceOf::<T>::zero(), |sum, (_, v)| sum.saturating_add(*v));
+		let _ = T::Currency::set_freeze(&FreezeReason::Maturing.into(), &who, total).defensive();
+		Maturing::<T>::insert(who, maturing);
+	}
+
+	/// Sorts the rewards by maturation, merging the earliest into the next ones until they fit
+	/// in `MaxMaturing`.
+	fn merge<T: Config>(
+		mut maturing: Vec<(BlockNumberFor<T>, BalanceOf<T>)>,
+	) -> BoundedVec<(BlockNumberFor<T>, BalanceOf<T>), T::MaxMaturing> {
+		maturing.sort_by_key(|(at, _)| *at);
+		maturing.dedup_by(|(at, value), (prev_at, prev)| {
+			let same = at == prev_at;
+			if same {
+				*prev = prev.saturating_add(*value);
+			}
+			same
+		});
+		while maturing.len() > T::MaxMaturing::get() as usize {
+			let (_, value) = maturing.remove(0);
+			maturing[0].1 = maturing[0].1.saturating_add(value);
+		}
+		BoundedVec::truncate_from(maturing)
+	}
+}
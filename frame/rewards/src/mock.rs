@@ -21,13 +21,19 @@
 use crate as pallet_rewards;
 
 use super::*;
+use crate::emission::Constant;
 use frame_support::{
-	derive_impl,
-	sp_runtime::BuildStorage,
+	derive_impl, parameter_types,
+	sp_runtime::{BuildStorage, ConsensusEngineId},
 	traits::{ConstU32, ConstU64},
 };
+use frame_system::EnsureRoot;
+use np_consensus_pow::POW_ENGINE_ID;
+use parity_scale_codec::Decode;
 use sp_io::TestExternalities;
 
+pub use frame_support::sp_runtime::testing::H256;
+
 #[frame_support::runtime]
 mod runtime {
 	#[runtime::runtime]
@@ -63,14 +69,49 @@ impl frame_system::Config for Test {
 #[derive_impl(pallet_balances::config_preludes::TestDefaultConfig)]
 impl pallet_balances::Config for Test {
 	type AccountStore = System;
+	type MaxFreezes = ConstU32<1>;
+}
+
+parameter_types! {
+	pub const DefaultEmissionCurve: Constant<u64> = Constant(100);
+	pub const FeeShare: Perbill = Perbill::from_percent(50);
+	pub const UncleReward: Perbill = Perbill::from_percent(80);
+	pub const NephewReward: Perbill = Perbill::from_percent(3);
+}
+
+pub type Header = frame_system::pallet_prelude::HeaderFor<Test>;
+
+pub struct PowAuthor;
+
+impl FindAuthor<u64> for PowAuthor {
+	fn find_author<'a, I>(digests: I) -> Option<u64>
+	where
+		I: 'a + IntoIterator<Item = (ConsensusEngineId, &'a [u8])>,
+	{
+		digests
+			.into_iter()
+			.find(|(id, _)| *id == POW_ENGINE_ID)
+			.and_then(|(_, mut data)| u64::decode(&mut data).ok())
+	}
 }
 
 impl Config for Test {
-	type EmissionSchedule = ConstU64<100>;
+	type EmissionCurve = Constant<u64>;
+	type DefaultEmissionCurve = DefaultEmissionCurve;
+	type UpdateOrigin = EnsureRoot<u64>;
+	type FeeShare = FeeShare;
+	type RuntimeFreezeReason = RuntimeFreezeReason;
 	type Currency = Balances;
 	type MinPayout = ConstU64<1>;
 	type MaturationTime = ConstU64<1>;
 	type MaxRewardSplits = ConstU32<100>;
+	type MaxMaturing = ConstU32<2>;
+	type FindAuthor = PowAuthor;
+	type MaxUncles = ConstU32<2>;
+	type MaxUncleLen = ConstU32<256>;
+	type UncleGenerations = ConstU64<6>;
+	type UncleReward = UncleReward;
+	type NephewReward = NephewReward;
 	type Share = u128;
 	type WeightInfo = ();
 }
@@ -18,17 +18,37 @@
 
 #![cfg(test)]
 
-use super::{mock::*, Error};
-use frame_support::{assert_noop, assert_ok};
+use super::{
+	emission::{Constant, ExponentialDecay, Halving, Inflation, TailEmission},
+	migrations::v1,
+	mock::*,
+	CollectedFees, EmissionCurve, Error, FreezeReason, Maturing, Pallet, Uncles,
+};
+use frame_support::{
+	assert_noop, assert_ok,
+	dispatch::CheckIfFeeless,
+	migrations::{SteppedMigration, SteppedMigrationError},
+	sp_runtime::{traits::Header as _, Digest, DigestItem, DispatchError, Perbill, Perquintill},
+	traits::{
+		fungible::{Balanced, Inspect, InspectFreeze, Mutate},
+		tokens::{Fortitude, Preservation},
+		GetStorageVersion, LockableCurrency, OnUnbalanced, StorageVersion, WithdrawReasons,
+	},
+	weights::{Weight, WeightMeter},
+	BoundedVec,
+};
+use np_consensus_pow::POW_ENGINE_ID;
+use np_rewards::RewardSchedule;
+use parity_scale_codec::Encode;
 
 #[test]
 fn coinbase_should_work() {
 	new_test_ext().execute_with(|| {
 		assert_noop!(
-			Rewards::coinbase(RuntimeOrigin::none(), vec![(0, 80), (1, 21)]),
+			Rewards::coinbase(RuntimeOrigin::none(), vec![(0, 80), (1, 21)], vec![]),
 			Error::<Test>::InvalidReward
 		);
-		assert_ok!(Rewards::coinbase(RuntimeOrigin::none(), vec![(0, 80), (1, 20)]));
+		assert_ok!(Rewards::coinbase(RuntimeOrigin::none(), vec![(0, 80), (1, 20)], vec![]));
 	});
 }
 
@@ -37,6 +57,256 @@ fn coinbase_should_work() {
 fn multiple_coinbase_should_fail() {
 	new_test_ext().execute_with(|| {
 		Rewards::insert_coinbase(0, vec![(0, 100)]);
-		assert_ok!(Rewards::coinbase(RuntimeOrigin::none(), vec![(0, 100)]));
+		assert_ok!(Rewards::coinbase(RuntimeOrigin::none(), vec![(0, 100)], vec![]));
+	});
+}
+
+#[test]
+fn emission_curves_should_work() {
+	let halving = Halving { initial: 100u64, interval: 10u64 };
+	assert_eq!(halving.emission(9, 0), 100);
+	assert_eq!(halving.emission(10, 0), 50);
+	assert_eq!(halving.emission(25, 0), 25);
+	assert_eq!(halving.emission(10 * 64, 0), 0);
+
+	let decay =
+		ExponentialDecay { initial: 1000u64, decay: Perbill::from_percent(10), period: 10u64 };
+	assert_eq!(decay.emission(9, 0), 1000);
+	assert_eq!(decay.emission(10, 0), 900);
+	assert_eq!(decay.emission(20, 0), 810);
+
+	let inflation = Inflation { rate: Perquintill::from_percent(1) };
+	assert_eq!(EmissionCurve::<u64, u64>::emission(&inflation, 0, 10_000), 100);
+
+	let tail = TailEmission { curve: halving, tail: Constant(10u64) };
+	assert_eq!(tail.emission(10, 0), 50);
+	assert_eq!(tail.emission(40, 0), 10);
+}
+
+#[test]
+fn set_emission_curve_should_work() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			Rewards::set_emission_curve(RuntimeOrigin::signed(0), Constant(50)),
+			DispatchError::BadOrigin
+		);
+		assert_ok!(Rewards::set_emission_curve(RuntimeOrigin::root(), Constant(50)));
+		assert_eq!(Rewards::block_reward(), 50);
+		assert_ok!(Rewards::coinbase(RuntimeOrigin::none(), vec![(0, 50)], vec![]));
+	});
+}
+
+#[test]
+fn claim_should_work() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(Rewards::coinbase(RuntimeOrigin::none(), vec![(0, 100)], vec![]));
+		assert_eq!(
+			Rewards::reward_schedule(&0),
+			RewardSchedule { locked: 100, maturing: vec![(2, 100)] }
+		);
+		assert_noop!(Rewards::claim(RuntimeOrigin::signed(1), 0), Error::<Test>::NothingToClaim);
+
+		System::set_block_number(2);
+		assert_ok!(Rewards::claim(RuntimeOrigin::signed(1), 0));
+		assert_eq!(Rewards::reward_schedule(&0), RewardSchedule { locked: 0, maturing: vec![] });
+		assert_eq!(Balances::balance(&0), 100);
+	});
+}
+
+#[test]
+fn claim_should_be_feeless_with_matured_rewards() {
+	let spendable =
+		|who| Balances::reducible_balance(&who, Preservation::Expendable, Fortitude::Polite);
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(Rewards::coinbase(RuntimeOrigin::none(), vec![(0, 100)], vec![]));
+		let claim = super::Call::<Test>::claim { who: 0 };
+		assert!(!claim.is_feeless(&RuntimeOrigin::signed(0)));
+
+		// The matured rewards are all the balance of the claimer, which cannot pay a fee.
+		System::set_block_number(2);
+		assert_eq!(spendable(0), 0);
+		assert!(claim.is_feeless(&RuntimeOrigin::signed(0)));
+		// Claims for others are charged.
+		assert!(!claim.is_feeless(&RuntimeOrigin::signed(1)));
+		assert_ok!(Rewards::claim(RuntimeOrigin::signed(0), 0));
+		assert_eq!(spendable(0), 100);
+		assert!(!claim.is_feeless(&RuntimeOrigin::signed(0)));
+	});
+}
+
+#[test]
+fn failed_payouts_should_be_skipped() {
+	new_test_ext().execute_with(|| {
+		// Leaves room in the total issuance for the first reward only.
+		assert_ok!(Balances::mint_into(&2, u64::MAX - 50));
+		assert_ok!(Rewards::coinbase(RuntimeOrigin::none(), vec![(0, 40), (1, 60)], vec![]));
+		assert_eq!(Rewards::reward_schedule(&0).locked, 40);
+		assert_eq!(Balances::balance(&1), 0);
+		assert_eq!(Rewards::reward_schedule(&1), RewardSchedule { locked: 0, maturing: vec![] });
+	});
+}
+
+#[test]
+fn maturing_rewards_should_be_merged() {
+	new_test_ext().execute_with(|| {
+		Rewards::insert_coinbase(1, vec![(0, 100)]);
+		Rewards::insert_coinbase(2, vec![(0, 100)]);
+		Rewards::insert_coinbase(3, vec![(0, 100)]);
+		assert_eq!(Maturing::<Test>::get(0).into_inner(), vec![(2, 100), (4, 200)]);
+		assert_eq!(Balances::balance_frozen(&FreezeReason::Maturing.into(), &0), 300);
+	});
+}
+
+#[test]
+fn fee_share_should_be_rewarded() {
+	new_test_ext().execute_with(|| {
+		let fees = Balances::issue(40);
+		Rewards::on_unbalanced(fees);
+		assert_eq!(CollectedFees::<Test>::get(), 20);
+		assert_eq!(Rewards::block_reward(), 120);
+
+		assert_ok!(Rewards::coinbase(RuntimeOrigin::none(), vec![(0, 120)], vec![]));
+		assert_eq!(CollectedFees::<Test>::get(), 0);
+		assert_eq!(Balances::total_issuance(), 120);
+	});
+}
+
+#[test]
+fn migrate_to_v1_should_work() {
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(0).put::<Pallet<Test>>();
+		System::set_block_number(3);
+		for who in [0, 1] {
+			assert_ok!(Balances::mint_into(&who, 300));
+			Balances::set_lock(v1::LOCK_IDENTIFIER, &who, 300, WithdrawReasons::all());
+			v1::RewardLocks::<Test>::insert(who, 300);
+		}
+		super::Rewards::<Test>::insert(1, BoundedVec::truncate_from(vec![(0, 100)]));
+		super::Rewards::<Test>::insert(2, BoundedVec::truncate_from(vec![(0, 100), (1, 100)]));
+
+		let step = v1::MigrateToV1::<Test, Balances>::step;
+		let Err(SteppedMigrationError::InsufficientWeight { required }) =
+			step(None, &mut WeightMeter::with_limit(Weight::zero()))
+		else {
+			panic!("a step must require weight");
+		};
+
+		// One account is migrated per step.
+		assert!(matches!(step(None, &mut WeightMeter::with_limit(required)), Ok(Some(3))));
+		assert_eq!(v1::RewardLocks::<Test>::iter().count(), 1);
+		assert_eq!(Pallet::<Test>::on_chain_storage_version(), 0);
+
+		// Rewards paid during the migration are frozen along with the migrated locks.
+		System::set_block_number(4);
+		Rewards::insert_coinbase(4, vec![(1, 10)]);
+
+		let mut cursor = Some(3);
+		while cursor.is_some() {
+			cursor = step(cursor, &mut WeightMeter::with_limit(required)).unwrap();
+		}
+
+		assert_eq!(Pallet::<Test>::on_chain_storage_version(), 1);
+		assert_eq!(v1::RewardLocks::<Test>::iter().count(), 0);
+		assert!(pallet_balances::Locks::<Test>::get(0).is_empty());
+		assert!(pallet_balances::Locks::<Test>::get(1).is_empty());
+		assert_eq!(
+			Rewards::reward_schedule(&0),
+			RewardSchedule { locked: 300, maturing: vec![(2, 100), (3, 200)] }
+		);
+		assert_eq!(
+			Rewards::reward_schedule(&1),
+			RewardSchedule { locked: 310, maturing: vec![(3, 300), (5, 10)] }
+		);
+
+		// Migrated already.
+		assert!(matches!(step(None, &mut WeightMeter::new()), Ok(None)));
+	});
+}
+
+fn uncle(number: u64, parent_hash: H256, author: u64) -> Header {
+	let digest = Digest { logs: vec![DigestItem::PreRuntime(POW_ENGINE_ID, author.encode())] };
+	Header::new(number, Default::default(), Default::default(), parent_hash, digest)
+}
+
+#[test]
+fn uncles_should_be_rewarded() {
+	new_test_ext().execute_with(|| {
+		frame_system::BlockHash::<Test>::insert(1, H256::repeat_byte(1));
+		frame_system::BlockHash::<Test>::insert(2, H256::repeat_byte(2));
+		System::set_block_number(4);
+
+		// One generation behind, 80% of the emission; two generations behind, 80% * 5 / 6.
+		let uncles = vec![uncle(3, H256::repeat_byte(2), 2), uncle(2, H256::repeat_byte(1), 3)];
+		assert_eq!(Rewards::uncle_reward(100, 1), 80);
+		assert_eq!(Rewards::uncle_reward(100, 2), 67);
+		assert_eq!(Rewards::nephew_reward(100, 2), 6);
+
+		assert_noop!(
+			Rewards::coinbase(RuntimeOrigin::none(), vec![(0, 100)], uncles.clone()),
+			Error::<Test>::InvalidReward
+		);
+		assert_ok!(Rewards::coinbase(RuntimeOrigin::none(), vec![(0, 106)], uncles.clone()));
+		assert_eq!(Rewards::reward_schedule(&2).locked, 80);
+		assert_eq!(Rewards::reward_schedule(&3).locked, 67);
+		assert_eq!(
+			Uncles::<Test>::get(4).into_inner(),
+			uncles.iter().map(|u| u.hash()).collect::<Vec<_>>()
+		);
+
+		// Uncles are included once.
+		System::set_block_number(5);
+		assert_noop!(Rewards::check_uncle(&uncles[0], &[]), Error::<Test>::UncleAlreadyIncluded);
+	});
+}
+
+#[test]
+fn invalid_uncles_should_fail() {
+	new_test_ext().execute_with(|| {
+		frame_system::BlockHash::<Test>::insert(1, H256::repeat_byte(1));
+		frame_system::BlockHash::<Test>::insert(2, H256::repeat_byte(2));
+		System::set_block_number(8);
+
+		let valid = uncle(2, H256::repeat_byte(1), 2);
+		assert_noop!(
+			Rewards::check_uncle(&uncle(1, H256::zero(), 2), &[]),
+			Error::<Test>::OldUncle
+		);
+		assert_noop!(
+			Rewards::check_uncle(&uncle(8, H256::zero(), 2), &[]),
+			Error::<Test>::TooHighUncle
+		);
+		assert_noop!(
+			Rewards::check_uncle(&uncle(3, H256::repeat_byte(1), 2), &[]),
+			Error::<Test>::InvalidUncleParent
+		);
+		assert_noop!(
+			Rewards::check_uncle(
+				&Header::new(
+					2,
+					Default::default(),
+					Default::default(),
+					H256::repeat_byte(1),
+					Default::default()
+				),
+				&[]
+			),
+			Error::<Test>::UncleWithoutAuthor
+		);
+		assert_noop!(
+			Rewards::check_uncle(&valid, &[valid.hash()]),
+			Error::<Test>::UncleAlreadyIncluded
+		);
+		let mut long = valid.clone();
+		long.digest_mut().push(DigestItem::Other(vec![0; 256]));
+		assert_noop!(Rewards::check_uncle(&long, &[]), Error::<Test>::UncleTooLong);
+		assert_ok!(Rewards::check_uncle(&valid, &[]), 2);
+
+		assert_noop!(
+			Rewards::coinbase(RuntimeOrigin::none(), vec![(0, 109)], vec![valid.clone(); 3]),
+			Error::<Test>::TooManyUncles
+		);
 	});
 }
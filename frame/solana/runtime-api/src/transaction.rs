@@ -18,8 +18,11 @@
 use crate::{error::Error, SolanaRuntimeCall};
 use nostd::{marker::PhantomData, prelude::*};
 use pallet_solana::{runtime::bank::TransactionSimulationResult, Pubkey};
+use serde::{Deserialize, Serialize};
 use solana_sdk::{
+	account::Account,
 	feature_set::FeatureSet,
+	hash::Hash,
 	message::{SanitizedMessage, SimpleAddressLoader},
 	reserved_account_keys::ReservedAccountKeys,
 	transaction::{MessageHash, SanitizedTransaction, VersionedTransaction},
@@ -42,18 +45,54 @@ fn verify_transaction(
 	Ok(())
 }
 
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SimulateTransactionConfig {
+	/// Verify the transaction signatures before simulating.
+	pub sig_verify: bool,
+	/// Replace the recent blockhash of the transaction with the latest blockhash.
+	/// Conflicts with `sig_verify`.
+	pub replace_recent_blockhash: bool,
+	/// Record inner instructions invoked via CPI.
+	pub enable_cpi_recording: bool,
+	/// Accounts to return the post-simulation state of.
+	pub accounts: Option<Vec<Pubkey>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SimulateTransactionResponse {
+	pub result: TransactionSimulationResult,
+	pub account_keys: AccountRawKeys,
+	/// Post-simulation state of the accounts requested in the config, `None` for
+	/// accounts not referenced by the transaction.
+	pub accounts: Option<Vec<Option<Account>>>,
+	/// Blockhash used for the simulation and its last valid block height, if the
+	/// recent blockhash was replaced.
+	pub replacement_blockhash: Option<(Hash, u64)>,
+}
+
 pub struct SimulateTransaction<T>(PhantomData<T>);
 impl<T>
 	SolanaRuntimeCall<
-		(VersionedTransaction, bool, bool),
-		(TransactionSimulationResult, AccountRawKeys),
+		(VersionedTransaction, SimulateTransactionConfig),
+		SimulateTransactionResponse,
 	> for SimulateTransaction<T>
 where
 	T: pallet_solana::Config,
 {
 	fn call(
-		(transaction, sig_verify, enable_cpi_recording): (VersionedTransaction, bool, bool),
-	) -> Result<(TransactionSimulationResult, AccountRawKeys), Error> {
+		(mut transaction, config): (VersionedTransaction, SimulateTransactionConfig),
+	) -> Result<SimulateTransactionResponse, Error> {
+		if config.sig_verify && config.replace_recent_blockhash {
+			return Err(Error::InvalidParams);
+		}
+
+		let replacement_blockhash = config.replace_recent_blockhash.then(|| {
+			let (blockhash, last_valid_block_height) =
+				pallet_solana::Pallet::<T>::latest_blockhash();
+			transaction.message.set_recent_blockhash(blockhash);
+			(blockhash, last_valid_block_height)
+		});
+
 		let transaction = SanitizedTransaction::try_create(
 			transaction,
 			MessageHash::Compute,
@@ -65,15 +104,30 @@ where
 
 		// TODO: Get feature_set
 		let feature_set = FeatureSet::default();
-		if sig_verify {
+		if config.sig_verify {
 			verify_transaction(&transaction, &feature_set)?;
 		}
 
 		let account_keys = get_account_keys(transaction.message());
-		let simulation_result =
-			pallet_solana::Pallet::<T>::simulate_transaction(transaction, enable_cpi_recording);
+		let result = pallet_solana::Pallet::<T>::simulate_transaction(
+			transaction,
+			config.enable_cpi_recording,
+		);
+
+		let accounts = config.accounts.map(|pubkeys| {
+			pubkeys
+				.iter()
+				.map(|pubkey| {
+					result
+						.post_simulation_accounts
+						.iter()
+						.find(|(key, _)| key == pubkey)
+						.map(|(_, account)| Account::from(account.clone()))
+				})
+				.collect()
+		});
 
-		Ok((simulation_result, account_keys))
+		Ok(SimulateTransactionResponse { result, account_keys, accounts, replacement_blockhash })
 	}
 }
 
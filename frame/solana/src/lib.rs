@@ -32,6 +32,7 @@ extern crate solana_metrics;
 pub use pallet::*;
 pub use types::*;
 
+pub use solana_program_runtime;
 pub use solana_rbpf;
 pub use solana_sdk::{pubkey::Pubkey, transaction::VersionedTransaction as Transaction};
 
@@ -87,11 +88,13 @@ impl<O: Into<Result<RawOrigin, O>> + From<RawOrigin>> EnsureOrigin<O> for Ensure
 pub mod pallet {
 	use super::*;
 	use crate::{
-		runtime::bank::{Bank, TransactionSimulationResult},
+		runtime::{
+			bank::{Bank, TransactionSimulationResult},
+			builtins::BuiltinPrograms,
+		},
 		svm::{
 			transaction_processor::{
 				ExecutionRecordingConfig, LoadAndExecuteSanitizedTransactionOutput,
-				TransactionProcessingConfig, TransactionProcessingEnvironment,
 				TransactionProcessor,
 			},
 			transaction_results::TransactionExecutionResult,
@@ -103,22 +106,27 @@ pub mod pallet {
 		pallet_prelude::*,
 		traits::{
 			fungible,
-			fungible::Inspect,
-			tokens::{Fortitude::Polite, Preservation::Preserve},
+			fungible::{Inspect, Unbalanced},
+			tokens::{Fortitude::Polite, Precision::Exact, Preservation::Expendable},
 		},
 	};
 	use frame_system::{pallet_prelude::*, CheckWeight};
-	use nostd::{prelude::*, sync::Arc};
+	use nostd::prelude::*;
 	use np_runtime::traits::LossyInto;
 	use parity_scale_codec::Codec;
 	use solana_sdk::{
 		account::Account,
 		bpf_loader, clock,
-		feature_set::FeatureSet,
+		epoch_schedule::EpochSchedule,
 		fee_calculator::FeeCalculator,
 		hash::Hash,
 		message::SimpleAddressLoader,
+		rent_collector::RENT_EXEMPT_RENT_EPOCH,
 		reserved_account_keys::ReservedAccountKeys,
+		slot_hashes::SlotHashes,
+		slot_history::SlotHistory,
+		system_program,
+		sysvar::{recent_blockhashes::RecentBlockhashes, Sysvar},
 		transaction::{MessageHash, SanitizedTransaction},
 		transaction_context::TransactionAccount,
 	};
@@ -179,10 +187,23 @@ pub mod pallet {
 		#[pallet::no_default_bounds]
 		type GenesisTimestamp: Get<Self::Moment>;
 
+		/// Maps a block number to the slot of that block.
+		#[pallet::no_default_bounds]
+		type BlockNumberToSlot: Convert<BlockNumberFor<Self>, clock::Slot>;
+
+		/// Builtin programs provided by the runtime.
+		type BuiltinPrograms: BuiltinPrograms;
+
 		/// Maximum scan result size in bytes.
 		#[pallet::constant]
 		type ScanResultsLimitBytes: Get<Option<u32>>;
 
+		/// Maximum size of log messages recorded for a transaction, in bytes.
+		///
+		/// Logs beyond this limit are truncated. `None` means no limit.
+		#[pallet::constant]
+		type LogMessagesBytesLimit: Get<Option<u32>>;
+
 		/// Maximum number of transactions to cache for tracking processed ones.
 		#[pallet::constant]
 		type TransactionCacheLimit: Get<u32>;
@@ -203,6 +224,7 @@ pub mod pallet {
 
 		parameter_types! {
 			pub const ScanResultsLimitBytes: Option<u32> = None;
+			pub const LogMessagesBytesLimit: Option<u32> = Some(10 * 1000);
 		}
 
 		#[frame_support::register_default_impl(TestDefaultConfig)]
@@ -217,13 +239,22 @@ pub mod pallet {
 			/// Timestamp at genesis block (Solana).
 			#[allow(clippy::inconsistent_digit_grouping)]
 			type GenesisTimestamp = ConstU64<1584336540_000>;
+			/// Every block occupies its own slot.
+			type BlockNumberToSlot = BlockNumberAsSlot;
+			/// No builtin programs other than the native ones.
+			type BuiltinPrograms = ();
 			/// Maximum scan result size in bytes.
 			type ScanResultsLimitBytes = ScanResultsLimitBytes;
+			/// Maximum size of log messages recorded for a transaction (10 KB).
+			type LogMessagesBytesLimit = LogMessagesBytesLimit;
 			/// Maximum number of transactions to cache for tracking processed ones.
 			type TransactionCacheLimit = ConstU32<10000>;
 		}
 	}
 
+	/// Number of sysvars read by [`Bank::new_from_parent`] at the start of every block.
+	const NUM_BLOCK_SYSVARS: u64 = 6;
+
 	#[pallet::pallet]
 	pub struct Pallet<T>(_);
 
@@ -292,6 +323,7 @@ pub mod pallet {
 			self.accounts.iter().for_each(|(pubkey, account)| {
 				let who = T::AccountIdConversion::convert(*pubkey);
 				assert!(<frame_system::Pallet<T>>::account_exists(&who));
+				<frame_system::Pallet<T>>::inc_sufficients(&who);
 				<AccountMeta<T>>::insert(
 					&who,
 					AccountMetadata {
@@ -313,9 +345,10 @@ pub mod pallet {
 	#[pallet::hooks]
 	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
 		fn on_initialize(now: BlockNumberFor<T>) -> Weight {
-			let elapsed =
-				<pallet_timestamp::Now<T>>::get().saturating_sub(T::GenesisTimestamp::get());
-			<Slot<T>>::put((elapsed / T::Moment::from(400u32)).saturated_into::<clock::Slot>());
+			let parent_number = now.saturating_sub(One::one());
+			let parent_slot = T::BlockNumberToSlot::convert(parent_number);
+			let slot = T::BlockNumberToSlot::convert(now);
+			<Slot<T>>::put(slot);
 
 			let parent_hash = <frame_system::Pallet<T>>::parent_hash();
 			<BlockhashQueue<T>>::insert(
@@ -323,11 +356,31 @@ pub mod pallet {
 				HashInfo {
 					// FIXME: Update fee calculator.
 					fee_calculator: FeeCalculator::default(),
-					hash_index: now.saturating_sub(One::one()),
+					hash_index: parent_number,
 					timestamp: <pallet_timestamp::Pallet<T>>::get(),
 				},
 			);
-			Weight::zero()
+
+			<Bank<T>>::new_from_parent(
+				parent_slot,
+				T::HashConversion::convert_back(parent_hash),
+				slot,
+			);
+
+			// Sysvars are read once each, slot hashes, slot history and recent blockhashes are
+			// rewritten, and the blockhash queue is scanned to rebuild recent blockhashes.
+			let max_age: u64 = T::BlockhashQueueMaxAge::get().saturated_into();
+			let weight =
+				T::DbWeight::get().reads_writes(max_age.saturating_add(NUM_BLOCK_SYSVARS + 2), 8);
+			// Only the data of the rewritten sysvars and the blockhash queue enter the proof.
+			#[allow(deprecated)]
+			let sysvars_len =
+				SlotHashes::size_of() + SlotHistory::size_of() + RecentBlockhashes::size_of();
+			let entry_len = 8 + T::Hash::max_encoded_len() + HashInfo::<T>::max_encoded_len();
+			weight.saturating_add(Weight::from_parts(
+				0,
+				max_age.saturating_mul(entry_len as u64).saturating_add(sysvars_len as u64),
+			))
 		}
 
 		fn on_finalize(now: BlockNumberFor<T>) {
@@ -359,11 +412,18 @@ pub mod pallet {
 	impl<T: Config> Pallet<T> {
 		pub fn create_account(pubkey: Pubkey, owner: Pubkey, executable: bool) {
 			let who = T::AccountIdConversion::convert(pubkey);
-			<frame_system::Pallet<T>>::inc_sufficients(&who);
-			<AccountMeta<T>>::insert(
-				who,
-				AccountMetadata { owner, executable, rent_epoch: u64::MAX },
-			);
+			if !<AccountMeta<T>>::contains_key(&who) {
+				<frame_system::Pallet<T>>::inc_sufficients(&who);
+			}
+			// Executable accounts never pay rent. Others owe rent from the current epoch
+			// until the rent collector finds them rent-exempt.
+			let rent_epoch = if executable { RENT_EXEMPT_RENT_EPOCH } else { Self::epoch() };
+			<AccountMeta<T>>::insert(who, AccountMetadata { owner, executable, rent_epoch });
+		}
+
+		/// Epoch of the current slot.
+		pub fn epoch() -> clock::Epoch {
+			EpochSchedule::without_warmup().get_epoch(<Slot<T>>::get())
 		}
 
 		pub fn deploy_program(
@@ -393,6 +453,16 @@ pub mod pallet {
 				.filter(|info| last_hash_index - info.hash_index <= max_age)
 		}
 
+		/// Returns the latest blockhash and the last block height at which a transaction
+		/// referencing it is still accepted.
+		pub fn latest_blockhash() -> (Hash, u64) {
+			let blockhash =
+				T::HashConversion::convert_back(<frame_system::Pallet<T>>::parent_hash());
+			let last_valid_block_height = <frame_system::Pallet<T>>::block_number()
+				.saturating_add(T::BlockhashQueueMaxAge::get());
+			(blockhash, last_valid_block_height.saturated_into())
+		}
+
 		fn apply_validated_transaction(
 			fee_payer: Pubkey,
 			transaction: Transaction,
@@ -445,32 +515,83 @@ pub mod pallet {
 		}
 
 		pub fn get_balance(pubkey: Pubkey) -> u64 {
-			Lamports::<T>::new(T::Currency::reducible_balance(
-				&T::AccountIdConversion::convert(pubkey),
-				Preserve,
-				Polite,
-			))
-			.get()
+			Self::lamports(&T::AccountIdConversion::convert(pubkey)).get()
 		}
 
+		/// Returns the account as seen by both the SVM and RPC.
+		///
+		/// An account without Solana metadata that holds lamports is a plain system account,
+		/// e.g. one that has only received native transfers.
 		pub fn get_account_info(pubkey: Pubkey) -> Option<Account> {
-			let meta = AccountMeta::<T>::get(T::AccountIdConversion::convert(pubkey));
-
-			if let Some(meta) = meta {
-				let lamports = Pallet::<T>::get_balance(pubkey);
-				let data: Vec<u8> =
-					AccountData::<T>::get(T::AccountIdConversion::convert(pubkey)).into();
-
-				Some(Account {
-					lamports,
-					data,
-					owner: meta.owner,
-					executable: meta.executable,
-					rent_epoch: meta.rent_epoch,
+			let who = T::AccountIdConversion::convert(pubkey);
+			let lamports = Self::lamports(&who).get();
+			let meta = AccountMeta::<T>::get(&who).or_else(|| {
+				(lamports > 0).then_some(AccountMetadata {
+					rent_epoch: RENT_EXEMPT_RENT_EPOCH,
+					owner: system_program::id(),
+					executable: false,
 				})
-			} else {
-				None
+			})?;
+			let data: Vec<u8> = AccountData::<T>::get(&who).into();
+
+			Some(Account {
+				lamports,
+				data,
+				owner: meta.owner,
+				executable: meta.executable,
+				rent_epoch: meta.rent_epoch,
+			})
+		}
+
+		/// Lamports of an account.
+		///
+		/// Only whole lamports of the reducible balance are visible. The sub-lamport remainder
+		/// is never moved by the SVM and stays with the account until it is reaped.
+		pub fn lamports(who: &T::AccountId) -> Lamports<T> {
+			Lamports::new(T::Currency::reducible_balance(who, Expendable, Polite))
+		}
+
+		/// Whether the lamports of an account can be set to `lamports`.
+		///
+		/// An account may either be emptied, in which case the remaining dust is reaped along
+		/// with the account, or keep at least the existential deposit.
+		pub fn can_set_lamports(who: &T::AccountId, lamports: u64) -> bool {
+			let current = Self::lamports(who).get();
+			let balance = T::Currency::balance(who);
+			let new_balance = match lamports.cmp(&current) {
+				core::cmp::Ordering::Greater => <Lamports<T>>::default()
+					.checked_add(lamports - current)
+					.and_then(|delta| balance.checked_add(&delta.into_inner())),
+				core::cmp::Ordering::Less =>
+					balance.checked_sub(&<Lamports<T>>::from(current - lamports).into_inner()),
+				core::cmp::Ordering::Equal => return true,
+			};
+			new_balance.is_some_and(|new_balance| {
+				lamports == 0 || new_balance >= T::Currency::minimum_balance()
+			})
+		}
+
+		/// Sets the lamports of an account by moving whole lamports only.
+		pub fn set_lamports(who: &T::AccountId, lamports: u64) -> DispatchResult {
+			let current = Self::lamports(who).get();
+			match lamports.cmp(&current) {
+				core::cmp::Ordering::Greater => {
+					let amount = <Lamports<T>>::from(lamports - current);
+					T::Currency::increase_balance(who, amount.into_inner(), Exact)?;
+				},
+				core::cmp::Ordering::Less => {
+					let amount = <Lamports<T>>::from(current - lamports);
+					T::Currency::decrease_balance(
+						who,
+						amount.into_inner(),
+						Exact,
+						Expendable,
+						Polite,
+					)?;
+				},
+				core::cmp::Ordering::Equal => {},
 			}
+			Ok(())
 		}
 
 		pub fn simulate_transaction(
@@ -485,33 +606,15 @@ pub mod pallet {
 			let check_result =
 				bank.check_transaction(&sanitized_tx, T::BlockhashQueueMaxAge::get());
 
-			let blockhash =
-				T::HashConversion::convert_back(<frame_system::Pallet<T>>::parent_hash());
-			// FIXME: Update lamports_per_signature.
-			let lamports_per_signature = Default::default();
-			let processing_environment = TransactionProcessingEnvironment {
-				blockhash,
-				epoch_total_stake: None,
-				epoch_vote_accounts: None,
-				feature_set: Arc::new(FeatureSet::default()),
-				fee_structure: None,
-				lamports_per_signature,
-				rent_collector: None,
-			};
-			// FIXME: Update fields.
-			let processing_config = TransactionProcessingConfig {
-				account_overrides: None,
-				check_program_modification_slot: false,
-				compute_budget: None,
-				log_messages_bytes_limit: None,
-				limit_to_load_programs: false,
-				recording_config: ExecutionRecordingConfig {
+			let processing_environment = bank.processing_environment();
+			let processing_config = bank.processing_config(
+				&sanitized_tx,
+				ExecutionRecordingConfig {
 					enable_cpi_recording,
 					enable_log_recording: true,
 					enable_return_data_recording: true,
 				},
-				transaction_account_lock_limit: None,
-			};
+			);
 
 			let transaction_processor = TransactionProcessor::default();
 			let LoadAndExecuteSanitizedTransactionOutput {
@@ -539,19 +642,22 @@ pub mod pallet {
 				.unwrap_or_default();
 
 			let flattened_result = execution_result.flattened_result();
-			let (logs, return_data, inner_instructions) = match execution_result {
-				TransactionExecutionResult::Executed { details, .. } =>
-					(details.log_messages, details.return_data, details.inner_instructions),
-				TransactionExecutionResult::NotExecuted(_) => (None, None, None),
+			let (logs, return_data, inner_instructions, units_consumed) = match execution_result {
+				TransactionExecutionResult::Executed { details, .. } => (
+					details.log_messages,
+					details.return_data,
+					details.inner_instructions,
+					details.executed_units,
+				),
+				TransactionExecutionResult::NotExecuted(_) => (None, None, None, 0),
 			};
 			let logs = logs.unwrap_or_default();
 
-			// TODO: Calculate units_consumed
 			TransactionSimulationResult {
 				result: flattened_result,
 				logs,
 				post_simulation_accounts,
-				units_consumed: 0,
+				units_consumed,
 				return_data,
 				inner_instructions,
 			}
@@ -19,7 +19,7 @@
 use crate as pallet_solana;
 
 use frame_support::{
-	derive_impl,
+	derive_impl, parameter_types,
 	sp_runtime::{
 		traits::{ConstU128, Convert, ConvertBack, IdentityLookup},
 		BuildStorage,
@@ -77,10 +77,14 @@ impl pallet_timestamp::Config for Test {
 	type OnTimestampSet = ();
 }
 
+parameter_types! {
+	pub static ExistentialDeposit: Balance = 1;
+}
+
 #[derive_impl(pallet_balances::config_preludes::TestDefaultConfig)]
 impl pallet_balances::Config for Test {
 	type Balance = Balance;
-	type ExistentialDeposit = ConstU128<1>;
+	type ExistentialDeposit = ExistentialDeposit;
 	type AccountStore = System;
 }
 
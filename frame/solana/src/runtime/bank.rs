@@ -17,12 +17,14 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use crate::{
+	runtime::builtins::{BuiltinProgram, BuiltinPrograms},
 	svm::{
 		account_loader::{
 			CheckedTransactionDetails, TransactionCheckResult, TransactionLoadResult,
 		},
 		nonce_info::{NonceInfo, NoncePartial},
 		rollback_accounts::RollbackAccounts,
+		transaction_account_state_info::TransactionAccountStateInfo,
 		transaction_processing_callback::TransactionProcessingCallback,
 		transaction_processor::{
 			ExecutionRecordingConfig, TransactionLogMessages, TransactionProcessingConfig,
@@ -30,26 +32,29 @@ use crate::{
 		},
 		transaction_results::TransactionExecutionResult,
 	},
-	AccountData, AccountMeta, AccountMetadata, Config, Lamports, Pallet,
+	AccountData, AccountMeta, AccountMetadata, BlockhashQueue, Config, Pallet,
 };
 use frame_support::{
-	sp_runtime::traits::{Convert, ConvertBack, SaturatedConversion},
-	traits::{
-		fungible::{Inspect, Unbalanced},
-		tokens::{Fortitude::Polite, Precision::Exact, Preservation::Preserve},
-		Get,
+	sp_runtime::{
+		traits::{Convert, ConvertBack, SaturatedConversion},
+		DispatchError,
 	},
+	storage::{with_transaction, TransactionOutcome},
+	traits::Get,
 	BoundedVec,
 };
 use frame_system::pallet_prelude::BlockNumberFor;
 use nostd::{marker::PhantomData, prelude::*, sync::Arc};
 use np_runtime::traits::LateInit;
 use serde::{Deserialize, Serialize};
+use solana_compute_budget::{
+	compute_budget::ComputeBudget, compute_budget_processor::process_compute_budget_instructions,
+};
 use solana_program_runtime::loaded_programs::ProgramCacheEntry;
 use solana_sdk::{
 	account::{
-		create_account_shared_data_with_fields as create_account, from_account, Account,
-		AccountSharedData, InheritableAccountFields, ReadableAccount,
+		create_account_shared_data_with_fields as create_account, from_account, AccountSharedData,
+		InheritableAccountFields, ReadableAccount, WritableAccount,
 	},
 	account_utils::StateMut,
 	clock::{Epoch, Slot, UnixTimestamp, INITIAL_RENT_EPOCH},
@@ -66,12 +71,24 @@ use solana_sdk::{
 	},
 	nonce_account,
 	pubkey::Pubkey,
+	rent::Rent,
 	rent_collector::RentCollector,
-	sysvar,
-	transaction::{Result, SanitizedTransaction, TransactionError},
-	transaction_context::{TransactionAccount, TransactionReturnData},
+	slot_hashes::SlotHashes,
+	slot_history::SlotHistory,
+	sysvar::{
+		self,
+		epoch_rewards::EpochRewards,
+		last_restart_slot::LastRestartSlot,
+		recent_blockhashes::{self, RecentBlockhashes},
+		stake_history::StakeHistory,
+	},
+	transaction::{Result, SanitizedTransaction, TransactionError, MAX_TX_ACCOUNT_LOCKS},
+	transaction_context::{IndexOfAccount, TransactionAccount, TransactionReturnData},
 };
 
+/// Approximate number of seconds in a year, as used by Solana's rent calculation.
+const SECONDS_PER_YEAR: f64 = 365.242_199 * 24.0 * 60.0 * 60.0;
+
 #[derive(Serialize, Deserialize)]
 pub struct TransactionSimulationResult {
 	pub result: Result<()>,
@@ -101,24 +118,50 @@ pub struct Bank<T> {
 }
 
 impl<T: Config> Bank<T> {
-	pub fn new(/* collector_id: Pubkey, */ slot: Slot) -> Self {
+	fn with_slot(slot: Slot) -> Self {
 		let epoch_schedule = EpochSchedule::without_warmup();
 		let epoch = epoch_schedule.get_epoch(slot);
 		let genesis_creation_time =
-			T::GenesisTimestamp::get().saturated_into::<u64>() as UnixTimestamp;
-		let mut bank = Self {
-			ns_per_slot: 400_000_000,
+			(T::GenesisTimestamp::get().saturated_into::<u64>() / 1000) as UnixTimestamp;
+		let ns_per_slot = 400_000_000;
+		let slots_per_year = SECONDS_PER_YEAR * 1_000_000_000f64 / ns_per_slot as f64;
+		Self {
+			ns_per_slot,
 			genesis_creation_time,
 			slot,
 			epoch,
 			//collector_id,
-			rent_collector: Default::default(),
+			rent_collector: RentCollector::new(
+				epoch,
+				epoch_schedule.clone(),
+				slots_per_year,
+				Rent::default(),
+			),
 			epoch_schedule,
 			transaction_processor: Default::default(),
 			_marker: PhantomData,
-		};
+		}
+	}
+
+	/// Creates a bank for a new block and updates the sysvars that change with every block.
+	///
+	/// The returned bank has no transaction processor and cannot execute transactions.
+	pub fn new_from_parent(parent_slot: Slot, parent_hash: Hash, slot: Slot) -> Self {
+		let bank = Self::with_slot(slot);
+		bank.update_slot_hashes(parent_slot, parent_hash);
+		bank.update_slot_history();
+		bank.update_recent_blockhashes();
+		bank.update_stake_history();
+		bank.update_epoch_rewards();
+		bank.update_last_restart_slot();
+		bank
+	}
+
+	pub fn new(/* collector_id: Pubkey, */ slot: Slot) -> Self {
+		let mut bank = Self::with_slot(slot);
 
-		let mut transaction_processor = TransactionProcessor::new(slot, epoch, Default::default());
+		let mut transaction_processor =
+			TransactionProcessor::new(slot, bank.epoch, Default::default());
 		// BUILTINS
 		transaction_processor.add_builtin(
 			&bank,
@@ -180,16 +223,24 @@ impl<T: Config> Bank<T> {
 				solana_address_lookup_table_program::processor::Entrypoint::vm,
 			),
 		);
+		for BuiltinProgram { program_id, name, entrypoint } in
+			T::BuiltinPrograms::builtin_programs()
+		{
+			transaction_processor.add_builtin(
+				&bank,
+				program_id,
+				name,
+				ProgramCacheEntry::new_builtin(0, name.len(), entrypoint),
+			);
+		}
 		// TODO: verify_precompiles.
 		// PRECOMPILES
 		// self.add_precompile(&solana_sdk::secp256k1_program::id());
 		// self.add_precompile(&solana_sdk::ed25519_program::id());
 
-		bank.update_clock(None);
+		bank.update_clock();
 		bank.update_rent();
 		bank.update_epoch_schedule();
-		//bank.update_recent_blockhashes();
-		//bank.update_last_restart_slot();
 		transaction_processor.fill_missing_sysvar_cache_entries(&bank);
 
 		bank.transaction_processor.init(transaction_processor);
@@ -204,55 +255,124 @@ impl<T: Config> Bank<T> {
 		&self.epoch_schedule
 	}
 
-	pub fn load_execute_and_commit_sanitized_transaction(
-		&self,
-		sanitized_tx: &SanitizedTransaction,
-	) -> Result<()> {
-		let check_result = self.check_transaction(sanitized_tx, T::BlockhashQueueMaxAge::get());
+	pub fn rent_collector(&self) -> &RentCollector {
+		&self.rent_collector
+	}
 
+	/// Environment shared by every transaction processed in the current block.
+	pub fn processing_environment(&self) -> TransactionProcessingEnvironment<'_> {
 		let blockhash = T::HashConversion::convert_back(<frame_system::Pallet<T>>::parent_hash());
 		// FIXME: Update lamports_per_signature.
 		let lamports_per_signature = Default::default();
-		let processing_environment = TransactionProcessingEnvironment {
+		TransactionProcessingEnvironment {
 			blockhash,
 			epoch_total_stake: None,
 			epoch_vote_accounts: None,
 			feature_set: Arc::new(FeatureSet::default()),
 			fee_structure: None,
 			lamports_per_signature,
-			rent_collector: None,
-		};
-		// FIXME: Update fields.
-		let processing_config = TransactionProcessingConfig {
+			rent_collector: Some(&self.rent_collector),
+		}
+	}
+
+	/// Processing configuration for `tx`, with the compute budget requested by its
+	/// compute budget instructions.
+	pub fn processing_config(
+		&self,
+		tx: &SanitizedTransaction,
+		recording_config: ExecutionRecordingConfig,
+	) -> TransactionProcessingConfig<'static> {
+		let compute_budget =
+			process_compute_budget_instructions(tx.message().program_instructions_iter())
+				.ok()
+				.map(ComputeBudget::from);
+		TransactionProcessingConfig {
 			account_overrides: None,
 			check_program_modification_slot: false,
-			compute_budget: None,
-			log_messages_bytes_limit: None,
+			compute_budget,
+			log_messages_bytes_limit: T::LogMessagesBytesLimit::get()
+				.map(|bytes_limit| bytes_limit as usize),
 			limit_to_load_programs: false,
-			recording_config: ExecutionRecordingConfig {
+			recording_config,
+			transaction_account_lock_limit: Some(MAX_TX_ACCOUNT_LOCKS),
+		}
+	}
+
+	pub fn load_execute_and_commit_sanitized_transaction(
+		&self,
+		sanitized_tx: &SanitizedTransaction,
+	) -> Result<()> {
+		let check_result = self.check_transaction(sanitized_tx, T::BlockhashQueueMaxAge::get());
+
+		let processing_environment = self.processing_environment();
+		let processing_config = self.processing_config(
+			sanitized_tx,
+			ExecutionRecordingConfig {
 				enable_cpi_recording: false,
 				enable_log_recording: true,
 				enable_return_data_recording: true,
 			},
-			transaction_account_lock_limit: None,
-		};
+		);
 
-		let mut sanitized_output =
-			self.transaction_processor.load_and_execute_sanitized_transaction(
-				self,
-				sanitized_tx,
-				check_result,
-				&processing_environment,
-				&processing_config,
-			);
+		// Balances may also be changed by runtime calls dispatched from builtin programs,
+		// so the transaction only commits the change of lamports it made itself.
+		let pre_lamports = sanitized_tx
+			.message()
+			.account_keys()
+			.iter()
+			.map(|address| <Pallet<T>>::lamports(&T::AccountIdConversion::convert(*address)).get())
+			.collect::<Vec<_>>();
+
+		transactional(|| {
+			// Changes made by runtime calls dispatched from a failed transaction are discarded,
+			// as are all the changes of a transaction whose accounts cannot be committed.
+			let outcome = with_transaction(|| {
+				let mut output = self.transaction_processor.load_and_execute_sanitized_transaction(
+					self,
+					sanitized_tx,
+					check_result,
+					&processing_environment,
+					&processing_config,
+				);
+				let mut rejected = None;
+				if output.execution_result.was_executed_successfully() {
+					match self.commit_transaction(
+						sanitized_tx,
+						&mut output.loaded_transaction,
+						output.execution_result.clone(),
+						processing_environment.blockhash,
+						Default::default(),
+						&pre_lamports,
+					) {
+						Ok(()) => return TransactionOutcome::Commit(Ok(None)),
+						Err(e) => {
+							if let TransactionExecutionResult::Executed { details, .. } =
+								&mut output.execution_result
+							{
+								details.status = Err(e.clone());
+							}
+							rejected = Some(e);
+						},
+					}
+				}
+				TransactionOutcome::Rollback(Ok::<_, DispatchError>(Some((output, rejected))))
+			})
+			.map_err(|_| TransactionError::CallChainTooDeep)?;
 
-		self.commit_transaction(
-			sanitized_tx,
-			&mut sanitized_output.loaded_transaction,
-			sanitized_output.execution_result.clone(),
-			blockhash,
-			Default::default(),
-		)
+			let Some((mut sanitized_output, rejected)) = outcome else { return Ok(Ok(())) };
+
+			// Like in Agave, a transaction whose accounts cannot be committed still pays its fee
+			// and advances its durable nonce, but its failure is reported.
+			self.commit_transaction(
+				sanitized_tx,
+				&mut sanitized_output.loaded_transaction,
+				sanitized_output.execution_result.clone(),
+				processing_environment.blockhash,
+				Default::default(),
+				&pre_lamports,
+			)?;
+			Ok(rejected.map_or(Ok(()), Err))
+		})?
 	}
 
 	pub fn commit_transaction(
@@ -262,6 +382,7 @@ impl<T: Config> Bank<T> {
 		execution_result: TransactionExecutionResult,
 		last_blockhash: Hash,
 		lamports_per_signature: u64,
+		pre_lamports: &[u64],
 	) -> Result<()> {
 		let durable_nonce = DurableNonce::from_blockhash(&last_blockhash);
 
@@ -319,7 +440,7 @@ impl<T: Config> Bank<T> {
 
 					if should_collect_account {
 						// Add to the accounts to store
-						accounts.push((&*address, &*account));
+						accounts.push((i, &*address, &*account));
 						transactions.push(Some(tx));
 					}
 				}
@@ -331,34 +452,54 @@ impl<T: Config> Bank<T> {
 			return Ok(());
 		}
 
+		let accounts = accounts
+			.into_iter()
+			.map(|(index, address, account)| {
+				let pubkey = T::AccountIdConversion::convert(*address);
+				let lamports = <Pallet<T>>::lamports(&pubkey)
+					.get()
+					.checked_add(account.lamports())
+					.and_then(|lamports| lamports.checked_sub(pre_lamports[index]))
+					.ok_or(TransactionError::InsufficientFundsForRent {
+						account_index: index as u8,
+					})?;
+				let mut account = account.clone();
+				account.set_lamports(lamports);
+				Ok((index, address, pubkey, account))
+			})
+			.collect::<Result<Vec<_>>>()?;
+
+		// Every committed account must make an allowed rent state transition from what is
+		// currently stored, so that no path can leave a new account below rent exemption.
+		// An account must also not be left with a balance below the existential deposit.
+		for (index, address, pubkey, account) in accounts.iter() {
+			TransactionAccountStateInfo::verify_commit(
+				&self.rent_collector.rent,
+				address,
+				self.get_account_shared_data(address).as_ref(),
+				account,
+				*index as IndexOfAccount,
+			)?;
+			if !<Pallet<T>>::can_set_lamports(pubkey, account.lamports()) {
+				return Err(TransactionError::InsufficientFundsForRent {
+					account_index: *index as u8,
+				});
+			}
+		}
+
 		// TODO: check has_space_available.
 		// TODO: check imbalance.
 
-		for (address, account) in accounts.iter() {
-			let pubkey = T::AccountIdConversion::convert(**address);
-			let lamports =
-				<Lamports<T>>::new(T::Currency::reducible_balance(&pubkey, Preserve, Polite));
-			match account.lamports().cmp(&lamports.get()) {
-				core::cmp::Ordering::Greater => {
-					let amount = <Lamports<T>>::from(account.lamports() - lamports.get());
-					// TODO: error handling.
-					T::Currency::increase_balance(&pubkey, amount.into_inner(), Exact);
-				},
-				core::cmp::Ordering::Less => {
-					let amount = <Lamports<T>>::from(lamports.get() - account.lamports());
-					// TODO: error handling.
-					T::Currency::decrease_balance(
-						&pubkey,
-						amount.into_inner(),
-						Exact,
-						Preserve,
-						Polite,
-					);
-				},
-				_ => {},
-			}
+		for (index, _, pubkey, account) in accounts.iter() {
+			<Pallet<T>>::set_lamports(pubkey, account.lamports()).map_err(|_| {
+				TransactionError::InsufficientFundsForRent { account_index: *index as u8 }
+			})?;
 
-			self.store_account(&pubkey, account);
+			if is_reclaimable(account) {
+				Self::purge_account(pubkey);
+			} else {
+				self.store_account(pubkey, account);
+			}
 		}
 
 		Ok(())
@@ -451,6 +592,11 @@ impl<T: Config> Bank<T> {
 		)
 	}
 
+	/// Whether the sysvar account exists, without reading its data.
+	fn sysvar_exists(&self, pubkey: &Pubkey) -> bool {
+		<AccountMeta<T>>::contains_key(T::AccountIdConversion::convert(*pubkey))
+	}
+
 	fn update_sysvar_account<F>(&self, pubkey: &Pubkey, updater: F)
 	where
 		F: Fn(&Option<AccountSharedData>) -> AccountSharedData,
@@ -474,19 +620,27 @@ impl<T: Config> Bank<T> {
 			.unwrap_or_default()
 	}
 
-	fn update_clock(&self, parent_epoch: Option<Epoch>) {
-		let mut unix_timestamp = self.clock().unix_timestamp;
-		let mut epoch_start_timestamp =
-		// On epoch boundaries, update epoch_start_timestamp
-		if parent_epoch.is_some() && parent_epoch.unwrap() != self.epoch() {
-			unix_timestamp
+	/// Unix timestamp of the current block, or the computed one if the block time is not set.
+	fn unix_timestamp(&self) -> UnixTimestamp {
+		let now = <pallet_timestamp::Pallet<T>>::get().saturated_into::<u64>();
+		if now == 0 {
+			self.unix_timestamp_from_genesis()
 		} else {
-			self.clock().epoch_start_timestamp
-		};
-		if self.slot == 0 {
-			unix_timestamp = self.unix_timestamp_from_genesis();
-			epoch_start_timestamp = self.unix_timestamp_from_genesis();
+			(now / 1000) as UnixTimestamp
 		}
+	}
+
+	fn update_clock(&self) {
+		let parent_clock = self
+			.get_account_shared_data(&sysvar::clock::id())
+			.map(|account| from_account::<sysvar::clock::Clock, _>(&account).unwrap_or_default());
+		let unix_timestamp = self.unix_timestamp();
+		// On epoch boundaries, update epoch_start_timestamp
+		let epoch_start_timestamp = match parent_clock {
+			Some(parent_clock) if parent_clock.epoch == self.epoch() =>
+				parent_clock.epoch_start_timestamp,
+			_ => unix_timestamp,
+		};
 		let clock = sysvar::clock::Clock {
 			slot: self.slot,
 			epoch_start_timestamp,
@@ -508,6 +662,88 @@ impl<T: Config> Bank<T> {
 		});
 	}
 
+	fn update_slot_hashes(&self, parent_slot: Slot, parent_hash: Hash) {
+		self.update_sysvar_account(&sysvar::slot_hashes::id(), |account| {
+			let mut slot_hashes =
+				account.as_ref().and_then(from_account::<SlotHashes, _>).unwrap_or_default();
+			slot_hashes.add(parent_slot, parent_hash);
+			create_account(&slot_hashes, self.inherit_specially_retained_account_fields(account))
+		});
+	}
+
+	fn update_slot_history(&self) {
+		self.update_sysvar_account(&sysvar::slot_history::id(), |account| {
+			let mut slot_history =
+				account.as_ref().and_then(from_account::<SlotHistory, _>).unwrap_or_default();
+			slot_history.add(self.slot);
+			create_account(&slot_history, self.inherit_specially_retained_account_fields(account))
+		});
+	}
+
+	#[allow(deprecated)]
+	fn update_recent_blockhashes(&self) {
+		let mut blockhashes = <BlockhashQueue<T>>::iter()
+			.map(|(hash, info)| {
+				(
+					info.hash_index.saturated_into::<u64>(),
+					T::HashConversion::convert_back(hash),
+					info.lamports_per_signature(),
+				)
+			})
+			.collect::<Vec<_>>();
+		// Most recent first, as in the blockhash queue of Solana.
+		blockhashes.sort_by(|a, b| b.0.cmp(&a.0));
+		let recent_blockhashes = blockhashes
+			.iter()
+			.take(recent_blockhashes::MAX_ENTRIES)
+			.map(|(index, hash, lamports_per_signature)| {
+				recent_blockhashes::IterItem(*index, hash, *lamports_per_signature)
+			})
+			.collect::<RecentBlockhashes>();
+		self.update_sysvar_account(&sysvar::recent_blockhashes::id(), |account| {
+			create_account(
+				&recent_blockhashes,
+				self.inherit_specially_retained_account_fields(account),
+			)
+		});
+	}
+
+	/// Stake history stays empty as there is no native staking.
+	fn update_stake_history(&self) {
+		if !self.sysvar_exists(&sysvar::stake_history::id()) {
+			self.update_sysvar_account(&sysvar::stake_history::id(), |account| {
+				create_account(
+					&StakeHistory::default(),
+					self.inherit_specially_retained_account_fields(account),
+				)
+			});
+		}
+	}
+
+	/// Epoch rewards are never distributed, so the sysvar is always inactive.
+	fn update_epoch_rewards(&self) {
+		if !self.sysvar_exists(&sysvar::epoch_rewards::id()) {
+			self.update_sysvar_account(&sysvar::epoch_rewards::id(), |account| {
+				create_account(
+					&EpochRewards::default(),
+					self.inherit_specially_retained_account_fields(account),
+				)
+			});
+		}
+	}
+
+	/// The chain has never been restarted from a snapshot.
+	fn update_last_restart_slot(&self) {
+		if !self.sysvar_exists(&sysvar::last_restart_slot::id()) {
+			self.update_sysvar_account(&sysvar::last_restart_slot::id(), |account| {
+				create_account(
+					&LastRestartSlot::default(),
+					self.inherit_specially_retained_account_fields(account),
+				)
+			});
+		}
+	}
+
 	fn update_epoch_schedule(&self) {
 		self.update_sysvar_account(&sysvar::epoch_schedule::id(), |account| {
 			create_account(
@@ -519,6 +755,10 @@ impl<T: Config> Bank<T> {
 
 	fn store_account(&self, pubkey: &T::AccountId, account: &AccountSharedData) {
 		<AccountMeta<T>>::mutate(pubkey, |meta| {
+			// The Solana-specific state keeps the account alive until it is purged.
+			if meta.is_none() {
+				<frame_system::Pallet<T>>::inc_sufficients(pubkey);
+			}
 			*meta = Some(AccountMetadata {
 				rent_epoch: account.rent_epoch(),
 				owner: *account.owner(),
@@ -534,6 +774,14 @@ impl<T: Config> Bank<T> {
 		}
 	}
 
+	/// Removes the Solana-specific state of an account that no longer holds any lamports.
+	pub(crate) fn purge_account(pubkey: &T::AccountId) {
+		<AccountData<T>>::remove(pubkey);
+		if <AccountMeta<T>>::take(pubkey).is_some() {
+			<frame_system::Pallet<T>>::dec_sufficients(pubkey);
+		}
+	}
+
 	/*
 	fn add_precompile(&self, program_id: &Pubkey) {
 		// add_precompiled_account
@@ -569,18 +817,7 @@ impl<T: Config> TransactionProcessingCallback for Bank<T> {
 	}
 
 	fn get_account_shared_data(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
-		let pubkey = T::AccountIdConversion::convert(*pubkey);
-		let account = <AccountMeta<T>>::get(&pubkey)?;
-		let lamports =
-			<Lamports<T>>::new(T::Currency::reducible_balance(&pubkey, Preserve, Polite));
-		let data = <AccountData<T>>::get(&pubkey).into();
-		Some(AccountSharedData::from(Account {
-			lamports: lamports.get(),
-			data,
-			owner: account.owner,
-			executable: account.executable,
-			rent_epoch: account.rent_epoch,
-		}))
+		<Pallet<T>>::get_account_info(*pubkey).map(AccountSharedData::from)
 	}
 
 	fn add_builtin_account(&self, name: &str, program_id: &Pubkey) {
@@ -612,6 +849,27 @@ impl<T: Config> TransactionProcessingCallback for Bank<T> {
 	}
 }
 
+/// Zero-lamport accounts are garbage collected like in Solana, except for sysvar and builtin
+/// accounts which are maintained by the bank itself and not backed by a balance.
+fn is_reclaimable(account: &AccountSharedData) -> bool {
+	account.lamports() == 0 &&
+		!sysvar::check_id(account.owner()) &&
+		!native_loader::check_id(account.owner())
+}
+
+/// Runs `f` in a storage layer that is committed only if `f` succeeds.
+fn transactional<R>(f: impl FnOnce() -> Result<R>) -> Result<R> {
+	with_transaction(|| {
+		let result = f();
+		if result.is_ok() {
+			TransactionOutcome::Commit(Ok::<_, DispatchError>(result))
+		} else {
+			TransactionOutcome::Rollback(Ok(result))
+		}
+	})
+	.unwrap_or(Err(TransactionError::CallChainTooDeep))
+}
+
 fn post_process_failed_tx(
 	account: &mut AccountSharedData,
 	is_fee_payer: bool,
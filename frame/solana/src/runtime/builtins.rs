@@ -0,0 +1,39 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use nostd::prelude::*;
+use solana_program_runtime::invoke_context::BuiltinFunctionWithContext;
+use solana_sdk::pubkey::Pubkey;
+
+/// A builtin program provided by the runtime.
+pub struct BuiltinProgram {
+	pub program_id: Pubkey,
+	pub name: &'static str,
+	pub entrypoint: BuiltinFunctionWithContext,
+}
+
+/// Builtin programs registered to the bank in addition to the native ones.
+pub trait BuiltinPrograms {
+	fn builtin_programs() -> Vec<BuiltinProgram>;
+}
+
+impl BuiltinPrograms for () {
+	fn builtin_programs() -> Vec<BuiltinProgram> {
+		Vec::new()
+	}
+}
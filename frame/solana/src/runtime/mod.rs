@@ -19,3 +19,4 @@
 #![allow(unexpected_cfgs)]
 
 pub mod bank;
+pub mod builtins;
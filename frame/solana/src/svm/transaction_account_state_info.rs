@@ -18,9 +18,10 @@
 use crate::svm::account_rent_state::RentState;
 use nostd::prelude::*;
 use solana_sdk::{
-	account::ReadableAccount,
+	account::{AccountSharedData, ReadableAccount},
 	message::SanitizedMessage,
 	native_loader,
+	pubkey::Pubkey,
 	rent::Rent,
 	transaction::Result,
 	transaction_context::{IndexOfAccount, TransactionContext},
@@ -83,6 +84,28 @@ impl TransactionAccountStateInfo {
 		}
 		Ok(())
 	}
+
+	/// Verify that storing `post_account` over the currently stored `pre_account` is an
+	/// allowed rent state transition. A missing account is treated as uninitialized.
+	pub(crate) fn verify_commit(
+		rent: &Rent,
+		address: &Pubkey,
+		pre_account: Option<&AccountSharedData>,
+		post_account: &AccountSharedData,
+		account_index: IndexOfAccount,
+	) -> Result<()> {
+		let pre_rent_state = pre_account
+			.map(|account| RentState::from_account(account, rent))
+			.unwrap_or(RentState::Uninitialized);
+		let post_rent_state = RentState::from_account(post_account, rent);
+		RentState::check_rent_state_with_account(
+			&pre_rent_state,
+			&post_rent_state,
+			address,
+			post_account,
+			account_index,
+		)
+	}
 }
 
 #[cfg(test)]
@@ -224,4 +247,48 @@ mod test {
 			Some(TransactionError::InsufficientFundsForRent { account_index: 0 })
 		);
 	}
+
+	#[test]
+	fn test_verify_commit() {
+		let rent = Rent::default();
+		let key = Keypair::new().pubkey();
+		let minimum_balance = rent.minimum_balance(0);
+
+		let rent_exempt_account = AccountSharedData::new(minimum_balance, 0, &key);
+		assert!(TransactionAccountStateInfo::verify_commit(
+			&rent,
+			&key,
+			None,
+			&rent_exempt_account,
+			0
+		)
+		.is_ok());
+
+		let rent_paying_account = AccountSharedData::new(minimum_balance - 1, 0, &key);
+		assert_eq!(
+			TransactionAccountStateInfo::verify_commit(&rent, &key, None, &rent_paying_account, 1)
+				.err(),
+			Some(TransactionError::InsufficientFundsForRent { account_index: 1 })
+		);
+
+		let debited_account = AccountSharedData::new(minimum_balance - 2, 0, &key);
+		assert!(TransactionAccountStateInfo::verify_commit(
+			&rent,
+			&key,
+			Some(&rent_paying_account),
+			&debited_account,
+			0
+		)
+		.is_ok());
+
+		let reclaimed_account = AccountSharedData::default();
+		assert!(TransactionAccountStateInfo::verify_commit(
+			&rent,
+			&key,
+			Some(&rent_paying_account),
+			&reclaimed_account,
+			0
+		)
+		.is_ok());
+	}
 }
@@ -193,6 +193,16 @@ impl TransactionProcessor {
 		let mut error_metrics = TransactionErrorMetrics::default();
 		let mut execute_timings = ExecuteTimings::default();
 
+		let check_result = check_result.and_then(|checked_details| {
+			if let Some(tx_account_lock_limit) = config.transaction_account_lock_limit {
+				SanitizedTransaction::validate_account_locks(
+					sanitized_tx.message(),
+					tx_account_lock_limit,
+				)?;
+			}
+			Ok(checked_details)
+		});
+
 		let validation_result = self.validate_fee(
 			callbacks,
 			config.account_overrides,
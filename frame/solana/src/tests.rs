@@ -16,27 +16,39 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use crate::{mock::*, runtime::bank::Bank, *};
+use crate::{
+	mock::*,
+	runtime::bank::{Bank, TransactionSimulationResult},
+	*,
+};
 
 use frame_support::{
 	sp_runtime::traits::Convert,
 	traits::{
 		fungible::{Inspect, Mutate},
-		Get, OnFinalize,
+		Get, OnFinalize, OnInitialize,
 	},
 	BoundedVec,
 };
 use frame_system::pallet_prelude::BlockNumberFor;
 use solana_sdk::{
+	account::from_account,
 	bpf_loader, bpf_loader_upgradeable,
+	compute_budget::ComputeBudgetInstruction,
 	hash::Hash,
-	instruction::{self, Instruction},
+	instruction::{self, AccountMeta, Instruction, InstructionError},
 	message::SimpleAddressLoader,
 	program_pack::Pack,
+	rent::Rent,
 	reserved_account_keys::ReservedAccountKeys,
 	signature::{Keypair, Signer},
-	system_instruction, system_program, system_transaction,
-	transaction::{MessageHash, Result, SanitizedTransaction, Transaction, VersionedTransaction},
+	slot_hashes::SlotHashes,
+	slot_history::{self, SlotHistory},
+	system_instruction, system_program, system_transaction, sysvar,
+	transaction::{
+		MessageHash, Result, SanitizedTransaction, Transaction, TransactionError,
+		VersionedTransaction, MAX_TX_ACCOUNT_LOCKS,
+	},
 };
 
 fn before_each() {
@@ -85,6 +97,18 @@ fn process_transaction(bank: &Bank<Test>, tx: Transaction) -> Result<()> {
 	bank.load_execute_and_commit_sanitized_transaction(&sanitized_tx)
 }
 
+fn simulate_transaction(tx: Transaction) -> TransactionSimulationResult {
+	let sanitized_tx = SanitizedTransaction::try_create(
+		VersionedTransaction::from(tx),
+		MessageHash::Compute,
+		None,
+		SimpleAddressLoader::Disabled,
+		&ReservedAccountKeys::empty_key_set(),
+	)
+	.unwrap();
+	<Pallet<Test>>::simulate_transaction(sanitized_tx, false)
+}
+
 fn mock_deploy_program(program_id: &Pubkey, data: Vec<u8>) {
 	<Pallet<Test>>::deploy_program(*program_id, data, None).unwrap();
 
@@ -397,3 +421,394 @@ fn trace_executed_transaction_events() {
 		assert!(token_account.mint == mint.pubkey());
 	});
 }
+
+#[test]
+fn rent_paying_account_creation_should_be_rejected() {
+	new_test_ext().execute_with(|| {
+		before_each();
+		let bank = mock_bank();
+
+		let from = Keypair::alice();
+		let to = Keypair::get("Account");
+
+		let minimum_balance = Rent::default().minimum_balance(0);
+		let tx = system_transaction::create_account(
+			&from,
+			&to,
+			Hash::default(),
+			minimum_balance - 1,
+			0,
+			&system_program::id(),
+		);
+
+		assert!(process_transaction(&bank, tx).is_ok());
+		assert!(<AccountMeta<Test>>::get(to.account_id()).is_none());
+		assert_eq!(Balances::total_balance(&to.account_id()), 0);
+	});
+}
+
+#[test]
+fn rent_should_be_collected_from_rent_paying_account() {
+	new_test_ext().execute_with(|| {
+		before_each();
+
+		let from = Keypair::alice();
+		let account = Keypair::get("Account");
+		<AccountMeta<Test>>::insert(
+			account.account_id(),
+			AccountMetadata { rent_epoch: 0, owner: system_program::id(), executable: false },
+		);
+		Balances::mint_into(&account.account_id(), sol_into_balances(1) / 10_000).unwrap();
+		let lamports = <Pallet<Test>>::get_balance(account.pubkey());
+		assert!(!Rent::default().is_exempt(lamports, 0));
+
+		let bank = mock_bank();
+		let transfer = system_instruction::transfer(&account.pubkey(), &from.pubkey(), 0);
+		let mut tx = Transaction::new_with_payer(&[transfer], Some(&from.pubkey()));
+		tx.sign(&[&from, &account], Hash::default());
+		assert!(process_transaction(&bank, tx).is_ok());
+
+		let meta = <AccountMeta<Test>>::get(account.account_id()).unwrap();
+		assert_eq!(meta.rent_epoch, bank.epoch() + 1);
+		assert!(<Pallet<Test>>::get_balance(account.pubkey()) < lamports);
+	});
+}
+
+#[test]
+fn zero_lamport_account_should_be_purged() {
+	new_test_ext().execute_with(|| {
+		before_each();
+		let bank = mock_bank();
+
+		let from = Keypair::alice();
+		let account = Keypair::get("Account");
+
+		let tx = system_transaction::create_account(
+			&from,
+			&account,
+			Hash::default(),
+			sol_into_lamports(1),
+			0,
+			&system_program::id(),
+		);
+		assert!(process_transaction(&bank, tx).is_ok());
+		assert!(<AccountMeta<Test>>::get(account.account_id()).is_some());
+		assert_eq!(System::sufficients(&account.account_id()), 1);
+
+		let lamports = <Pallet<Test>>::get_balance(account.pubkey());
+		let transfer = system_instruction::transfer(&account.pubkey(), &from.pubkey(), lamports);
+		let mut tx = Transaction::new_with_payer(&[transfer], Some(&from.pubkey()));
+		tx.sign(&[&from, &account], Hash::default());
+		assert!(process_transaction(&bank, tx).is_ok());
+
+		assert!(<AccountMeta<Test>>::get(account.account_id()).is_none());
+		assert!(<AccountData<Test>>::get(account.account_id()).is_empty());
+		assert!(!System::account_exists(&account.account_id()));
+	});
+}
+
+#[test]
+fn native_only_account_should_be_visible_as_system_account() {
+	new_test_ext().execute_with(|| {
+		before_each();
+		let bank = mock_bank();
+
+		let from = Keypair::alice();
+		let to = Keypair::get("Account");
+		Balances::mint_into(&to.account_id(), sol_into_balances(1)).unwrap();
+
+		let account = <Pallet<Test>>::get_account_info(to.pubkey()).unwrap();
+		assert_eq!(account.owner, system_program::id());
+		assert_eq!(account.lamports, sol_into_lamports(1));
+
+		let tx = system_transaction::transfer(&from, &to.pubkey(), 1, Hash::default());
+		assert!(process_transaction(&bank, tx).is_ok());
+		assert_eq!(
+			Balances::total_balance(&to.account_id()),
+			sol_into_balances(1) + <Lamports<Test>>::from(1).into_inner()
+		);
+	});
+}
+
+#[test]
+fn sub_lamport_remainder_should_be_preserved() {
+	new_test_ext().execute_with(|| {
+		before_each();
+		let bank = mock_bank();
+
+		let from = Keypair::get("Account");
+		let to = Keypair::bob();
+		Balances::mint_into(&from.account_id(), sol_into_balances(1) + 1_234).unwrap();
+		assert_eq!(<Pallet<Test>>::get_balance(from.pubkey()), sol_into_lamports(1));
+
+		let tx = system_transaction::transfer(&from, &to.pubkey(), 1_000, Hash::default());
+		assert!(process_transaction(&bank, tx).is_ok());
+		assert_eq!(<Pallet<Test>>::get_balance(from.pubkey()), sol_into_lamports(1) - 1_000);
+		assert_eq!(<Pallet<Test>>::lamports(&from.account_id()).remainder(), 1_234);
+	});
+}
+
+#[test]
+fn transfer_leaving_less_than_existential_deposit_should_fail() {
+	ExistentialDeposit::set(sol_into_balances(1));
+	new_test_ext().execute_with(|| {
+		before_each();
+		let bank = mock_bank();
+
+		let from = Keypair::get("Account");
+		let to = Keypair::bob();
+		Balances::mint_into(&from.account_id(), sol_into_balances(2) + 1_234).unwrap();
+
+		let tx = system_transaction::transfer(
+			&from,
+			&to.pubkey(),
+			sol_into_lamports(3) / 2,
+			Hash::default(),
+		);
+		assert_eq!(
+			process_transaction(&bank, tx),
+			Err(TransactionError::InsufficientFundsForRent { account_index: 0 })
+		);
+		assert_eq!(Balances::total_balance(&from.account_id()), sol_into_balances(2) + 1_234);
+		assert_eq!(Balances::total_balance(&to.account_id()), sol_into_balances(10));
+
+		// Emptying the account reaps it along with its sub-lamport dust.
+		let tx = system_transaction::transfer(
+			&from,
+			&to.pubkey(),
+			sol_into_lamports(2),
+			Hash::default(),
+		);
+		assert!(process_transaction(&bank, tx).is_ok());
+		assert_eq!(Balances::total_balance(&from.account_id()), 0);
+		assert!(!System::account_exists(&from.account_id()));
+		assert!(<AccountMeta<Test>>::get(from.account_id()).is_none());
+		assert_eq!(Balances::total_balance(&to.account_id()), sol_into_balances(12));
+	});
+}
+
+#[test]
+fn random_transfers_should_keep_lamports_consistent() {
+	use rand::{rngs::StdRng, Rng, SeedableRng};
+
+	let existential_deposit = sol_into_balances(1) / 100;
+	let multiplier = <<Test as Config>::DecimalMultiplier as Get<u128>>::get();
+	ExistentialDeposit::set(existential_deposit);
+	new_test_ext().execute_with(|| {
+		before_each();
+		let bank = mock_bank();
+		let mut rng = StdRng::seed_from_u64(0x5eed);
+
+		let payer = Keypair::alice();
+		let users = (0..4).map(|i| Keypair::get(&format!("User{}", i))).collect::<Vec<_>>();
+		for user in users.iter() {
+			let balance = rng.gen_range(existential_deposit..sol_into_balances(2));
+			Balances::mint_into(&user.account_id(), balance).unwrap();
+		}
+		let remainders = users
+			.iter()
+			.map(|user| Balances::total_balance(&user.account_id()) % multiplier)
+			.collect::<Vec<_>>();
+
+		for _ in 0..200 {
+			let from = &users[rng.gen_range(0..users.len())];
+			let to = &users[rng.gen_range(0..users.len())];
+			let lamports = <Pallet<Test>>::get_balance(from.pubkey());
+			let ed_lamports = (existential_deposit / multiplier) as u64;
+			let amount = match rng.gen_range(0..4) {
+				0 => lamports,
+				1 => lamports.saturating_sub(ed_lamports / 2),
+				_ => rng.gen_range(0..=lamports),
+			};
+
+			let balances_before = users
+				.iter()
+				.map(|user| Balances::total_balance(&user.account_id()))
+				.collect::<Vec<_>>();
+
+			let transfer = system_instruction::transfer(&from.pubkey(), &to.pubkey(), amount);
+			let mut tx = Transaction::new_with_payer(&[transfer], Some(&payer.pubkey()));
+			tx.sign(&[&payer, from], Hash::default());
+			let _ = process_transaction(&bank, tx);
+
+			let mut reaped_dust = 0;
+			for (i, user) in users.iter().enumerate() {
+				let who = user.account_id();
+				let balance = Balances::total_balance(&who);
+				assert!(balance == 0 || balance >= existential_deposit);
+				assert_eq!(
+					<Pallet<Test>>::get_balance(user.pubkey()) as u128,
+					balance / multiplier
+				);
+				assert_eq!(
+					<Pallet<Test>>::get_account_info(user.pubkey())
+						.map(|account| account.lamports)
+						.unwrap_or_default(),
+					<Pallet<Test>>::get_balance(user.pubkey())
+				);
+				if balance == 0 {
+					if balances_before[i] != 0 {
+						reaped_dust += balances_before[i] % multiplier;
+					}
+				} else if balances_before[i] != 0 {
+					assert_eq!(balance % multiplier, remainders[i]);
+				}
+			}
+			assert_eq!(
+				balances_before.iter().sum::<u128>(),
+				users
+					.iter()
+					.map(|user| Balances::total_balance(&user.account_id()))
+					.sum::<u128>() +
+					reaped_dust
+			);
+		}
+	});
+}
+
+#[test]
+fn block_sysvars_should_follow_block_production() {
+	new_test_ext().execute_with(|| {
+		for (number, byte) in [(5, 1u8), (6, 2u8)] {
+			System::set_block_number(number);
+			System::set_parent_hash(HashConversion::convert(Hash::new_from_array([byte; 32])));
+			Solana::on_initialize(number);
+			assert_eq!(<Slot<Test>>::get(), number);
+		}
+
+		let account = <Pallet<Test>>::get_account_info(sysvar::slot_hashes::id()).unwrap();
+		let slot_hashes = from_account::<SlotHashes, _>(&account).unwrap();
+		assert_eq!(slot_hashes.get(&4), Some(&Hash::new_from_array([1; 32])));
+		assert_eq!(slot_hashes.get(&5), Some(&Hash::new_from_array([2; 32])));
+
+		let account = <Pallet<Test>>::get_account_info(sysvar::slot_history::id()).unwrap();
+		let slot_history = from_account::<SlotHistory, _>(&account).unwrap();
+		assert_eq!(slot_history.check(5), slot_history::Check::Found);
+		assert_eq!(slot_history.check(6), slot_history::Check::Found);
+		assert_eq!(slot_history.check(7), slot_history::Check::Future);
+		assert_eq!(slot_history.check(4), slot_history::Check::NotFound);
+
+		#[allow(deprecated)]
+		{
+			let account =
+				<Pallet<Test>>::get_account_info(sysvar::recent_blockhashes::id()).unwrap();
+			let recent_blockhashes =
+				from_account::<sysvar::recent_blockhashes::RecentBlockhashes, _>(&account).unwrap();
+			assert_eq!(recent_blockhashes[0].blockhash, Hash::new_from_array([2; 32]));
+			assert_eq!(recent_blockhashes[1].blockhash, Hash::new_from_array([1; 32]));
+		}
+
+		for id in [
+			sysvar::stake_history::id(),
+			sysvar::epoch_rewards::id(),
+			sysvar::last_restart_slot::id(),
+		] {
+			assert!(<Pallet<Test>>::get_account_info(id).is_some());
+		}
+	});
+}
+
+#[test]
+fn clock_sysvar_should_follow_block_timestamp() {
+	new_test_ext().execute_with(|| {
+		before_each();
+
+		let program_id = Keypair::get("Program").pubkey();
+		let program_data =
+			std::fs::read("tests/example-programs/clock-sysvar/clock_sysvar_program.so")
+				.expect("program data");
+		mock_deploy_program(&program_id, program_data);
+
+		let now = <<Test as Config>::GenesisTimestamp as Get<u64>>::get() + 6_000_000;
+		Timestamp::set_timestamp(now);
+
+		let payer = Keypair::alice();
+		let instruction = Instruction::new_with_bytes(program_id, &[], vec![]);
+		let mut tx = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+		tx.sign(&[&payer], Hash::default());
+		let sanitized_tx = SanitizedTransaction::try_create(
+			VersionedTransaction::from(tx),
+			MessageHash::Compute,
+			None,
+			SimpleAddressLoader::Disabled,
+			&ReservedAccountKeys::empty_key_set(),
+		)
+		.unwrap();
+
+		let result = <Pallet<Test>>::simulate_transaction(sanitized_tx, false);
+		assert!(result.result.is_ok());
+		assert_eq!(result.return_data.unwrap().data, ((now / 1000) as i64).to_be_bytes());
+	});
+}
+
+#[test]
+fn simulation_should_report_consumed_compute_units() {
+	new_test_ext().execute_with(|| {
+		before_each();
+
+		let program_id = Keypair::get("Program").pubkey();
+		let program_data =
+			std::fs::read("tests/example-programs/hello-solana/hello_solana_program.so")
+				.expect("program data");
+		mock_deploy_program(&program_id, program_data);
+
+		let payer = Keypair::alice();
+		let instruction = Instruction::new_with_bytes(program_id, &[], vec![]);
+		let mut tx = Transaction::new_with_payer(&[instruction.clone()], Some(&payer.pubkey()));
+		tx.sign(&[&payer], Hash::default());
+		let result = simulate_transaction(tx);
+		assert!(result.result.is_ok());
+		assert!(result.units_consumed > 0);
+
+		// The compute unit limit requested by the transaction is enforced.
+		let units_consumed = result.units_consumed;
+		let mut tx = Transaction::new_with_payer(
+			&[
+				ComputeBudgetInstruction::set_compute_unit_limit(units_consumed as u32),
+				instruction.clone(),
+			],
+			Some(&payer.pubkey()),
+		);
+		tx.sign(&[&payer], Hash::default());
+		let result = simulate_transaction(tx);
+		assert!(result.result.is_ok());
+		assert_eq!(result.units_consumed, units_consumed);
+
+		let mut tx = Transaction::new_with_payer(
+			&[
+				ComputeBudgetInstruction::set_compute_unit_limit(units_consumed as u32 - 1),
+				instruction,
+			],
+			Some(&payer.pubkey()),
+		);
+		tx.sign(&[&payer], Hash::default());
+		let result = simulate_transaction(tx);
+		assert_eq!(
+			result.result,
+			Err(TransactionError::InstructionError(
+				1,
+				InstructionError::ComputationalBudgetExceeded
+			))
+		);
+		assert_eq!(result.units_consumed, units_consumed - 1);
+	});
+}
+
+#[test]
+fn transaction_exceeding_account_lock_limit_should_fail() {
+	new_test_ext().execute_with(|| {
+		before_each();
+
+		let payer = Keypair::alice();
+		let accounts = (0..MAX_TX_ACCOUNT_LOCKS)
+			.map(|_| AccountMeta::new_readonly(Pubkey::new_unique(), false))
+			.collect();
+		let instruction = Instruction::new_with_bytes(system_program::id(), &[], accounts);
+		let mut tx = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+		tx.sign(&[&payer], Hash::default());
+
+		let result = simulate_transaction(tx);
+		assert_eq!(result.result, Err(TransactionError::TooManyAccountLocks));
+		assert_eq!(result.units_consumed, 0);
+	});
+}
@@ -17,14 +17,19 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use crate::{BalanceOf, Config};
-use frame_support::sp_runtime::traits::{CheckedAdd, CheckedMul, CheckedSub, Get, Saturating};
+use frame_support::sp_runtime::traits::{
+	CheckedAdd, CheckedMul, CheckedSub, Convert, Get, Saturating, UniqueSaturatedInto,
+};
 use frame_system::pallet_prelude::BlockNumberFor;
 use nostd::cmp::Ordering;
 use np_runtime::traits::LossyInto;
 use parity_scale_codec::{Decode, Encode, MaxEncodedLen};
 use scale_info::TypeInfo;
 use solana_sdk::{
-	clock::Epoch, fee_calculator::FeeCalculator, instruction::InstructionError, pubkey::Pubkey,
+	clock::{Epoch, Slot},
+	fee_calculator::FeeCalculator,
+	instruction::InstructionError,
+	pubkey::Pubkey,
 };
 
 #[derive(Decode, Encode, MaxEncodedLen, TypeInfo)]
@@ -41,6 +46,15 @@ impl<T: Config> HashInfo<T> {
 	}
 }
 
+/// Assigns one slot to every block, so that the slot is the block number.
+pub struct BlockNumberAsSlot;
+
+impl<N: UniqueSaturatedInto<Slot>> Convert<N, Slot> for BlockNumberAsSlot {
+	fn convert(number: N) -> Slot {
+		number.unique_saturated_into()
+	}
+}
+
 #[derive(Clone, Debug, Default, Eq, PartialEq, Decode, Encode, MaxEncodedLen, TypeInfo)]
 pub struct AccountMetadata {
 	/// the epoch at which this account will next owe rent
@@ -57,7 +71,7 @@ pub struct Lamports<T: Config>(BalanceOf<T>);
 
 impl<T: Config> core::fmt::Display for Lamports<T> {
 	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
-		write!(f, "{}.{}", self.get(), (self.0 % T::DecimalMultiplier::get()).lossy_into())
+		write!(f, "{}.{}", self.get(), self.remainder().lossy_into())
 	}
 }
 
@@ -147,6 +161,11 @@ impl<T: Config> Lamports<T> {
 		(self.0 / T::DecimalMultiplier::get()).lossy_into()
 	}
 
+	/// Sub-lamport part of the balance, which is not visible to the SVM.
+	pub fn remainder(&self) -> BalanceOf<T> {
+		self.0 % T::DecimalMultiplier::get()
+	}
+
 	pub fn inner(&self) -> BalanceOf<T> {
 		self.0
 	}
@@ -210,6 +229,7 @@ mod tests {
 
 		let lamports = lamports.checked_add(1).unwrap();
 		assert_eq!(lamports.get(), 2);
+		assert_eq!(lamports.remainder(), 1_234);
 		assert_eq!(lamports.into_inner(), 2_000_001_234);
 	}
 }
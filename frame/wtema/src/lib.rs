@@ -27,19 +27,32 @@
 //!   t = prior block solvetime
 //!   T = desired average block time
 //! ```
+//!
+//! A timestamp must be greater than the median of the last `MedianTimeSpan` timestamps.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
+extern crate alloc;
+
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod tests;
+
 pub use pallet::*;
 
 use core::{fmt::Debug, marker::PhantomData};
 use frame_support::{
 	sp_runtime,
-	traits::{Get, OnTimestampSet},
+	traits::{Get, IsSubType, OnTimestampSet},
+	BoundedVec,
 };
+use np_consensus_pow::timestamp;
 use parity_scale_codec::FullCodec;
 use sp_core::U256;
-use sp_runtime::traits::{One, SaturatedConversion, UniqueSaturatedFrom, UniqueSaturatedInto};
+use sp_runtime::traits::{
+	One, SaturatedConversion, Saturating, UniqueSaturatedFrom, UniqueSaturatedInto,
+};
 
 /// Helper type to calculate the minimum difficulty.
 pub struct MinDifficulty<T>(PhantomData<T>);
@@ -62,6 +75,7 @@ where
 pub mod pallet {
 	use super::*;
 	use frame_support::pallet_prelude::*;
+	use frame_system::pallet_prelude::BlockNumberFor;
 
 	#[pallet::pallet]
 	pub struct Pallet<T>(_);
@@ -94,6 +108,10 @@ pub mod pallet {
 		/// rounding errors.
 		#[pallet::constant]
 		type MinDifficulty: Get<Self::Difficulty>;
+
+		/// Number of recent timestamps the median time past is taken over.
+		#[pallet::constant]
+		type MedianTimeSpan: Get<u32>;
 	}
 
 	/// Target difficulty for the next block.
@@ -106,6 +124,11 @@ pub mod pallet {
 	#[pallet::getter(fn last_timestamp)]
 	pub type LastTimestamp<T: Config> = StorageValue<_, T::Moment, ValueQuery>;
 
+	/// Timestamps of the most recent blocks, oldest first.
+	#[pallet::storage]
+	pub type Timestamps<T: Config> =
+		StorageValue<_, BoundedVec<T::Moment, T::MedianTimeSpan>, ValueQuery>;
+
 	#[pallet::genesis_config]
 	pub struct GenesisConfig<T: Config> {
 		pub initial_difficulty: T::Difficulty,
@@ -124,6 +147,33 @@ pub mod pallet {
 			Difficulty::<T>::put(self.initial_difficulty);
 		}
 	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn integrity_test() {
+			assert!(T::MedianTimeSpan::get() > 0);
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Median of the timestamps of the most recent blocks, if any.
+	pub fn median_time_past() -> Option<T::Moment> {
+		timestamp::median_time_past(Timestamps::<T>::get().into_iter(), T::MedianTimeSpan::get())
+	}
+
+	/// Timestamp set by the `pallet_timestamp` call among `calls`.
+	pub fn block_timestamp<'a>(
+		calls: impl IntoIterator<Item = &'a <T as frame_system::Config>::RuntimeCall>,
+	) -> Option<T::Moment>
+	where
+		<T as frame_system::Config>::RuntimeCall: IsSubType<pallet_timestamp::Call<T>>,
+	{
+		calls.into_iter().find_map(|call| match call.is_sub_type() {
+			Some(pallet_timestamp::Call::set { now }) => Some(*now),
+			_ => None,
+		})
+	}
 }
 
 impl<T: Config> OnTimestampSet<T::Moment> for Pallet<T>
@@ -131,9 +181,14 @@ where
 	T::Moment: Into<U256>,
 {
 	fn on_timestamp_set(now: T::Moment) {
+		if let Some(median_time_past) = Self::median_time_past() {
+			assert!(now > median_time_past, "Timestamp must be greater than the median time past");
+		}
+
 		let block_time = match frame_system::Pallet::<T>::block_number() {
 			n if n <= One::one() => T::TargetBlockTime::get(),
-			_ => now - LastTimestamp::<T>::get(),
+			// A timestamp after the median time past may still be before the last one.
+			_ => now.saturating_sub(LastTimestamp::<T>::get()),
 		};
 		let desired_block_time = T::TargetBlockTime::get().into();
 		let prior_target = U256::max_value() / Difficulty::<T>::get().into();
@@ -149,5 +204,9 @@ where
 
 		Difficulty::<T>::put(difficulty);
 		LastTimestamp::<T>::put(now);
+
+		let mut timestamps = Timestamps::<T>::get().into_inner();
+		timestamp::push_bounded(&mut timestamps, now, T::MedianTimeSpan::get());
+		Timestamps::<T>::put(BoundedVec::truncate_from(timestamps));
 	}
 }
@@ -0,0 +1,77 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+#![cfg(test)]
+
+use crate as pallet_wtema;
+
+use super::*;
+use frame_support::{
+	derive_impl, parameter_types,
+	sp_runtime::BuildStorage,
+	traits::{ConstU32, ConstU64},
+};
+use sp_io::TestExternalities;
+
+#[frame_support::runtime]
+mod runtime {
+	#[runtime::runtime]
+	#[runtime::derive(RuntimeCall, RuntimeEvent, RuntimeError, RuntimeOrigin, RuntimeTask)]
+	pub struct Test;
+
+	#[runtime::pallet_index(0)]
+	pub type System = frame_system;
+
+	#[runtime::pallet_index(1)]
+	pub type Timestamp = pallet_timestamp;
+
+	#[runtime::pallet_index(2)]
+	pub type Wtema = pallet_wtema;
+}
+
+type Block = frame_system::mocking::MockBlock<Test>;
+
+#[derive_impl(frame_system::config_preludes::TestDefaultConfig)]
+impl frame_system::Config for Test {
+	type Block = Block;
+}
+
+#[derive_impl(pallet_timestamp::config_preludes::TestDefaultConfig)]
+impl pallet_timestamp::Config for Test {
+	type OnTimestampSet = Wtema;
+}
+
+parameter_types! {
+	pub MinDifficulty: U256 = U256::from(1000);
+}
+
+impl Config for Test {
+	type Difficulty = U256;
+	type TargetBlockTime = ConstU64<10_000>;
+	type Filter = ConstU32<16>;
+	type MinDifficulty = MinDifficulty;
+	type MedianTimeSpan = ConstU32<3>;
+}
+
+pub fn new_test_ext() -> TestExternalities {
+	let mut t = frame_system::GenesisConfig::<Test>::default().build_storage().unwrap();
+	pallet_wtema::GenesisConfig::<Test> { initial_difficulty: U256::from(10_000_000) }
+		.assimilate_storage(&mut t)
+		.unwrap();
+	TestExternalities::new(t)
+}
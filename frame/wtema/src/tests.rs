@@ -0,0 +1,83 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+#![cfg(test)]
+
+use super::{
+	mock::{Wtema as Pallet, *},
+	Timestamps,
+};
+use frame_support::traits::OnTimestampSet;
+use sp_core::U256;
+
+const TARGET_BLOCK_TIME: u64 = 10_000;
+
+fn set_timestamp(number: u64, now: u64) {
+	System::set_block_number(number);
+	Pallet::on_timestamp_set(now);
+}
+
+#[test]
+fn timestamps_should_be_bounded() {
+	new_test_ext().execute_with(|| {
+		for number in 1..=5 {
+			set_timestamp(number, number * TARGET_BLOCK_TIME);
+		}
+
+		assert_eq!(Timestamps::<Test>::get().into_inner(), vec![30_000, 40_000, 50_000]);
+		assert_eq!(Pallet::last_timestamp(), 50_000);
+		// Blocks on target keep the difficulty.
+		assert_eq!(Pallet::difficulty(), U256::from(10_000_000));
+	});
+}
+
+#[test]
+fn median_time_past_should_work() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(Pallet::median_time_past(), None);
+
+		set_timestamp(1, 10_000);
+		set_timestamp(2, 20_000);
+		set_timestamp(3, 40_000);
+		assert_eq!(Pallet::median_time_past(), Some(20_000));
+
+		set_timestamp(4, 30_000);
+		assert_eq!(Pallet::median_time_past(), Some(30_000));
+	});
+}
+
+#[test]
+#[should_panic(expected = "Timestamp must be greater than the median time past")]
+fn timestamp_at_median_time_past_should_fail() {
+	new_test_ext().execute_with(|| {
+		set_timestamp(1, 10_000);
+		set_timestamp(2, 20_000);
+		set_timestamp(3, 40_000);
+		set_timestamp(4, 20_000);
+	});
+}
+
+#[test]
+fn block_timestamp_should_work() {
+	let calls = [
+		RuntimeCall::System(frame_system::Call::remark { remark: vec![] }),
+		RuntimeCall::Timestamp(pallet_timestamp::Call::set { now: 42 }),
+	];
+	assert_eq!(Pallet::block_timestamp(&calls), Some(42));
+	assert_eq!(Pallet::block_timestamp(&calls[..1]), None);
+}
@@ -0,0 +1,53 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Representations of Babel assets in each VM.
+
+use alloc::vec::Vec;
+use parity_scale_codec::{Decode, Encode};
+use scale_info::TypeInfo;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sp_core::{H160, H256};
+
+/// A representation of an asset to look up.
+#[derive(Debug, Clone, Eq, PartialEq, Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AssetQuery<AssetId> {
+	/// Asset id of `pallet_assets`.
+	Id(AssetId),
+	/// Address of the ERC-20 precompile.
+	Erc20(H160),
+	/// Cosmos denom.
+	Denom(Vec<u8>),
+	/// Address of the SPL mint.
+	SplMint(H256),
+}
+
+/// The canonical representations of an asset in each VM.
+#[derive(Debug, Clone, Eq, PartialEq, Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AssetRepresentations<AssetId> {
+	/// Asset id of `pallet_assets`.
+	pub id: AssetId,
+	/// Address of the ERC-20 precompile.
+	pub erc20: H160,
+	/// Cosmos denom, if registered.
+	pub denom: Option<Vec<u8>>,
+	/// Address of the SPL mint.
+	pub spl_mint: H256,
+}
@@ -19,6 +19,8 @@
 
 extern crate alloc;
 
+pub mod asset;
+
 use parity_scale_codec::{Decode, Encode, MaxEncodedLen};
 use scale_info::TypeInfo;
 #[cfg(feature = "serde")]
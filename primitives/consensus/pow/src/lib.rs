@@ -21,20 +21,41 @@
 
 extern crate alloc;
 
+pub mod timestamp;
+
 use alloc::vec::Vec;
 use core::ops::AddAssign;
 use parity_scale_codec::{Decode, Encode};
 use sp_api::decl_runtime_apis;
 use sp_arithmetic::traits::{Bounded, SaturatedConversion, Saturating, UniqueSaturatedFrom};
-use sp_core::U256;
-use sp_runtime::ConsensusEngineId;
+use sp_core::{H256, U256};
+use sp_inherents::InherentIdentifier;
+use sp_runtime::{traits::Block as BlockT, ConsensusEngineId};
 
 /// `ConsensusEngineId` for PoW.
 pub const POW_ENGINE_ID: ConsensusEngineId = *b"pow_";
 
+/// Inherent identifier of the uncles a block may include.
+pub const UNCLES_INHERENT_IDENTIFIER: InherentIdentifier = *b"powuncle";
+
+/// Candidate uncle headers, sealed, provided to the inherent of the block.
+pub type UnclesInherentType<Header> = Vec<Header>;
+
 /// Seal for PoW.
 pub type Seal = Vec<u8>;
 
+/// Seal of the reference PoW algorithms, SCALE encoded into a [`Seal`].
+///
+/// `work` is the hash of the pre-hash of the block followed by the little-endian bytes of
+/// `nonce`, and it must pass [`check_hash`] against the target difficulty.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Decode, Encode)]
+pub struct WorkSeal {
+	/// Nonce found by the miner.
+	pub nonce: u64,
+	/// Work hash of the nonce.
+	pub work: H256,
+}
+
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Decode, Encode)]
 pub struct BlockWeight<Weight>(Weight);
 
@@ -89,4 +110,20 @@ decl_runtime_apis! {
 		/// Return the target difficulty of the next block.
 		fn difficulty() -> Difficulty;
 	}
+
+	/// API to validate the timestamps of blocks, which have no slot to be checked against.
+	pub trait TimestampValidationApi {
+		/// Return the median time past in milliseconds, the timestamp of the next block must
+		/// exceed.
+		fn median_time_past() -> u64;
+
+		/// Return the timestamp in milliseconds set by `block`.
+		fn block_timestamp(block: Block) -> Option<u64>;
+	}
+
+	/// API to extract the uncles included by a block, for their seals to be verified on import.
+	pub trait UnclesApi {
+		/// Return the uncle headers included by `block`.
+		fn uncles(block: Block) -> Vec<<Block as BlockT>::Header>;
+	}
 }
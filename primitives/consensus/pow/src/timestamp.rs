@@ -0,0 +1,41 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Timestamps of PoW blocks, which have no slot to be checked against.
+//!
+//! Runtimes keep a bounded window of recent timestamps with [`push_bounded`], and take the median
+//! time past over it with [`median_time_past`] for the node to check the timestamps of imported
+//! blocks against it through `TimestampValidationApi`.
+
+use alloc::vec::Vec;
+
+/// Median of the last `span` timestamps, ordered oldest first, if any.
+pub fn median_time_past<M: Ord + Copy>(
+	timestamps: impl DoubleEndedIterator<Item = M>,
+	span: u32,
+) -> Option<M> {
+	let mut recent: Vec<_> = timestamps.rev().take(span as usize).collect();
+	recent.sort();
+	recent.get(recent.len() / 2).copied()
+}
+
+/// Appends `item` to `window`, dropping the oldest items beyond `max_len`.
+pub fn push_bounded<X>(window: &mut Vec<X>, item: X, max_len: u32) {
+	window.push(item);
+	let excess = window.len().saturating_sub(max_len as usize);
+	window.drain(..excess);
+}
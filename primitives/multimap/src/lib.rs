@@ -133,7 +133,11 @@ where
 		Map::try_mutate(key.clone(), |v| {
 			ensure!(Index::get(&value).filter(|k| *k != key).is_none(), Error::DuplicateValue);
 
-			*v = Some(value.clone());
+			if let Some(old) = v.replace(value.clone()) {
+				if old != value {
+					Index::remove(old);
+				}
+			}
 			Index::insert(value, key);
 
 			Ok(true)
@@ -249,4 +253,27 @@ mod tests {
 			assert!(matches!(Map::try_insert(1, 1000), Ok(true)));
 		});
 	}
+
+	#[test]
+	fn unique_map_adapter_replaces_value() {
+		new_test_ext().execute_with(|| {
+			// reinserting the same value keeps it indexed
+			assert!(matches!(Map::try_insert(0, 1000), Ok(true)));
+			assert!(matches!(Map::try_insert(0, 1000), Ok(true)));
+			assert_eq!(Map::find_key(1000), Some(0));
+
+			assert!(matches!(Map::try_insert(0, 1001), Ok(true)));
+			assert_eq!(Map::get(0), Some(1001));
+			assert_eq!(Map::find_key(1001), Some(0));
+			// the replaced value is no longer indexed
+			assert_eq!(Map::find_key(1000), None);
+			assert!(matches!(Map::try_insert(1, 1000), Ok(true)));
+			assert_eq!(Map::find_key(1000), Some(1));
+
+			// duplicate value leaves the map unchanged
+			assert!(matches!(Map::try_insert(0, 1000), Err(Error::DuplicateValue)));
+			assert_eq!(Map::get(0), Some(1001));
+			assert_eq!(Map::find_key(1001), Some(0));
+		});
+	}
 }
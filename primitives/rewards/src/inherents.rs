@@ -33,6 +33,9 @@ pub enum InherentError {
 	/// Duplicate reward beneficiary.
 	#[cfg_attr(feature = "std", error("Duplicate reward beneficiary"))]
 	DuplicateBeneficiary,
+	/// Too many uncles.
+	#[cfg_attr(feature = "std", error("Too many uncles"))]
+	TooManyUncles,
 }
 
 impl IsFatalError for InherentError {
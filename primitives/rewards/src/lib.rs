@@ -22,10 +22,23 @@
 extern crate alloc;
 
 pub mod inherents;
+#[cfg(feature = "std")]
+pub mod pool;
 pub use inherents::*;
 
 use alloc::{collections::BTreeMap, vec::Vec};
 use np_arithmetic::traits::{BaseArithmetic, SaturatingMulDiv};
+use parity_scale_codec::{Decode, Encode};
+use sp_runtime::RuntimeDebug;
+
+/// Rewards of an account that are not yet spendable.
+#[derive(Clone, Default, PartialEq, Eq, Encode, Decode, RuntimeDebug)]
+pub struct RewardSchedule<BlockNumber, Balance> {
+	/// Total rewards frozen, including the matured ones not yet claimed.
+	pub locked: Balance,
+	/// Maturing rewards, by the block number they mature at.
+	pub maturing: Vec<(BlockNumber, Balance)>,
+}
 
 /// Distributes the total reward according to the weight of each account.
 pub fn split_reward<Balance, AccountId, Share>(
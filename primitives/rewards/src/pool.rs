@@ -0,0 +1,319 @@
+// This file is part of Noir.
+
+// Copyright (C) Haderech Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reward shares of mining pools.
+//!
+//! Pools report their accepted shares in a text file or through a local socket, one share per
+//! line, oldest first:
+//!
+//! ```text
+//! # comment
+//! <hex of the SCALE encoded account> <weight>
+//! block
+//! ```
+//!
+//! where `block` marks a block found by the pool, closing its round.
+
+use crate::{InherentType, INHERENT_IDENTIFIER};
+use parity_scale_codec::{Decode, Encode};
+use parking_lot::Mutex;
+use sp_inherents::{InherentData, InherentIdentifier};
+use sp_runtime::traits::{BlakeTwo256, Hash, One, Saturating, Zero};
+#[cfg(unix)]
+use std::{io::Read, os::unix::net::UnixStream, time::Duration};
+use std::{path::PathBuf, str::FromStr};
+
+const LOG_TARGET: &str = "rewards";
+
+/// Timeout of reading shares from a socket.
+#[cfg(unix)]
+const SOCKET_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Where the shares are read from.
+#[derive(Clone, Debug)]
+pub enum ShareSource {
+	/// Text file, read for every block.
+	File(PathBuf),
+	/// Unix socket, connected for every block and read until closed.
+	#[cfg(unix)]
+	Socket(PathBuf),
+}
+
+/// How the reward is split among the shares.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Accounting {
+	/// Proportionally to the shares of the current round.
+	Proportional,
+	/// Proportionally to the last N shares, regardless of the rounds.
+	Pplns(usize),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error(transparent)]
+	Io(#[from] std::io::Error),
+	#[error("Invalid share at line {0}")]
+	InvalidShare(usize),
+}
+
+/// Hash of the share set, committed in the pre-digest so that the payout split can be audited.
+///
+/// It is recomputed over the shares of the rewards inherent of a block to be checked against the
+/// pre-digest.
+pub fn shares_hash<AccountId: Encode, Share: Encode>(
+	shares: &InherentType<AccountId, Share>,
+) -> sp_core::H256 {
+	BlakeTwo256::hash_of(shares)
+}
+
+/// Reads the shares of each block from a pool, falling back to the author if there is none.
+pub struct PoolShares<AccountId, Share> {
+	source: ShareSource,
+	accounting: Accounting,
+	max_splits: usize,
+	author: AccountId,
+	last: Mutex<Option<InherentType<AccountId, Share>>>,
+}
+
+impl<AccountId, Share> PoolShares<AccountId, Share>
+where
+	AccountId: Clone + Ord + Encode + Decode + Send + 'static,
+	Share: Copy + Ord + Zero + One + Saturating + Encode + FromStr + Send + 'static,
+{
+	/// Create pool shares keeping the `max_splits` largest shares, which should not exceed the
+	/// `MaxRewardSplits` of the runtime.
+	pub fn new(
+		source: ShareSource,
+		accounting: Accounting,
+		max_splits: usize,
+		author: AccountId,
+	) -> Self {
+		Self { source, accounting, max_splits, author, last: Mutex::new(None) }
+	}
+
+	/// Read the shares of the next block and create the inherent data provider for them.
+	///
+	/// The shares are read on a blocking thread of the tokio runtime.
+	pub async fn inherent_data_provider(&self) -> PoolInherentDataProvider<AccountId, Share> {
+		let (source, accounting, max_splits) =
+			(self.source.clone(), self.accounting, self.max_splits);
+		let shares = tokio::task::spawn_blocking(move || read(&source, accounting, max_splits))
+			.await
+			.unwrap_or_else(|e| Err(std::io::Error::from(e).into()))
+			.inspect_err(|e| log::warn!(target: LOG_TARGET, "Unable to read pool shares: {}", e))
+			.ok()
+			.filter(|shares| !shares.is_empty())
+			.unwrap_or_else(|| InherentType::from([(self.author.clone(), Share::one())]));
+
+		*self.last.lock() = Some(shares.clone());
+		PoolInherentDataProvider { shares }
+	}
+
+	/// Hash of the shares last provided.
+	pub fn shares_hash(&self) -> Option<sp_core::H256> {
+		self.last.lock().as_ref().map(shares_hash)
+	}
+}
+
+fn read<AccountId, Share>(
+	source: &ShareSource,
+	accounting: Accounting,
+	max_splits: usize,
+) -> Result<InherentType<AccountId, Share>, Error>
+where
+	AccountId: Clone + Ord + Decode,
+	Share: Copy + Ord + Zero + Saturating + FromStr,
+{
+	let text = match source {
+		ShareSource::File(path) => std::fs::read_to_string(path)?,
+		#[cfg(unix)]
+		ShareSource::Socket(path) => {
+			let mut stream = UnixStream::connect(path)?;
+			stream.set_read_timeout(Some(SOCKET_TIMEOUT))?;
+			let mut text = String::new();
+			stream.read_to_string(&mut text)?;
+			text
+		},
+	};
+
+	Ok(account(parse(&text)?, accounting, max_splits))
+}
+
+enum Entry<AccountId, Share> {
+	Share(AccountId, Share),
+	Block,
+}
+
+fn parse<AccountId: Decode, Share: FromStr>(
+	text: &str,
+) -> Result<Vec<Entry<AccountId, Share>>, Error> {
+	text.lines()
+		.enumerate()
+		.map(|(i, line)| (i + 1, line.split('#').next().unwrap_or_default().trim()))
+		.filter(|(_, line)| !line.is_empty())
+		.map(|(number, line)| {
+			if line == "block" {
+				return Ok(Entry::Block)
+			}
+
+			let mut fields = line.split_whitespace();
+			let (Some(account), Some(weight), None) = (fields.next(), fields.next(), fields.next())
+			else {
+				return Err(Error::InvalidShare(number))
+			};
+			let entry = hex::decode(account.trim_start_matches("0x")).ok().and_then(|account| {
+				Some(Entry::Share(AccountId::decode(&mut &account[..]).ok()?, weight.parse().ok()?))
+			});
+			entry.ok_or(Error::InvalidShare(number))
+		})
+		.collect()
+}
+
+fn account<AccountId, Share>(
+	entries: Vec<Entry<AccountId, Share>>,
+	accounting: Accounting,
+	max_splits: usize,
+) -> InherentType<AccountId, Share>
+where
+	AccountId: Clone + Ord,
+	Share: Copy + Ord + Zero + Saturating,
+{
+	let counted = entries.iter().rev().filter_map(|entry| match entry {
+		Entry::Share(account, weight) => Some(Some((account, *weight))),
+		Entry::Block => (accounting == Accounting::Proportional).then_some(None),
+	});
+	let window = match accounting {
+		Accounting::Proportional => usize::MAX,
+		Accounting::Pplns(n) => n,
+	};
+
+	let mut shares = InherentType::<AccountId, Share>::new();
+	for (account, weight) in counted.map_while(|share| share).take(window) {
+		let share = shares.entry(account.clone()).or_insert_with(Share::zero);
+		*share = share.saturating_add(weight);
+	}
+	shares.retain(|_, share| !share.is_zero());
+
+	if shares.len() > max_splits {
+		let mut largest = shares.into_iter().collect::<Vec<_>>();
+		largest.sort_by(|(_, a), (_, b)| b.cmp(a));
+		largest.truncate(max_splits);
+		shares = largest.into_iter().collect();
+	}
+
+	shares
+}
+
+/// Inherent data provider of the shares read by [`PoolShares`].
+pub struct PoolInherentDataProvider<AccountId, Share> {
+	pub shares: InherentType<AccountId, Share>,
+}
+
+#[async_trait::async_trait]
+impl<AccountId, Share> sp_inherents::InherentDataProvider
+	for PoolInherentDataProvider<AccountId, Share>
+where
+	AccountId: Encode + Send + Sync,
+	Share: Encode + Send + Sync,
+{
+	async fn provide_inherent_data(
+		&self,
+		inherent_data: &mut InherentData,
+	) -> Result<(), sp_inherents::Error> {
+		inherent_data.put_data(INHERENT_IDENTIFIER, &self.shares)
+	}
+
+	async fn try_handle_error(
+		&self,
+		_identifier: &InherentIdentifier,
+		_error: &[u8],
+	) -> Option<Result<(), sp_inherents::Error>> {
+		None
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_inherents::InherentDataProvider;
+
+	const SHARES: &str = "
+		# round 1
+		01 5
+		02 3
+		block
+		# round 2
+		02 1 # late share
+		03 2
+		0x01 4
+	";
+
+	#[test]
+	fn parse_rejects_invalid_shares() {
+		assert!(matches!(parse::<u8, u32>("01 1\n01"), Err(Error::InvalidShare(2))));
+		assert!(matches!(parse::<u8, u32>("zz 1"), Err(Error::InvalidShare(1))));
+		assert!(matches!(parse::<u8, u32>("01 1 1"), Err(Error::InvalidShare(1))));
+	}
+
+	#[test]
+	fn proportional_accounting_works() {
+		let entries = parse::<u8, u32>(SHARES).unwrap();
+		let shares = account(entries, Accounting::Proportional, 10);
+		assert_eq!(shares, InherentType::from([(1, 4), (2, 1), (3, 2)]));
+	}
+
+	#[test]
+	fn pplns_accounting_works() {
+		let entries = parse::<u8, u32>(SHARES).unwrap();
+		let shares = account(entries, Accounting::Pplns(4), 10);
+		assert_eq!(shares, InherentType::from([(1, 4), (2, 4), (3, 2)]));
+
+		let entries = parse::<u8, u32>(SHARES).unwrap();
+		let shares = account(entries, Accounting::Pplns(4), 2);
+		assert_eq!(shares, InherentType::from([(1, 4), (2, 4)]));
+	}
+
+	#[tokio::test]
+	async fn shares_hash_is_recomputed_from_the_inherent() {
+		let path = std::env::temp_dir().join(format!("np-rewards-shares-{}", std::process::id()));
+		std::fs::write(&path, SHARES).unwrap();
+		let pool = PoolShares::<u8, u32>::new(
+			ShareSource::File(path.clone()),
+			Accounting::Proportional,
+			10,
+			9,
+		);
+
+		let mut inherent_data = InherentData::new();
+		pool.inherent_data_provider()
+			.await
+			.provide_inherent_data(&mut inherent_data)
+			.await
+			.unwrap();
+		let shares = inherent_data
+			.get_data::<InherentType<u8, u32>>(&INHERENT_IDENTIFIER)
+			.unwrap()
+			.unwrap();
+		assert_eq!(shares, InherentType::from([(1, 4), (2, 1), (3, 2)]));
+		assert_eq!(pool.shares_hash(), Some(shares_hash(&shares)));
+
+		std::fs::remove_file(&path).unwrap();
+		let shares = pool.inherent_data_provider().await.shares;
+		assert_eq!(shares, InherentType::from([(9, 1)]));
+		assert_eq!(pool.shares_hash(), Some(shares_hash(&shares)));
+	}
+}
@@ -187,6 +187,21 @@ where
 	}
 }
 
+impl<T, E> TryFrom<AccountId32<T>> for ed25519::Public
+where
+	T: TryInto<ed25519::Public, Error = E>,
+	E: Default,
+{
+	type Error = E;
+
+	fn try_from(account: AccountId32<T>) -> Result<Self, Self::Error> {
+		match account.1 {
+			Some(key) => key.try_into(),
+			None => Err(Default::default()),
+		}
+	}
+}
+
 impl<T> Property for AccountId32<T> {
 	type Value = Option<T>;
 